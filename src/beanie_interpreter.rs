@@ -1,110 +1,416 @@
 use std::collections::HashMap;
+use regex::Regex;
 use tree_sitter_beanie::data::context::BeanieParsingContext;
-use tree_sitter_beanie::data::expression::data_type::DataType;
 use tree_sitter_beanie::data::expression::instruction_expression::InstructionExpression;
 use tree_sitter_beanie::data::instructions::types::OperationType;
 use crate::data::context::BeanieRuntimeContext;
 use crate::data::expression::BeanieExpression;
 use crate::data::function::Function;
+use crate::data::number::NumberType;
 use crate::data::operations::graph_operation::GraphOperation;
 use crate::data::operations::in_operation::InOperation;
 use crate::data::operations::Operation;
 use crate::data::operations::out_operation::OutOperation;
 use crate::data::operations::print_operation::PrintOperation;
 use crate::data::operations::use_operation::UseOperation;
-use crate::utilities::logger;
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::operations;
+use crate::data::span::{SourceLocator, Span};
 
-pub fn run(bn_file_path: String, bn_file: String, parameters: Vec<String>, default_data_type: DataType) {
-    interpret(parse(bn_file_path, bn_file, default_data_type), parameters);
+/// Starts the line a file can open with to choose the number type of its math, as in
+/// `#type rational` or `#type float:256`, in place of the default data type. A file used by another
+/// keeps its own. The same line before a definition further down chooses the type of that
+/// definition alone.
+const TYPE_PRAGMA: &str = "#type";
+
+/// The number types a file chooses for its math with [`TYPE_PRAGMA`] lines.
+pub struct TypePragmas {
+    /// The type of the math of the file, which the grammar parses it as the data type of.
+    pub file: NumberType,
+    /// The types of the definitions written on the line after a pragma of their own, by the index
+    /// of that line.
+    definitions: HashMap<usize, NumberType>,
+}
+
+impl TypePragmas {
+    /// A file of `file` math without definitions of types of their own.
+    pub fn new(file: NumberType) -> TypePragmas {
+        TypePragmas {
+            file,
+            definitions: HashMap::new(),
+        }
+    }
+
+    /// Gives `expression` the type of the definition starting on the line of `span`, else that of
+    /// the file.
+    fn typed(&self, expression: BeanieExpression, span: Option<&Span>) -> BeanieExpression {
+        let number_type = span.and_then(|span| self.definitions.get(&(span.line - 1)));
+        expression.typed(number_type.copied().unwrap_or(self.file), &self.file.data_type())
+    }
+}
+
+pub fn run(bn_file_path: String, bn_file: String, parameters: Vec<String>, default_data_type: NumberType) -> BeanieResult<()> {
+    interpret(parse(bn_file_path, bn_file, default_data_type)?, parameters)
+}
+
+pub fn eval(expression: String, uses: Vec<String>, default_data_type: NumberType) -> BeanieResult<String> {
+    let mut bn_file = String::new();
+    for file_path in uses {
+        bn_file += format!("use {}\n", file_path).as_str();
+    }
+    bn_file += format!("out {}", expression).as_str();
+
+    let (mut context, instructions) = parse(String::from("<eval>"), bn_file, default_data_type)?;
+    let output = match context.output.clone() {
+        Some(output) => output,
+        None => return Err(BeanieError::Parse(format!("{} is not an expression", expression))),
+    };
+    
+    // only the use instructions are executed, out would otherwise try writing to a file
+    let mut threads_to_wait = Vec::new();
+    for instruction in instructions.iter().take(instructions.len() - 1) {
+        operations::execute(instruction.as_ref(), &mut context, &HashMap::new(), &mut threads_to_wait)?;
+    }
+    operations::join_all(threads_to_wait)?;
+    
+    output.evaluate_to_string(&context)
+}
+
+pub fn graph(bn_file_path: String, bn_file: String, parameters: Vec<String>, default_data_type: NumberType, function_name: String, arguments: Vec<(String, BeanieExpression)>) -> BeanieResult<()> {
+    let (context, mut instructions) = parse(bn_file_path, bn_file, default_data_type)?;
+    
+    let mut graph_operation = GraphOperation::new(function_name, None);
+    for (name, argument) in arguments {
+        graph_operation.add_argument(name, argument)?;
+    }
+    instructions.push(Box::new(graph_operation));
+    
+    interpret((context, instructions), parameters)
 }
 
-pub fn parse(bn_file_path: String, bn_file: String, default_data_type: DataType) -> (BeanieRuntimeContext, Vec<Box<dyn Operation>>) {
-    contextualize(bn_file_path, tree_sitter_beanie::parse(&bn_file, default_data_type, None).unwrap())
+pub fn parse(bn_file_path: String, bn_file: String, default_data_type: NumberType) -> BeanieResult<(BeanieRuntimeContext, Vec<Box<dyn Operation>>)> {
+    let (bn_file, pragmas) = type_pragmas(&bn_file, default_data_type).map_err(|err| err.within(bn_file_path.as_str()))?;
+    let original = tree_sitter_beanie::parse(&bn_file, pragmas.file.data_type(), None)
+        .map_err(|err| BeanieError::Parse(format!("Failed to parse {}. Error: {}", bn_file_path, err)))?;
+
+    contextualize(bn_file_path, &bn_file, original, &pragmas)
 }
 
-fn contextualize(bn_file_path: String, original: BeanieParsingContext) -> (BeanieRuntimeContext, Vec<Box<dyn Operation>>) {
-    let mut context = BeanieRuntimeContext {
-        beanie_file_path: bn_file_path,
-        constants: HashMap::new(),
-        functions: HashMap::new(),
-        inputs: Vec::new(),
-        output: None,
+/// Validates a file without running it and returns every problem found, an empty list meaning the
+/// file is fine.
+pub fn check(bn_file_path: String, bn_file: String, default_data_type: NumberType) -> Vec<BeanieError> {
+    let (bn_file, pragmas) = match type_pragmas(&bn_file, default_data_type) {
+        Ok(pragmas) => pragmas,
+        Err(err) => return vec![err],
+    };
+    let original = match tree_sitter_beanie::parse(&bn_file, pragmas.file.data_type(), None) {
+        Ok(original) => original,
+        Err(err) => return vec![BeanieError::Parse(format!("Failed to parse. Error: {}", err))],
     };
 
+    let mut problems = Vec::new();
+    let locator = SourceLocator::new(&bn_file_path, &bn_file);
+    let (mut context, instructions) = contextualize_reporting(bn_file_path, &bn_file, original, &pragmas, &mut problems);
+
+    // instructions first, so functions brought in through use are known when checking the rest
+    for instruction in &instructions {
+        let mut instruction_problems = Vec::new();
+        instruction.check(&mut context, &mut instruction_problems);
+        problems.extend(instruction_problems.into_iter().map(|problem| problem.at(instruction.span())));
+    }
+
+    let mut constants: Vec<_> = context.constants.iter().collect();
+    constants.sort_by(|a, b| a.0.cmp(b.0));
+    for (names, expression) in constants {
+        let mut constant_problems = Vec::new();
+        expression.check(&context, &mut constant_problems);
+
+        let span = locator.definition(&names[0]);
+        problems.extend(constant_problems.into_iter().map(|problem| problem.at(span.as_ref()).within(format!("In constant {}", names.join(", ")).as_str())));
+    }
+
+    let mut functions: Vec<_> = context.functions.iter().filter(|(_, function)| function.external_context.is_none()).collect();
+    functions.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, function) in functions {
+        let mut function_problems = Vec::new();
+        function.expression.check(&context, &mut function_problems);
+        problems.extend(function_problems.into_iter().map(|problem| problem.at(function.span.as_ref()).within(format!("In function {}", name).as_str())));
+    }
+
+    problems
+}
+
+/// Turns what the parser produced from `bn_file` into a runtime context and the operations to run,
+/// its math being of the types of `pragmas`.
+pub fn contextualize(bn_file_path: String, bn_file: &str, original: BeanieParsingContext, pragmas: &TypePragmas) -> BeanieResult<(BeanieRuntimeContext, Vec<Box<dyn Operation>>)> {
+    let mut problems = Vec::new();
+    let contextualized = contextualize_reporting(bn_file_path, bn_file, original, pragmas, &mut problems);
+
+    match problems.into_iter().next() {
+        Some(problem) => Err(problem),
+        None => Ok(contextualized),
+    }
+}
+
+/// Like [`contextualize`], but collects malformed instructions and arguments into `problems`
+/// instead of stopping at the first one.
+fn contextualize_reporting(bn_file_path: String, bn_file: &str, original: BeanieParsingContext, pragmas: &TypePragmas, problems: &mut Vec<BeanieError>) -> (BeanieRuntimeContext, Vec<Box<dyn Operation>>) {
+    let mut locator = SourceLocator::new(&bn_file_path, bn_file);
+    let mut context = BeanieRuntimeContext::new(bn_file_path);
+    context.number_type = pragmas.file;
+
     let mut instructions: Vec<Box<dyn Operation>> = Vec::new();
 
     for instruction in original.instructions {
+        let span = locator.instruction(keyword(&instruction.operation_type));
 
         match instruction.operation_type {
             OperationType::Use => {
                 if let InstructionExpression::FilePath(file_path) = instruction.expression {
-                    instructions.push(Box::new(UseOperation::new(file_path)));
+                    instructions.push(Box::new(UseOperation::new(file_path, span.clone())));
                 } else {
-                    logger::log_error("Use operation can be only followed by a file path expression");
+                    problems.push(BeanieError::Parse(String::from("Use operation can be only followed by a file path expression")).at(span.as_ref()));
                 }
             }
             OperationType::In => {
                 // todo: make in instruction only take in an identifier instead of a math expression
                 if let InstructionExpression::Math(expr_sig) = instruction.expression {
-                    instructions.push(Box::new(InOperation::new(expr_sig.evaluation.clone())));
+                    instructions.push(Box::new(InOperation::new(expr_sig.evaluation.clone(), pragmas.file.within(&expr_sig.data_type, &pragmas.file.data_type()), span.clone())));
                     context.inputs.push(expr_sig.evaluation);
                 }
                 else {
-                    logger::log_error("In operation can be only followed by an identifier");
+                    problems.push(BeanieError::Parse(String::from("In operation can be only followed by an identifier")).at(span.as_ref()));
                 }
             }
             OperationType::Out => {
                 if let InstructionExpression::Math(expr_sig) = instruction.expression {
-                    instructions.push(Box::new(OutOperation::new(BeanieExpression::from(expr_sig.clone()))));
-                    context.output = Some(BeanieExpression::from(expr_sig));
+                    let expression = pragmas.typed(BeanieExpression::from(expr_sig), None).located(span.clone());
+                    problems.extend(expression.syntax_error());
+                    instructions.push(Box::new(OutOperation::new(expression.clone(), span.clone())));
+                    context.output = Some(expression);
                 }
                 else {
-                    logger::log_error("Out operation can be only followed by a math expression");
+                    problems.push(BeanieError::Parse(String::from("Out operation can be only followed by a math expression")).at(span.as_ref()));
                 }
             }
             OperationType::Graph => {
                 if let InstructionExpression::Math(expr_sig) = instruction.expression {
-                    instructions.push(Box::new(GraphOperation::new(expr_sig.evaluation)))
+                    instructions.push(Box::new(GraphOperation::new(expr_sig.evaluation, span.clone())))
                 }
                 else {
-                    logger::log_error("Graph operation can be only followed by an identifier");
+                    problems.push(BeanieError::Parse(String::from("Graph operation can be only followed by an identifier")).at(span.as_ref()));
                 }
             }
             OperationType::Print => {
                 if let InstructionExpression::Math(expr_sig) = instruction.expression {
-                    instructions.push(Box::new(PrintOperation::new(BeanieExpression::from(expr_sig))))
+                    let expression = pragmas.typed(BeanieExpression::from(expr_sig), None).located(span.clone());
+                    problems.extend(expression.syntax_error());
+                    instructions.push(Box::new(PrintOperation::new(expression, span.clone())))
                 }
                 else {
-                    logger::log_error("Out operation can be only followed by a math expression");
+                    problems.push(BeanieError::Parse(String::from("Print operation can be only followed by a math expression")).at(span.as_ref()));
                 } 
             }
         }
 
         for (name, argument) in instruction.arguments {
-            if let Some(ins) = instructions.last_mut() { ins.add_argument(name, BeanieExpression::from(argument)) }
+            if let Some(ins) = instructions.last_mut() {
+                let argument = pragmas.typed(BeanieExpression::from(argument), None).located(span.clone());
+                problems.extend(argument.syntax_error());
+
+                if let Err(problem) = ins.add_argument(name, argument) {
+                    problems.push(problem.at(span.as_ref()));
+                }
+            }
         }
     }
 
-    for constant in original.constants {
-        context.constants.insert(constant.0, BeanieExpression::from(constant.1));
+    for (names, signature) in original.constants {
+        let span = locator.definition_body(&names[0]);
+        let expression = pragmas.typed(BeanieExpression::from(signature), locator.definition(&names[0]).as_ref()).located(span);
+
+        problems.extend(expression.syntax_error());
+        context.insert_constant(names, expression);
+    }
+    for (name, signature) in original.functions {
+        let mut function = Function::from(signature);
+
+        function.name = name.clone();
+        function.span = locator.definition(&name);
+        function.expression = pragmas.typed(function.expression, function.span.as_ref()).located(locator.definition_body(&name));
+        problems.extend(function.expression.syntax_error());
+        context.insert_function(name, function);
     }
-    for function in original.functions {
-        context.functions.insert(function.0, Function::from(function.1));
+
+    if let Err(cycle) = context.dependency_graph().check_cycles() {
+        problems.push(cycle);
     }
 
     (context, instructions)
 }
 
-fn interpret(context: (BeanieRuntimeContext, Vec<Box<dyn Operation>>), parameters: Vec<String>) {
+/// The number types `bn_file` chooses with [`TYPE_PRAGMA`] lines, and the file with those lines
+/// blanked out so the rest keeps its lines. A pragma before anything else chooses the type of the
+/// file, which is `default_data_type` without one, and any other the type of the definition on the
+/// next line that is not empty.
+fn type_pragmas(bn_file: &str, default_data_type: NumberType) -> BeanieResult<(String, TypePragmas)> {
+    let first_line = bn_file.lines().position(|line| !line.trim().is_empty());
+    let mut pragmas = TypePragmas::new(default_data_type);
+    let mut annotation = None;
+    let mut lines = Vec::new();
+
+    for (index, line) in bn_file.lines().enumerate() {
+        let number_type = line.trim()
+            .strip_prefix(TYPE_PRAGMA)
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map(str::trim);
+
+        match number_type {
+            Some(number_type) => {
+                let number_type = number_type.parse::<NumberType>()
+                    .map_err(|err| BeanieError::Parse(format!("{}, in {} {}", err, TYPE_PRAGMA, number_type)))?;
+
+                if Some(index) == first_line {
+                    pragmas.file = number_type;
+                } else {
+                    annotation = Some(number_type);
+                }
+                lines.push("");
+            }
+            None => {
+                if !line.trim().is_empty() {
+                    if let Some(number_type) = annotation.take() {
+                        pragmas.definitions.insert(index, number_type);
+                    }
+                }
+                lines.push(line);
+            }
+        }
+    }
+
+    Ok((lines.join("\n"), pragmas))
+}
+
+fn keyword(operation_type: &OperationType) -> &'static str {
+    match operation_type {
+        OperationType::Use => "use",
+        OperationType::In => "in",
+        OperationType::Out => "out",
+        OperationType::Graph => "graph",
+        OperationType::Print => "print",
+    }
+}
+
+/// Maps command line parameters onto the `in` declarations of a file. A parameter is either
+/// `name=value` or a bare value, bare values filling the inputs not given by name in declaration
+/// order.
+pub fn bind_inputs(inputs: &[String], parameters: &[String]) -> BeanieResult<HashMap<String, String>> {
+    let named_pattern = Regex::new(r"^([a-zA-Z][a-zA-Z0-9_]*)=(.*)$").unwrap();
+    let expected = || inputs.join(", ");
+
+    let mut bound = HashMap::new();
+    let mut positional = Vec::new();
+
+    for parameter in parameters {
+        match named_pattern.captures(parameter) {
+            Some(captures) => {
+                let name = captures[1].to_string();
+
+                if !inputs.contains(&name) {
+                    return Err(BeanieError::Input(format!("Unknown input {}. Expected inputs: {}", name, expected())));
+                }
+                if bound.insert(name.clone(), captures[2].to_string()).is_some() {
+                    return Err(BeanieError::Input(format!("Input {} is given more than once", name)));
+                }
+            }
+            None => positional.push(parameter.clone()),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    for input in inputs {
+        if bound.contains_key(input) { continue; }
+        match positional.next() {
+            Some(value) => { bound.insert(input.clone(), value); }
+            None => break,
+        }
+    }
+
+    if positional.next().is_some() {
+        return Err(BeanieError::Input(format!("Too many inputs given. Expected inputs: {}", expected())));
+    }
+
+    let missing: Vec<&str> = inputs.iter().filter(|input| !bound.contains_key(*input)).map(|s| s.as_str()).collect();
+    if !missing.is_empty() {
+        return Err(BeanieError::Input(format!("Missing inputs {}. Expected inputs: {}", missing.join(", "), expected())));
+    }
+
+    Ok(bound)
+}
+
+fn interpret(context: (BeanieRuntimeContext, Vec<Box<dyn Operation>>), parameters: Vec<String>) -> BeanieResult<()> {
+    run_parsed(&context, &parameters).map(|_| ())
+}
+
+/// Runs already parsed instructions against a copy of their context, so a file parsed once can be
+/// run for many sets of inputs. Returns the context as the instructions left it.
+pub fn run_parsed(parsed: &(BeanieRuntimeContext, Vec<Box<dyn Operation>>), parameters: &[String]) -> BeanieResult<BeanieRuntimeContext> {
+    execute_parsed(parsed, parameters, |_| true)
+}
+
+/// Like [`run_parsed`], but executes only the instructions the output is evaluated from, leaving out
+/// those printing, graphing or writing results, for runs that evaluate the output themselves such
+/// as one per row of a batch.
+pub fn prepare_parsed(parsed: &(BeanieRuntimeContext, Vec<Box<dyn Operation>>), parameters: &[String]) -> BeanieResult<BeanieRuntimeContext> {
+    execute_parsed(parsed, parameters, |instruction| instruction.prepares_output())
+}
+
+fn execute_parsed(parsed: &(BeanieRuntimeContext, Vec<Box<dyn Operation>>), parameters: &[String], executed: fn(&dyn Operation) -> bool) -> BeanieResult<BeanieRuntimeContext> {
     let mut threads_to_wait = Vec::new();
-    let instructions = context.1;
-    let mut context = context.0;
+    let mut context = parsed.0.clone();
+    let inputs = bind_inputs(&context.inputs, parameters)?;
 
-    for instruction in instructions {
-        instruction.execute(&mut context, &parameters, &mut threads_to_wait);
+    for instruction in parsed.1.iter().filter(|instruction| executed(instruction.as_ref())) {
+        if let Err(err) = operations::execute(instruction.as_ref(), &mut context, &inputs, &mut threads_to_wait) {
+            // let graphs that were already started finish before reporting
+            let _ = operations::join_all(threads_to_wait);
+            return Err(err);
+        }
     }
 
-    for thread in threads_to_wait {
-        thread.join().unwrap();
+    operations::join_all(threads_to_wait)?;
+    Ok(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs() -> Vec<String> {
+        vec![String::from("a"), String::from("b"), String::from("c")]
+    }
+
+    fn bind(parameters: &[&str]) -> BeanieResult<HashMap<String, String>> {
+        bind_inputs(&inputs(), &parameters.iter().map(|parameter| parameter.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn inputs_are_bound_by_name_then_by_position() {
+        let bound = bind(&["c=3", "1", "2"]).unwrap();
+        assert_eq!(bound, HashMap::from([
+            (String::from("a"), String::from("1")),
+            (String::from("b"), String::from("2")),
+            (String::from("c"), String::from("3")),
+        ]));
+
+        // only the first = separates the name from the value
+        assert_eq!(bind(&["a=x==1", "b=2", "c=3"]).unwrap()["a"], "x==1");
+    }
+
+    #[test]
+    fn inputs_that_do_not_match_the_file_are_rejected() {
+        for parameters in [&["d=1", "2", "3"][..], &["a=1", "a=2", "3"], &["1", "2", "3", "4"], &["1", "2"]] {
+            assert!(matches!(bind(parameters), Err(BeanieError::Input(_))), "{:?} bound", parameters);
+        }
     }
 }
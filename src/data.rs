@@ -2,3 +2,11 @@ pub mod expression;
 pub mod operations;
 pub mod function;
 pub mod context;
+pub mod error;
+pub mod span;
+pub mod dependency_graph;
+pub mod matrix;
+pub mod number;
+pub mod value;
+pub mod unit;
+pub mod measured;
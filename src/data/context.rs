@@ -1,17 +1,66 @@
+use std::any::Any;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
+use mexprp::Context;
+use mexprp::num::{ComplexFloat, ComplexRugRat};
+use rug::{Complex, Rational};
+use crate::data::dependency_graph::{Definition, DependencyGraph};
+use crate::data::error::{BeanieError, BeanieResult, Frame};
+use crate::data::expression::evaluator::{self, promotion};
 use crate::data::expression::BeanieExpression;
 use crate::data::function::Function;
+use crate::data::number::big_float::BigFloat;
+use crate::data::number::big_integer::BigInteger;
+use crate::data::number::interval::Interval;
+use crate::data::number::{BeanieNum, NumberType};
+use crate::data::value::Value;
+
+/// Values of evaluated constants by the names they are assigned to and the number type they were
+/// evaluated as.
+type ConstantValues = HashMap<(Vec<String>, NumberType), Rc<dyn Any>>;
+
+/// Values of memoized function calls by the file the function is written in, its name, its
+/// arguments and the number type.
+type CallValues = HashMap<(String, String, Vec<String>, NumberType), Rc<dyn Any>>;
 
 #[derive(Debug, Clone)]
 pub struct BeanieRuntimeContext {
     pub beanie_file_path: String,
+    /// Changed through [`Self::insert_constant`] and [`Self::remove_constant`], which drop the
+    /// values cached from the old definitions.
     pub constants: HashMap<Vec<String>, BeanieExpression>,
+    /// Changed through [`Self::insert_function`], for the same reason as the constants.
     pub functions: HashMap<String, Function>,
     pub inputs: Vec<String>,
     pub output: Option<BeanieExpression>,
+    /// The number type of the file, which the files it uses are parsed as unless they choose their
+    /// own with a `#type` pragma.
+    pub number_type: NumberType,
+    dependency_graph: RefCell<Option<Rc<DependencyGraph>>>,
+    /// Values of the constants evaluated so far.
+    constant_values: RefCell<ConstantValues>,
+    call_values: RefCell<CallValues>,
+    /// Number types of the constants and functions found so far.
+    number_types: RefCell<HashMap<Definition, Option<NumberType>>>,
 }
 
 impl BeanieRuntimeContext {
+    pub fn new(beanie_file_path: String) -> BeanieRuntimeContext {
+        BeanieRuntimeContext {
+            beanie_file_path,
+            constants: HashMap::new(),
+            functions: HashMap::new(),
+            inputs: Vec::new(),
+            output: None,
+            number_type: NumberType::Decimal,
+            dependency_graph: RefCell::new(None),
+            constant_values: RefCell::new(HashMap::new()),
+            call_values: RefCell::new(HashMap::new()),
+            number_types: RefCell::new(HashMap::new()),
+        }
+    }
+
     pub fn has_constant(&self, name: &str) -> bool {
         for key in self.constants.keys() {
             if key.iter().any(|s| s == name) {
@@ -21,21 +70,189 @@ impl BeanieRuntimeContext {
         false
     }
 
+    pub fn insert_constant(&mut self, names: Vec<String>, expression: BeanieExpression) {
+        self.constants.insert(names, expression);
+        self.forget_values();
+    }
+
+    /// Declares the inputs of a used file as constants, which [`Self::bind_inputs`] gives the
+    /// arguments of each call of its output as values.
+    pub fn declare_inputs(&mut self) {
+        for input in self.inputs.clone() {
+            self.insert_constant(vec![input.clone()], BeanieExpression::String(input));
+        }
+    }
+
+    /// Binds the inputs of a used file to the arguments of a call of its output, computed with
+    /// `builtins`. Only the values of the constants and memoized calls depending on the inputs are
+    /// forgotten, so the others are still evaluated once for every call.
+    pub fn bind_inputs<N: BeanieNum>(&self, bindings: Vec<(String, Value<N>)>, builtins: &Context<N>) {
+        let number_type = N::number_type(builtins);
+        let bound: Vec<Definition> = bindings.iter().map(|(name, _)| Definition::Constant(vec![name.clone()])).collect();
+        let mut forgotten = self.dependency_graph().dependents(&bound);
+        forgotten.extend(bound);
+
+        self.constant_values.borrow_mut().retain(|(names, _), _| !forgotten.contains(&Definition::Constant(names.clone())));
+        self.number_types.borrow_mut().retain(|definition, _| !forgotten.contains(definition));
+        self.call_values.borrow_mut().retain(|(file_path, function, _, _), _| {
+            *file_path != self.beanie_file_path || !forgotten.contains(&Definition::Function(function.clone()))
+        });
+
+        for (name, value) in bindings {
+            self.number_types.borrow_mut().insert(Definition::Constant(vec![name.clone()]), Some(number_type));
+            self.constant_values.borrow_mut().insert((vec![name], number_type), Rc::new(value));
+        }
+    }
+
+    pub fn remove_constant(&mut self, name: &str) {
+        self.constants.retain(|key, _| !key.iter().any(|s| s == name));
+        self.forget_values();
+    }
+
     pub fn has_function(&self, name: &str) -> bool {
         self.functions.contains_key(name)
     }
 
-    pub fn get_constant(&self, name: &str) -> Option<(BeanieExpression, usize)> {
+    pub fn insert_function(&mut self, name: String, function: Function) {
+        self.functions.insert(name, function);
+        self.forget_values();
+    }
+
+    /// Returns the expression assigned to `name` and the position of `name` among the names it is
+    /// assigned to.
+    pub fn get_constant(&self, name: &str) -> Option<(&BeanieExpression, usize)> {
+        self.get_constant_names(name).map(|(names, index)| (&self.constants[names], index))
+    }
+
+    /// Returns every name assigned together with `name`, and the position of `name` among them.
+    pub fn get_constant_names(&self, name: &str) -> Option<(&Vec<String>, usize)> {
         for key in self.constants.keys() {
             let mut iterator = key.iter();
-            if let Some(index) = iterator.position(|s| s == name) { return Some((self.constants[key].clone(), index)) }
+            if let Some(index) = iterator.position(|s| s == name) { return Some((key, index)) }
         }
 
         None
     }
 
-    pub fn get_function(&self, name: &str) -> Option<Function> {
-        if !self.has_function(name) { return None; }
-        Some(self.functions[name].clone())
+    pub fn get_function(&self, name: &str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    pub fn dependency_graph(&self) -> Rc<DependencyGraph> {
+        self.dependency_graph.borrow_mut()
+            .get_or_insert_with(|| Rc::new(DependencyGraph::build(self)))
+            .clone()
+    }
+
+    /// Evaluates the constant assigned to `names` as an `N` with `builtins`, evaluating each
+    /// constant it depends on first. Every value is kept, so a constant is evaluated once however
+    /// often it is used.
+    pub fn evaluate_constant<N: BeanieNum>(&self, names: &[String], builtins: &Context<N>) -> BeanieResult<Value<N>> {
+        let number_type = N::number_type(builtins);
+        if let Some(value) = self.constant_value::<N>(names, number_type) {
+            return Ok(value);
+        }
+
+        for dependency in self.dependency_graph().evaluation_order(names)? {
+            if self.constant_value::<N>(&dependency, number_type).is_some() { continue; }
+
+            let value = self.evaluate_promoted(&dependency, builtins)?;
+            self.constant_values.borrow_mut().insert((dependency, number_type), Rc::new(value));
+        }
+
+        self.constant_value::<N>(names, number_type)
+            .ok_or_else(|| BeanieError::Resolution(format!("Failed to find constant {}", names.join(", "))))
+    }
+
+    /// Evaluates the constant assigned to `names` as its own number type, then converts the value
+    /// to `N`, so a rational constant is exact and an integer one divides as integers do even when
+    /// used by a decimal expression.
+    fn evaluate_promoted<N: BeanieNum>(&self, names: &[String], builtins: &Context<N>) -> BeanieResult<Value<N>> {
+        let number_type = match self.constant_type(names)? {
+            Some(number_type) if number_type != N::number_type(builtins) => number_type,
+            _ => {
+                let frame = Frame {
+                    file_path: self.beanie_file_path.clone(),
+                    name: names.join(", "),
+                    arguments: None,
+                };
+                return self.constants[names].evaluate(self, builtins).map_err(|err| err.called_from(frame));
+            }
+        };
+
+        let precision = number_type.precision();
+        match number_type {
+            NumberType::Integer => self.evaluate_constant(names, &evaluator::builtins::<BigInteger>(precision))?.convert(builtins),
+            NumberType::Rational => self.evaluate_constant(names, &evaluator::builtins::<Rational>(precision))?.convert(builtins),
+            NumberType::Decimal => self.evaluate_constant(names, &evaluator::builtins::<f64>(precision))?.convert(builtins),
+            NumberType::Interval(_) => self.evaluate_constant(names, &evaluator::builtins::<Interval>(precision))?.convert(builtins),
+            NumberType::Float(_) => self.evaluate_constant(names, &evaluator::builtins::<BigFloat>(precision))?.convert(builtins),
+            NumberType::ComplexRational => self.evaluate_constant(names, &evaluator::builtins::<ComplexRugRat>(precision))?.convert(builtins),
+            NumberType::ImaginaryDecimal => self.evaluate_constant(names, &evaluator::builtins::<ComplexFloat>(precision))?.convert(builtins),
+            NumberType::Complex => self.evaluate_constant(names, &evaluator::builtins::<Complex>(precision))?.convert(builtins),
+        }
+    }
+
+    /// The number type the constant assigned to `names` is evaluated as, finding the types of the
+    /// constants it depends on first. See [`promotion::expression_type`].
+    pub fn constant_type(&self, names: &[String]) -> BeanieResult<Option<NumberType>> {
+        let definition = |names: &[String]| Definition::Constant(names.to_vec());
+
+        if let Some(number_type) = self.number_types.borrow().get(&definition(names)) {
+            return Ok(*number_type);
+        }
+
+        for dependency in self.dependency_graph().evaluation_order(names)? {
+            if self.number_types.borrow().contains_key(&definition(&dependency)) { continue; }
+
+            let number_type = promotion::expression_type(&self.constants[&dependency], self, &mut Vec::new());
+            self.number_types.borrow_mut().insert(definition(&dependency), number_type);
+        }
+
+        Ok(self.number_types.borrow().get(&definition(names)).copied().flatten())
+    }
+
+    /// The number type of the function `name`. Only the types found outside of the functions
+    /// being looked into are kept, as the others leave out the calls `calling` skips.
+    pub fn function_type(&self, name: &str, calling: &mut Vec<String>) -> Option<NumberType> {
+        let definition = Definition::Function(name.to_string());
+
+        if let Some(number_type) = self.number_types.borrow().get(&definition) {
+            return *number_type;
+        }
+
+        let keep = calling.is_empty();
+        let number_type = promotion::function_type(self.functions.get(name)?, self, calling);
+        if keep {
+            self.number_types.borrow_mut().insert(definition, number_type);
+        }
+        number_type
+    }
+
+    fn constant_value<N: BeanieNum>(&self, names: &[String], number_type: NumberType) -> Option<Value<N>> {
+        self.constant_values.borrow()
+            .get(&(names.to_vec(), number_type))
+            .and_then(|value| value.downcast_ref::<Value<N>>())
+            .cloned()
+    }
+
+    /// The value the function `function` of the file at `file_path` was found to have for
+    /// `arguments` by an earlier call as `number_type`, if memoized.
+    pub fn call_value<N: BeanieNum>(&self, file_path: &str, function: &str, arguments: &[String], number_type: NumberType) -> Option<Value<N>> {
+        self.call_values.borrow()
+            .get(&(file_path.to_string(), function.to_string(), arguments.to_vec(), number_type))
+            .and_then(|value| value.downcast_ref::<Value<N>>())
+            .cloned()
+    }
+
+    pub fn memoize_call<N: BeanieNum>(&self, file_path: &str, function: &str, arguments: Vec<String>, number_type: NumberType, value: Value<N>) {
+        self.call_values.borrow_mut().insert((file_path.to_string(), function.to_string(), arguments, number_type), Rc::new(value));
+    }
+
+    fn forget_values(&mut self) {
+        self.dependency_graph = RefCell::new(None);
+        self.constant_values.get_mut().clear();
+        self.call_values.get_mut().clear();
+        self.number_types.get_mut().clear();
     }
 }
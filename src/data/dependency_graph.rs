@@ -0,0 +1,210 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use crate::data::context::BeanieRuntimeContext;
+use crate::data::error::{BeanieError, BeanieResult};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Definition {
+    /// A constant, by all the names it is assigned to.
+    Constant(Vec<String>),
+    Function(String),
+}
+
+impl Display for Definition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Definition::Constant(names) => write!(f, "{}", names.join(", ")),
+            Definition::Function(name) => write!(f, "{}()", name),
+        }
+    }
+}
+
+/// Which constants and functions of a file each constant and function refers to.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    dependencies: HashMap<Definition, Vec<Definition>>,
+}
+
+impl DependencyGraph {
+    pub fn build(context: &BeanieRuntimeContext) -> DependencyGraph {
+        let constant_keys: HashMap<&str, &Vec<String>> = context.constants.keys()
+            .flat_map(|names| names.iter().map(move |name| (name.as_str(), names)))
+            .collect();
+
+        // resolved the way the evaluator does, calls preferring functions and names constants
+        let resolve_name = |name: &str| match constant_keys.get(name) {
+            Some(names) => Some(Definition::Constant((*names).clone())),
+            None if context.has_function(name) => Some(Definition::Function(name.to_string())),
+            None => None,
+        };
+        let resolve_call = |name: &str| match context.has_function(name) {
+            true => Some(Definition::Function(name.to_string())),
+            false => resolve_name(name),
+        };
+
+        let mut dependencies = HashMap::new();
+
+        for (names, expression) in &context.constants {
+            let mut referenced = Vec::new();
+            expression.visit_references(&mut |name, is_call| referenced.extend(if is_call { resolve_call(name) } else { resolve_name(name) }));
+            dependencies.insert(Definition::Constant(names.clone()), referenced);
+        }
+
+        for (name, function) in &context.functions {
+            let mut referenced = Vec::new();
+
+            // a function from another file is evaluated against that file, not this one
+            if function.external_context.is_none() {
+                function.expression.visit_references(&mut |name, is_call| referenced.extend(if is_call { resolve_call(name) } else { resolve_name(name) }));
+            }
+            dependencies.insert(Definition::Function(name.clone()), referenced);
+        }
+
+        DependencyGraph { dependencies }
+    }
+
+    /// The constants `constant` depends on, directly or through functions, ordered so each comes
+    /// after its own dependencies and ending with `constant` itself. Fails when they depend on each
+    /// other in a cycle, functions calling themselves being left to the evaluator.
+    pub fn evaluation_order(&self, constant: &[String]) -> BeanieResult<Vec<Vec<String>>> {
+        let mut order = Vec::new();
+        self.visit(Definition::Constant(constant.to_vec()), &mut HashSet::new(), &mut order)?;
+        Ok(order)
+    }
+
+    /// Every definition depending on one of `definitions`, directly or through others.
+    pub fn dependents(&self, definitions: &[Definition]) -> HashSet<Definition> {
+        let mut dependents = HashSet::new();
+        let mut pending = definitions.to_vec();
+
+        while let Some(definition) = pending.pop() {
+            for (dependent, dependencies) in &self.dependencies {
+                if dependencies.contains(&definition) && dependents.insert(dependent.clone()) {
+                    pending.push(dependent.clone());
+                }
+            }
+        }
+
+        dependents
+    }
+
+    /// Fails on the first cycle between constants.
+    pub fn check_cycles(&self) -> BeanieResult<()> {
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+
+        let mut definitions: Vec<&Definition> = self.dependencies.keys().collect();
+        definitions.sort();
+
+        for definition in definitions {
+            self.visit(definition.clone(), &mut visited, &mut order)?;
+        }
+        Ok(())
+    }
+
+    /// Depth first search from `start`, adding every constant after the ones it depends on to
+    /// `order`. Iterative, so long chains of constants do not overflow the stack.
+    fn visit(&self, start: Definition, visited: &mut HashSet<Definition>, order: &mut Vec<Vec<String>>) -> BeanieResult<()> {
+        if visited.contains(&start) { return Ok(()); }
+
+        let no_dependencies = Vec::new();
+        let mut path: Vec<(Definition, usize)> = vec![(start, 0)];
+
+        while let Some((definition, next)) = path.last() {
+            let dependency = self.dependencies.get(definition).unwrap_or(&no_dependencies).get(*next);
+
+            match dependency {
+                Some(dependency) => {
+                    if let Some(position) = path.iter().position(|(on_path, _)| on_path == dependency) {
+                        let cycle: Vec<&Definition> = path[position..].iter().map(|(definition, _)| definition).collect();
+                        if cycle.iter().any(|definition| matches!(definition, Definition::Constant(_))) {
+                            return Err(cycle_error(&cycle));
+                        }
+                    }
+
+                    let dependency = (!visited.contains(dependency) && !path.iter().any(|(on_path, _)| on_path == dependency))
+                        .then(|| dependency.clone());
+                    path.last_mut().unwrap().1 += 1;
+
+                    if let Some(dependency) = dependency {
+                        path.push((dependency, 0));
+                    }
+                }
+                None => {
+                    let (definition, _) = path.pop().unwrap();
+                    if let Definition::Constant(names) = &definition {
+                        order.push(names.clone());
+                    }
+                    visited.insert(definition);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn cycle_error(cycle: &[&Definition]) -> BeanieError {
+    let mut names: Vec<String> = cycle.iter().map(|definition| definition.to_string()).collect();
+    names.push(cycle[0].to_string());
+
+    BeanieError::Resolution(format!("Definitions depend on each other in a cycle: {}", names.join(" -> ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::expression::BeanieExpression;
+    use crate::data::function::Function;
+    use crate::data::number::NumberType;
+    use super::*;
+
+    fn math(text: &str) -> BeanieExpression {
+        BeanieExpression::math(text.to_string(), Some(NumberType::Decimal), &[])
+    }
+
+    fn constant(name: &str) -> Definition {
+        Definition::Constant(vec![name.to_string()])
+    }
+
+    #[test]
+    fn dependents_are_found_through_constants_and_functions() {
+        let mut context = BeanieRuntimeContext::new(String::from("test.bn"));
+        context.insert_constant(vec![String::from("a")], math("1"));
+        context.insert_constant(vec![String::from("b")], math("a + 1"));
+        context.insert_constant(vec![String::from("c")], math("f(2)"));
+        context.insert_constant(vec![String::from("d")], math("3"));
+        context.insert_function(String::from("f"), Function::new(vec![String::from("x")], BeanieExpression::math(String::from("x * b"), Some(NumberType::Decimal), &[String::from("x")])));
+
+        let dependents = DependencyGraph::build(&context).dependents(&[constant("a")]);
+        assert_eq!(dependents, HashSet::from([constant("b"), Definition::Function(String::from("f")), constant("c")]));
+    }
+
+    #[test]
+    fn constants_are_ordered_after_what_they_depend_on() {
+        let mut context = BeanieRuntimeContext::new(String::from("test.bn"));
+        context.insert_constant(vec![String::from("a")], math("b + c"));
+        context.insert_constant(vec![String::from("b")], math("c * 2"));
+        context.insert_constant(vec![String::from("c")], math("1"));
+
+        let order = DependencyGraph::build(&context).evaluation_order(&[String::from("a")]).unwrap();
+        assert_eq!(order, vec![vec![String::from("c")], vec![String::from("b")], vec![String::from("a")]]);
+    }
+
+    #[test]
+    fn cycles_through_constants_are_rejected_but_recursive_functions_are_not() {
+        let mut context = BeanieRuntimeContext::new(String::from("test.bn"));
+        context.insert_constant(vec![String::from("a")], math("b + 1"));
+        context.insert_constant(vec![String::from("b")], math("g(2)"));
+        context.insert_function(String::from("g"), Function::new(vec![String::from("x")], BeanieExpression::math(String::from("a * x"), Some(NumberType::Decimal), &[String::from("x")])));
+
+        let graph = DependencyGraph::build(&context);
+        assert!(matches!(graph.check_cycles(), Err(BeanieError::Resolution(message)) if message.contains("a -> b -> g() -> a")));
+        assert!(graph.evaluation_order(&[String::from("b")]).is_err());
+
+        let mut context = BeanieRuntimeContext::new(String::from("test.bn"));
+        context.insert_constant(vec![String::from("n")], math("f(3)"));
+        context.insert_function(String::from("f"), Function::new(vec![String::from("x")], BeanieExpression::math(String::from("{ 1 if x < 1; x * f(x - 1) otherwise }"), Some(NumberType::Decimal), &[String::from("x")])));
+
+        assert!(DependencyGraph::build(&context).check_cycles().is_ok());
+    }
+}
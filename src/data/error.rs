@@ -0,0 +1,191 @@
+use std::fmt::{Display, Formatter};
+use mexprp::MathError;
+use thiserror::Error;
+use crate::data::span::Span;
+
+pub type BeanieResult<T> = Result<T, BeanieError>;
+
+#[derive(Debug, Clone, Error)]
+pub enum BeanieError {
+    /// The source of a file or an expression within it is malformed.
+    #[error("{0}")]
+    Parse(String),
+    /// A name, function, file output or graph target that does not exist.
+    #[error("{0}")]
+    Resolution(String),
+    /// A value or argument of the wrong kind, or a call with the wrong number of arguments.
+    #[error("{0}")]
+    Type(String),
+    /// Evaluating an expression failed, e.g. a division by zero.
+    #[error("Failed to evaluate {expression}. {error}")]
+    Arithmetic { expression: String, error: MathError },
+    /// Reading or writing a file failed.
+    #[error("{0}")]
+    Io(String),
+    /// The inputs given on the command line do not match the file.
+    #[error("{0}")]
+    Input(String),
+    /// Function calls nested deeper than the maximum recursion depth.
+    #[error("{0}")]
+    Recursion(String),
+    /// Any of the above, pointed at the source that caused it.
+    #[error("{error}\n{span}")]
+    Located { span: Span, error: Box<BeanieError> },
+    /// Any of the above, with the calls that were being evaluated when it happened, innermost first.
+    #[error("{}{error}", traceback(.frames))]
+    Traced { frames: Vec<Frame>, error: Box<BeanieError> },
+}
+
+/// A function call or constant that was being evaluated when an error happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub file_path: String,
+    pub name: String,
+    /// The values the function was called with, `None` for constants.
+    pub arguments: Option<Vec<String>>,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.arguments {
+            Some(arguments) => write!(f, "{}({})", self.name, arguments.join(", "))?,
+            None => write!(f, "{}", self.name)?,
+        }
+
+        if !self.file_path.is_empty() {
+            write!(f, " in {}", self.file_path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of outermost and of innermost frames shown of a long traceback, such as that of a
+/// recursion going too deep.
+const TRACEBACK_ENDS: usize = 10;
+
+fn traceback(frames: &[Frame]) -> String {
+    let mut traceback = String::from("Traceback (most recent call last):\n");
+    for (position, frame) in frames.iter().rev().enumerate() {
+        if frames.len() > 2 * TRACEBACK_ENDS + 1 && position >= TRACEBACK_ENDS && position < frames.len() - TRACEBACK_ENDS {
+            if position == TRACEBACK_ENDS {
+                traceback += format!("  ... {} more calls\n", frames.len() - 2 * TRACEBACK_ENDS).as_str();
+            }
+            continue;
+        }
+        traceback += format!("  {}\n", frame).as_str();
+    }
+    traceback
+}
+
+impl BeanieError {
+    /// Prefixes the message with where the error happened, keeping its kind.
+    pub fn within(self, location: &str) -> BeanieError {
+        match self {
+            BeanieError::Parse(message) => BeanieError::Parse(format!("{}: {}", location, message)),
+            BeanieError::Resolution(message) => BeanieError::Resolution(format!("{}: {}", location, message)),
+            BeanieError::Type(message) => BeanieError::Type(format!("{}: {}", location, message)),
+            BeanieError::Arithmetic { expression, error } => BeanieError::Arithmetic { expression: format!("{} ({})", expression, location), error },
+            BeanieError::Io(message) => BeanieError::Io(format!("{}: {}", location, message)),
+            BeanieError::Input(message) => BeanieError::Input(format!("{}: {}", location, message)),
+            BeanieError::Recursion(message) => BeanieError::Recursion(format!("{}: {}", location, message)),
+            BeanieError::Located { span, error } => BeanieError::Located { span, error: Box::new(error.within(location)) },
+            BeanieError::Traced { frames, error } => BeanieError::Traced { frames, error: Box::new(error.within(location)) },
+        }
+    }
+
+    /// Records that the error happened while evaluating `frame`, called by whatever was evaluated
+    /// before.
+    pub fn called_from(self, frame: Frame) -> BeanieError {
+        match self {
+            BeanieError::Traced { mut frames, error } => {
+                frames.push(frame);
+                BeanieError::Traced { frames, error }
+            }
+            _ => BeanieError::Traced { frames: vec![frame], error: Box::new(self) },
+        }
+    }
+
+    /// Points the error at `span`, unless it already points somewhere more specific, such as an
+    /// expression within the instruction or a file it uses.
+    pub fn at(self, span: Option<&Span>) -> BeanieError {
+        match self {
+            // the location goes below the traceback, next to the message
+            BeanieError::Traced { frames, error } => BeanieError::Traced { frames, error: Box::new(error.at(span)) },
+            _ => match span {
+                Some(span) if self.span().is_none() => BeanieError::Located { span: span.clone(), error: Box::new(self) },
+                _ => self,
+            }
+        }
+    }
+
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            BeanieError::Located { span, .. } => Some(span),
+            BeanieError::Traced { error, .. } => error.span(),
+            _ => None,
+        }
+    }
+
+    /// The code the process exits with when this error ends a run.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            BeanieError::Input(_) => 2,
+            BeanieError::Parse(_) => 3,
+            BeanieError::Resolution(_) => 4,
+            BeanieError::Type(_) => 5,
+            BeanieError::Arithmetic { .. } => 6,
+            BeanieError::Io(_) => 7,
+            BeanieError::Recursion(_) => 8,
+            BeanieError::Located { error, .. } => error.exit_code(),
+            BeanieError::Traced { error, .. } => error.exit_code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(name: &str, arguments: Option<&[&str]>) -> Frame {
+        Frame {
+            file_path: String::from("test.bn"),
+            name: name.to_string(),
+            arguments: arguments.map(|arguments| arguments.iter().map(|argument| argument.to_string()).collect()),
+        }
+    }
+
+    #[test]
+    fn tracebacks_list_the_outermost_call_first() {
+        let error = BeanieError::Resolution(String::from("Unknown identifier y"))
+            .called_from(frame("f", Some(&["2"])))
+            .called_from(frame("g", Some(&["1", "x"])))
+            .called_from(frame("c", None));
+
+        assert_eq!(error.to_string(), "Traceback (most recent call last):\n  c in test.bn\n  g(1, x) in test.bn\n  f(2) in test.bn\nUnknown identifier y");
+        assert_eq!(error.exit_code(), 4);
+    }
+
+    #[test]
+    fn long_tracebacks_leave_out_the_calls_in_the_middle() {
+        let mut error = BeanieError::Recursion(String::from("Too deep"));
+        for depth in 0..30 {
+            error = error.called_from(frame("f", Some(&[depth.to_string().as_str()])));
+        }
+
+        let traceback = error.to_string();
+        assert_eq!(traceback.lines().count(), 1 + 2 * TRACEBACK_ENDS + 1 + 1);
+        assert!(traceback.contains("  f(29) in test.bn\n"));
+        assert!(traceback.contains("  ... 10 more calls\n"));
+        assert!(!traceback.contains("f(15)"));
+        assert!(traceback.ends_with("  f(0) in test.bn\nToo deep"));
+    }
+
+    #[test]
+    fn a_traced_error_is_located_below_its_traceback() {
+        let span = Span::new("test.bn", "x = 1 / 0", 4..9);
+        let error = BeanieError::Type(String::from("Bad")).called_from(frame("x", None)).at(Some(&span));
+
+        assert!(matches!(&error, BeanieError::Traced { error, .. } if matches!(**error, BeanieError::Located { .. })));
+        assert_eq!(error.span(), Some(&span));
+    }
+}
@@ -1,122 +1,278 @@
-use mexprp::{Answer, Context, Num, Expression};
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::Arc;
+use mexprp::{Answer, Context};
 use mexprp::num::{ComplexFloat, ComplexRugRat};
-use regex::Regex;
 use rug::{Complex, Rational};
 use tree_sitter_beanie::data::context::ExpressionSignature;
 use tree_sitter_beanie::data::expression::data_type::DataType;
 use tree_sitter_beanie::data::expression::expression_type::ExpressionType;
 use tree_sitter_beanie::data::expression::instruction_expression::InstructionExpression;
 use crate::data::context::BeanieRuntimeContext;
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::expression::evaluator::linear_algebra::MATRIX_BUILTINS;
+use crate::data::expression::evaluator::number_theory::INTEGER_BUILTINS;
+use crate::data::expression::evaluator::promotion::{self, CONVERSION_BUILTINS};
+use crate::data::expression::evaluator::uncertainty::MEASUREMENT_BUILTINS;
+use crate::data::expression::evaluator::{Scope, LIST_BUILTINS};
+use crate::data::expression::syntax_tree::{BinaryOperator, Node, NodeKind, SyntaxError};
+use crate::data::span::Span;
+use crate::data::unit::Unit;
+use crate::data::value::Value;
+use crate::data::number::big_float::{self, BigFloat};
+use crate::data::number::big_integer::BigInteger;
+use crate::data::number::interval::{Interval, INTERVAL_BUILTINS};
+use crate::data::number::{BeanieNum, NumberType};
+
+pub mod syntax_tree;
+pub mod evaluator;
+
+lazy_static! {
+    /// Names mexprp defines on its own, such as sqrt, sin or pi.
+    static ref BUILTIN_NAMES: HashSet<String> = {
+        let builtins = Context::<f64>::new();
+        let mut names: HashSet<String> = builtins.funcs.keys().chain(builtins.vars.keys()).cloned().collect();
+        names.insert(String::from("i"));
+        names.extend(LIST_BUILTINS.iter().chain(&MATRIX_BUILTINS).chain(&INTEGER_BUILTINS).chain(&INTERVAL_BUILTINS).chain(&MEASUREMENT_BUILTINS).chain(&CONVERSION_BUILTINS).map(|name| name.to_string()));
+        names
+    };
+}
 
 #[derive(Clone, Debug)]
 pub enum BeanieExpression {
-    Math(String, DataType),
+    Math(MathExpression),
     SimpleF64(f64),
     Boolean(bool),
     FilePath(String),
     String(String),
 }
 
-impl BeanieExpression {
-    fn build_context<N: Num + 'static>(str: &str, ctx: Option<&BeanieRuntimeContext>) -> Context<N>{
-        if let Some(ctx) = ctx {
-            let pattern = Regex::new(r"[a-zA-Z][a-zA-Z0-9_]*").unwrap();
-            let mut context = Context::new();
-
-            for ident in pattern.find_iter(str) {
-                let name = ident.as_str();
-                
-                // todo
-                if ctx.has_constant(name) {
-                    let constant = ctx.get_constant(name).unwrap();
-                    let expression = constant.0;
-                    let index = constant.1;
-
-                    let value = match expression.evaluate::<N>(ctx) {
-                        Answer::Single(ans) => ans,
-                        Answer::Multiple(ans) => ans[index].clone(),
-                    };
-
-                    context.set_var(name, value);
-
-                } else if ctx.has_function(name) {
-                    let function = ctx.get_function(name).unwrap();
-                    context.set_func(name, function);
-                }
-            }
-            
-            return context;
-        }
+/// A math expression together with its syntax tree, parsed once when the expression is created.
+#[derive(Clone, Debug)]
+pub struct MathExpression {
+    pub text: String,
+    /// The type of the numbers of the expression, `None` when they are not numbers, as for
+    /// booleans.
+    pub number_type: Option<NumberType>,
+    /// Where the expression was written, if it came from a file.
+    pub span: Option<Span>,
+    /// Shared between the copies of a context, a syntax error is kept until the expression is
+    /// evaluated or checked.
+    tree: Arc<Result<Node, SyntaxError>>,
+}
 
-        Context::empty()
+impl MathExpression {
+    /// The syntax tree, or the error parsing the expression pointed at where it went wrong.
+    fn tree(&self) -> BeanieResult<&Node> {
+        self.tree.as_ref().as_ref().map_err(|err| err.error.clone().at(self.span_of(&err.range).as_ref()))
     }
-    
-    fn evaluate_with_math_ctx<N: Num + 'static>(expr: &str, ctx: &Context<N>, extra_ctxs: Vec<&Context<N>>) -> Answer<N> {
-        if !extra_ctxs.is_empty() {
 
-            let mut final_ctx = ctx.clone();
+    /// Where the bytes `range` of the parsed text were written.
+    fn span_of(&self, range: &Range<usize>) -> Option<Span> {
+        self.span.as_ref().map(|span| span.within(self.text.trim(), range))
+    }
 
-            for extra_ctx in extra_ctxs {
-                for (name, func) in &extra_ctx.funcs {
-                    final_ctx.funcs.insert(name.clone(), func.clone());
-                }
+    fn evaluate<N: BeanieNum>(&self, ctx: &BeanieRuntimeContext, arguments: &[Value<N>], builtins: &Context<N>) -> BeanieResult<Value<N>> {
+        let scope = Scope {
+            text: self.text.as_str(),
+            span: self.span.as_ref(),
+            context: ctx,
+            arguments,
+            builtins,
+        };
 
-                for (name, var) in &extra_ctx.vars {
-                    final_ctx.vars.insert(name.clone(), var.clone());
-                }
+        scope.evaluate(self.tree()?)
+    }
+}
+
+impl BeanieExpression {
+    /// Parses `text` into a math expression of `number_type`, `parameters` being the names bound by
+    /// the function it is the body of, if any.
+    pub fn math(text: String, number_type: Option<NumberType>, parameters: &[String]) -> BeanieExpression {
+        let tree = Node::parse(text.trim(), parameters);
+
+        BeanieExpression::Math(MathExpression {
+            text,
+            number_type,
+            span: None,
+            tree: Arc::new(tree),
+        })
+    }
+
+    /// Gives a math expression `chosen`, the type of the file or definition it is written in, which
+    /// the grammar parsed as `parsed_as`. See [`NumberType::within`].
+    pub fn typed(self, chosen: NumberType, parsed_as: &DataType) -> BeanieExpression {
+        match self {
+            BeanieExpression::Math(math) => {
+                let number_type = math.number_type.and_then(|number_type| chosen.within(&number_type.data_type(), parsed_as));
+                BeanieExpression::Math(MathExpression { number_type, ..math })
             }
+            _ => self,
+        }
+    }
 
-            return Expression::parse_ctx(expr, final_ctx).unwrap().eval().unwrap();
+    /// Attaches where a math expression was written, so errors evaluating it can point there.
+    pub fn located(self, span: Option<Span>) -> BeanieExpression {
+        match self {
+            BeanieExpression::Math(math) => BeanieExpression::Math(MathExpression { span, ..math }),
+            _ => self,
         }
-        
-        Expression::parse_ctx(expr, ctx.clone()).unwrap().eval().unwrap()
     }
-    
-    pub fn evaluate_with_extra<N: Num + 'static>(&self, ctx: Option<&BeanieRuntimeContext>, extra_ctx: Vec<&Context<N>>) -> Answer<N> {
+
+    /// The error parsing the expression ran into, if any.
+    pub fn syntax_error(&self) -> Option<BeanieError> {
         match self {
-            BeanieExpression::Math(expr, _) => {
-                let str = expr.as_str().trim();
-                BeanieExpression::evaluate_with_math_ctx(str, &BeanieExpression::build_context(str, ctx), extra_ctx)
-            },
-            BeanieExpression::SimpleF64(value) => N::from_f64(value.clone(), &Context::empty()).unwrap(),
-            BeanieExpression::Boolean(b) => N::from_f64(if b.clone() { 1f64 } else { 0f64 }, &Context::empty()).unwrap(),
-            _ => unreachable!()
+            BeanieExpression::Math(math) => math.tree().err(),
+            _ => None,
         }
     }
 
-    pub fn evaluate<N: Num + 'static>(&self, ctx: &BeanieRuntimeContext) -> Answer<N> {
-        self.evaluate_with_extra(Some(ctx), Vec::new())
+    /// Evaluates the expression with `builtins`, `arguments` being the values of the parameters it
+    /// was parsed with.
+    pub fn evaluate_with_arguments<N: BeanieNum>(&self, ctx: &BeanieRuntimeContext, arguments: &[Value<N>], builtins: &Context<N>) -> BeanieResult<Value<N>> {
+        let from_f64 = |value: f64| N::from_f64(value, builtins)
+            .map(Value::Number)
+            .map_err(|err| BeanieError::Arithmetic { expression: value.to_string(), error: err });
+
+        match self {
+            BeanieExpression::Math(math) => math.evaluate(ctx, arguments, builtins).map_err(|err| err.at(math.span.as_ref())),
+            BeanieExpression::SimpleF64(value) => from_f64(*value),
+            BeanieExpression::Boolean(b) => from_f64(if *b { 1f64 } else { 0f64 }),
+            _ => Err(BeanieError::Type(format!("Expected a math expression, got a {}", self.get_type()))),
+        }
+    }
+
+    pub fn evaluate<N: BeanieNum>(&self, ctx: &BeanieRuntimeContext, builtins: &Context<N>) -> BeanieResult<Value<N>> {
+        self.evaluate_with_arguments(ctx, &[], builtins)
+    }
+
+    /// Evaluates an expression that must give exactly one value, such as an instruction argument.
+    pub fn evaluate_single<N: BeanieNum>(&self, ctx: &BeanieRuntimeContext) -> BeanieResult<N> {
+        match self.evaluate(ctx, &evaluator::builtins::<N>(big_float::DEFAULT_PRECISION))? {
+            Value::Number(Answer::Single(ans)) => Ok(ans),
+            Value::Number(Answer::Multiple(ans)) => Err(BeanieError::Type(format!("Expected a single value, got {} values from {}", ans.len(), self.get_math().unwrap_or_default()))),
+            Value::Quantity(_, unit) => Err(BeanieError::Type(format!("Expected a plain number, got a quantity in {} from {}", unit, self.get_math().unwrap_or_default()))),
+            Value::Measured(measured) => Err(BeanieError::Type(format!("Expected a plain number, got the measured value {} from {}", measured, self.get_math().unwrap_or_default()))),
+            Value::List(_) => Err(BeanieError::Type(format!("Expected a single value, got a list from {}", self.get_math().unwrap_or_default()))),
+            Value::Matrix(_) => Err(BeanieError::Type(format!("Expected a single value, got a matrix from {}", self.get_math().unwrap_or_default()))),
+        }
     }
    
-    pub fn evaluate_to_string(&self, context: &BeanieRuntimeContext) -> String {
-        match self {
-            BeanieExpression::Math(expression_component, data_type) => {
-                match data_type {
-                    DataType::Decimal => self.evaluate::<f64>(context).to_string(),
-                    DataType::ImaginaryDecimal => self.evaluate::<ComplexFloat>(context).to_string(),
-                    DataType::Complex => self.evaluate::<Complex>(context).to_string(),
-                    DataType::Rational => self.evaluate::<Rational>(context).to_string(),
-                    DataType::ComplexRational => self.evaluate::<ComplexRugRat>(context).to_string(),
-                    _ => expression_component.as_str().to_string(),
+    pub fn evaluate_to_string(&self, context: &BeanieRuntimeContext) -> BeanieResult<String> {
+        self.format(context, false)
+    }
+
+    /// Like [`Self::evaluate_to_string`], but laying matrices out over several lines, for printing.
+    pub fn evaluate_to_pretty_string(&self, context: &BeanieRuntimeContext) -> BeanieResult<String> {
+        self.format(context, true)
+    }
+
+    fn format(&self, context: &BeanieRuntimeContext, pretty: bool) -> BeanieResult<String> {
+        fn format<N: BeanieNum>(value: Value<N>, pretty: bool) -> String {
+            if pretty { value.to_pretty_string() } else { value.to_string() }
+        }
+
+        Ok(match self {
+            BeanieExpression::Math(math) => {
+                let number_type = self.number_type(context);
+                let precision = number_type.map_or(big_float::DEFAULT_PRECISION, NumberType::precision);
+
+                match number_type {
+                    Some(NumberType::Integer) => format(self.evaluate(context, &evaluator::builtins::<BigInteger>(precision))?, pretty),
+                    Some(NumberType::Rational) => format(self.evaluate(context, &evaluator::builtins::<Rational>(precision))?, pretty),
+                    Some(NumberType::Decimal) => format(self.evaluate(context, &evaluator::builtins::<f64>(precision))?, pretty),
+                    Some(NumberType::Interval(_)) => format(self.evaluate(context, &evaluator::builtins::<Interval>(precision))?, pretty),
+                    Some(NumberType::Float(_)) => format(self.evaluate(context, &evaluator::builtins::<BigFloat>(precision))?, pretty),
+                    Some(NumberType::ComplexRational) => format(self.evaluate(context, &evaluator::builtins::<ComplexRugRat>(precision))?, pretty),
+                    Some(NumberType::ImaginaryDecimal) => format(self.evaluate(context, &evaluator::builtins::<ComplexFloat>(precision))?, pretty),
+                    Some(NumberType::Complex) => format(self.evaluate(context, &evaluator::builtins::<Complex>(precision))?, pretty),
+                    None => math.text.clone(),
                 }
             } 
             BeanieExpression::Boolean(b) => b.to_string(),
             BeanieExpression::FilePath(file_path) => file_path.to_string(),
             BeanieExpression::String(str) => str.trim_matches('\'').to_string(),
             BeanieExpression::SimpleF64(value) => value.to_string(),
-        }
+        })
     }
     
+    /// Looks for names that can not be resolved and calls to functions with the wrong number of
+    /// arguments, adding them to `problems`. Syntax errors are left to [`Self::syntax_error`].
+    pub fn check(&self, context: &BeanieRuntimeContext, problems: &mut Vec<BeanieError>) {
+        let math = match self {
+            BeanieExpression::Math(math) => math,
+            _ => return,
+        };
+
+        let tree = match math.tree() {
+            Ok(tree) => tree,
+            Err(_) => return,
+        };
+
+        let text = math.text.trim();
+        let is_value = |name: &str| context.has_constant(name) || context.inputs.iter().any(|input| input == name);
+        let mut found = Vec::new();
+
+        tree.visit(&mut |node| {
+            let problem = match &node.kind {
+                NodeKind::Name(name) if !is_value(name) && !context.has_function(name) && !BUILTIN_NAMES.contains(name) && !Unit::is_named(name) => {
+                    Some(BeanieError::Resolution(format!("Unknown identifier {}", name)))
+                }
+                NodeKind::Call(name, arguments) => match context.functions.get(name) {
+                    Some(function) if function.parameters.len() != arguments.len() => Some(BeanieError::Type(format!("Function {} takes {} arguments but is called with {}", name, function.parameters.len(), arguments.len()))),
+                    Some(_) => None,
+                    None if is_value(name) || BUILTIN_NAMES.contains(name) => None,
+                    None => Some(BeanieError::Resolution(format!("Unknown identifier {}", name))),
+                },
+                // 5 g written after a constant g is declared is 5 times the constant, not 5 grams
+                NodeKind::Binary(BinaryOperator::Multiply, left, right) => match &right.kind {
+                    NodeKind::Name(name) if Unit::is_named(name) && is_value(name) && text.get(left.range.end..right.range.start).is_some_and(|between| between.trim().is_empty()) => {
+                        Some(BeanieError::Resolution(format!("{} after a value is the constant {}, not the unit. Write * to multiply by the constant, or rename it to use the unit", name, name)))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            if let Some(problem) = problem {
+                if !found.iter().any(|(found, _): &(BeanieError, _)| found.to_string() == problem.to_string()) { found.push((problem, node.range.clone())); }
+            }
+        });
+
+        problems.extend(found.into_iter().map(|(problem, range)| problem.at(math.span_of(&range).as_ref())));
+    }
+
+    /// Calls `visitor` with every constant or function name the expression refers to, and whether
+    /// it is called.
+    pub fn visit_references<F: FnMut(&str, bool)>(&self, visitor: &mut F) {
+        if let BeanieExpression::Math(math) = self {
+            if let Ok(tree) = math.tree() {
+                tree.visit(&mut |node| match &node.kind {
+                    NodeKind::Name(name) => visitor(name, false),
+                    NodeKind::Call(name, _) => visitor(name, true),
+                    _ => {}
+                });
+            }
+        }
+    }
+
     pub fn get_math(&self) -> Option<String> {
         match self {
-            BeanieExpression::Math(math, _) => Some(math.clone()),
+            BeanieExpression::Math(math) => Some(math.text.clone()),
             _ => None,
         }
     }
     
+    /// The number type the expression is evaluated as, promoted from its data type to hold the
+    /// types of the constants and functions it uses. `None` when it is not a math expression of
+    /// numbers.
+    pub fn number_type(&self, context: &BeanieRuntimeContext) -> Option<NumberType> {
+        promotion::expression_type(self, context, &mut Vec::new())
+    }
+
     pub fn get_type(&self) -> ExpressionType {
         match self {
-            BeanieExpression::Math(_, _) => ExpressionType::MathExpression,
+            BeanieExpression::Math(_) => ExpressionType::MathExpression,
             BeanieExpression::Boolean(_) => ExpressionType::Boolean,
             BeanieExpression::FilePath(_) => ExpressionType::FilePath,
             BeanieExpression::String(_) => ExpressionType::String,
@@ -127,7 +283,7 @@ impl BeanieExpression {
 
 impl From<ExpressionSignature> for BeanieExpression {
     fn from(value: ExpressionSignature) -> Self {
-        BeanieExpression::Math(value.evaluation.clone(), value.data_type.clone())
+        BeanieExpression::math(value.evaluation, NumberType::of(&value.data_type), &[])
     }
 }
 
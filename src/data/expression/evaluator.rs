@@ -0,0 +1,455 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
+use mexprp::{Answer, Calculation, Context, MathError, Num, Term};
+use rug::Rational;
+use crate::data::context::BeanieRuntimeContext;
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::expression::evaluator::promotion::CONVERSION_BUILTINS;
+use crate::data::expression::syntax_tree::{BinaryOperator, Case, Node, NodeKind, SeriesOperator, UnaryOperator};
+use crate::data::number::big_integer::{self, BigInteger};
+use crate::data::number::interval::Interval;
+use crate::data::number::{self, BeanieNum};
+use crate::data::matrix::Matrix;
+use crate::data::span::Span;
+use crate::data::unit::Unit;
+use crate::data::value::Value;
+
+pub mod linear_algebra;
+pub mod number_theory;
+pub mod promotion;
+pub mod uncertainty;
+pub mod units;
+
+/// Builtins by the number type, the precision and whether square roots give both roots.
+type Builtins = HashMap<(TypeId, u32, bool), Rc<dyn Any>>;
+
+thread_local! {
+    /// mexprp's builtin functions and constants, such as sqrt or pi, built once for each.
+    static BUILTINS: RefCell<Builtins> = RefCell::new(HashMap::new());
+}
+
+/// The mexprp context holding the builtins for `N` at `precision`, also used for its
+/// configuration, such as the precision of rug numbers.
+pub fn builtins<N: BeanieNum>(precision: u32) -> Rc<Context<N>> {
+    cached_builtins(precision, true)
+}
+
+/// The builtins for `N` with square roots giving only the principal root, for calculations that
+/// need a single value, as on measured values.
+pub fn principal_builtins<N: BeanieNum>(precision: u32) -> Rc<Context<N>> {
+    cached_builtins(precision, false)
+}
+
+fn cached_builtins<N: BeanieNum>(precision: u32, sqrt_both: bool) -> Rc<Context<N>> {
+    let builtins = BUILTINS.with(|builtins| builtins.borrow_mut()
+        .entry((TypeId::of::<N>(), precision, sqrt_both))
+        .or_insert_with(|| {
+            let mut builtins = N::builtins(precision);
+            builtins.cfg.sqrt_both &= sqrt_both;
+            Rc::new(builtins)
+        })
+        .clone());
+
+    builtins.downcast::<Context<N>>().unwrap()
+}
+
+/// Builtins working on whole lists, which a function of the file by the same name replaces.
+pub const LIST_BUILTINS: [&str; 4] = ["len", "map", "filter", "reduce"];
+
+/// Everything a syntax tree is evaluated against. A name is the parameter of the function being
+/// evaluated if there is one by that name, which is already decided when parsing, then a constant
+/// of the file, then a mexprp builtin, then a unit. A call goes to a function of the file before a builtin. A
+/// function used from another file is evaluated in the scope of that file.
+///
+/// Conditions are numbers like any other value, comparisons giving 1 when they hold and 0 when
+/// they do not, and any value other than 0 counting as holding.
+///
+/// Arithmetic, comparisons and calls to functions other than the list builtins go element by
+/// element over lists. So does arithmetic on matrices, except for multiplying and raising them.
+/// Arithmetic on quantities checks and combines their units, and arithmetic on measured numbers
+/// propagates their uncertainties.
+pub struct Scope<'a, N: BeanieNum> {
+    /// The text of the expression, for error messages.
+    pub text: &'a str,
+    /// Where the expression was written, if it came from a file.
+    pub span: Option<&'a Span>,
+    pub context: &'a BeanieRuntimeContext,
+    /// Values of the parameters of the function being evaluated.
+    pub arguments: &'a [Value<N>],
+    pub builtins: &'a Context<N>,
+}
+
+impl<'a, N: BeanieNum> Scope<'a, N> {
+    fn arithmetic(&self, error: MathError) -> BeanieError {
+        match error {
+            // mexprp has no ordering for complex numbers
+            MathError::CmpError if N::typename() == Interval::typename() => BeanieError::Type(format!("Intervals that overlap can not be compared, as in {}", self.text)),
+            MathError::CmpError => BeanieError::Type(format!("Values of type {} can not be compared, as in {}", N::typename(), self.text)),
+            MathError::Unimplemented { op, num_type } if num_type == BigInteger::typename() => BeanieError::Type(format!("{} is not an integer, in {}", op, self.text)),
+            MathError::Unimplemented { op, num_type } if num_type == big_integer::TOO_LARGE => BeanieError::Type(format!("{} would have more than {} bits, too many to compute, in {}", op, big_integer::MAX_POWER_BITS, self.text)),
+            error => BeanieError::Arithmetic {
+                expression: self.text.to_string(),
+                error,
+            },
+        }
+    }
+
+    fn boolean(&self, value: bool) -> Calculation<N> {
+        N::from_f64(if value { 1f64 } else { 0f64 }, self.builtins)
+    }
+
+    fn holds(&self, value: &N) -> Result<bool, MathError> {
+        let zero = N::from_f64(0f64, self.builtins)?;
+        Ok(zero.to_vec().iter().all(|zero| value != zero))
+    }
+
+    fn number(&self, value: f64) -> BeanieResult<Value<N>> {
+        N::from_f64(value, self.builtins)
+            .map(Value::Number)
+            .map_err(|err| self.arithmetic(err))
+    }
+
+    /// The number literal `text`, read exactly and rounded only if the type has fewer digits.
+    fn literal(&self, text: &str) -> BeanieResult<Value<N>> {
+        let value = number::parse_literal(text).and_then(|value| N::from_parts(&value, &Rational::new(), self.builtins));
+
+        match value {
+            Some(value) => Ok(Value::Number(Answer::Single(value))),
+            None => Err(self.arithmetic(MathError::Unimplemented { op: text.to_string(), num_type: N::typename() })),
+        }
+    }
+
+    /// Evaluates `node`, pointing an error at the part of the source it was parsed from.
+    pub fn evaluate(&self, node: &Node) -> BeanieResult<Value<N>> {
+        self.evaluate_kind(&node.kind).map_err(|err| match self.span {
+            Some(span) => err.at(Some(&span.within(self.text.trim(), &node.range))),
+            None => err,
+        })
+    }
+
+    fn evaluate_kind(&self, kind: &NodeKind) -> BeanieResult<Value<N>> {
+        match kind {
+            NodeKind::Number(text) => self.literal(text),
+            NodeKind::Parameter(index, name) => self.arguments.get(*index)
+                .cloned()
+                .ok_or_else(|| BeanieError::Resolution(format!("No value given for parameter {}", name))),
+            NodeKind::Name(name) => self.name(name),
+            NodeKind::Call(name, arguments) if LIST_BUILTINS.contains(&name.as_str()) && !self.context.has_function(name) => self.list_builtin(name, arguments),
+            NodeKind::Call(name, arguments) if CONVERSION_BUILTINS.contains(&name.as_str()) && !self.context.has_function(name) => self.conversion(name, arguments),
+            NodeKind::Call(name, arguments) => {
+                let arguments = arguments.iter().map(|argument| self.evaluate(argument)).collect::<BeanieResult<Vec<_>>>()?;
+                self.call(name, arguments)
+            }
+            NodeKind::Unary(UnaryOperator::Factorial, operand) => {
+                let operand = self.evaluate(operand)?;
+                Value::broadcast(&[operand], &|operand| self.integer_function("factorial", operand))
+            }
+            NodeKind::Unary(operator, operand) => {
+                let operand = self.evaluate(operand)?;
+
+                Value::broadcast(&[operand], &|operand| match &operand[0] {
+                    Value::Measured(measured) => self.measured_unary(*operator, measured),
+                    operand => operand.map(&|operand| match operator {
+                        UnaryOperator::Negate => self.negate(operand),
+                        UnaryOperator::PlusMinus => self.negate(operand).map(|negated| operand.clone().join(negated)),
+                        UnaryOperator::Not => operand.unop(|value| self.boolean(!self.holds(value)?)),
+                        UnaryOperator::Factorial => unreachable!("evaluated as the factorial builtin"),
+                    }.map_err(|err| self.arithmetic(err))),
+                })
+            }
+            NodeKind::Binary(operator @ (BinaryOperator::And | BinaryOperator::Or), left, right) => self.logical(*operator, left, right),
+            NodeKind::Binary(operator, left, right) => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                self.binary(*operator, &left, &right)
+            }
+            NodeKind::Piecewise(cases, otherwise) => self.piecewise(cases, otherwise.as_deref()),
+            NodeKind::Series { operator, index, from, to, body } => self.series(*operator, index, from, to, body),
+            NodeKind::List(elements) => Ok(Value::List(elements.iter().map(|element| self.evaluate(element)).collect::<BeanieResult<_>>()?)),
+            NodeKind::Matrix(rows) => {
+                let rows = rows.iter()
+                    .map(|row| row.iter()
+                        .map(|element| match self.evaluate(element)? {
+                            Value::Number(Answer::Single(element)) => Ok(element),
+                            element => Err(BeanieError::Type(format!("An element of a matrix must be a single number, got {} in {}", element, self.text))),
+                        })
+                        .collect::<BeanieResult<Vec<N>>>())
+                    .collect::<BeanieResult<Vec<Vec<N>>>>()?;
+
+                Ok(Value::Matrix(Matrix::new(rows)?))
+            }
+            NodeKind::Range(from, to) => {
+                let from = self.integer("The bounds of a range", from)?;
+                let to = self.integer("The bounds of a range", to)?;
+                Ok(Value::List((from..=to).map(|value| self.number(value as f64)).collect::<BeanieResult<_>>()?))
+            }
+            NodeKind::Convert(value, unit) => {
+                let value = self.evaluate(value)?;
+                self.convert(&value, unit)
+            }
+        }
+    }
+
+    /// Applies `operator` to two values, going element by element over lists.
+    fn binary(&self, operator: BinaryOperator, left: &Value<N>, right: &Value<N>) -> BeanieResult<Value<N>> {
+        if let Some(result) = self.matrix_operation(operator, left, right) {
+            return result;
+        }
+
+        match (left, right) {
+            (Value::List(_), _) | (_, Value::List(_)) => Value::broadcast(&[left.clone(), right.clone()], &|values| self.binary(operator, &values[0], &values[1])),
+            _ if operator == BinaryOperator::Uncertainty => self.measurement(left, right),
+            (Value::Measured(_), _) | (_, Value::Measured(_)) => self.measured_operation(operator, left, right),
+            (Value::Quantity(..), _) | (_, Value::Quantity(..)) => self.quantity_operation(operator, left, right),
+            _ => left.zip_with(right, &|left, right| self.numbers(operator, left, right)),
+        }
+    }
+
+    fn numbers(&self, operator: BinaryOperator, left: &Answer<N>, right: &Answer<N>) -> BeanieResult<Answer<N>> {
+        let ctx = self.builtins;
+
+        match operator {
+            BinaryOperator::Add => left.op(right, |a, b| a.add(b, ctx)),
+            BinaryOperator::Subtract => left.op(right, |a, b| a.sub(b, ctx)),
+            BinaryOperator::PlusMinus => left.op(right, |a, b| a.add(b, ctx))
+                .and_then(|sum| Ok(sum.join(left.op(right, |a, b| a.sub(b, ctx))?))),
+            BinaryOperator::Multiply => left.op(right, |a, b| a.mul(b, ctx)),
+            BinaryOperator::Divide => left.op(right, |a, b| a.div(b, ctx)),
+            BinaryOperator::Power => left.op(right, |a, b| a.pow(b, ctx)),
+            BinaryOperator::Modulo => left.op(right, |a, b| a.modulo(b, ctx)),
+            BinaryOperator::Less => left.op(right, |a, b| self.boolean(a.tryord(b, ctx)? == Ordering::Less)),
+            BinaryOperator::LessEqual => left.op(right, |a, b| self.boolean(a.tryord(b, ctx)? != Ordering::Greater)),
+            BinaryOperator::Greater => left.op(right, |a, b| self.boolean(a.tryord(b, ctx)? == Ordering::Greater)),
+            BinaryOperator::GreaterEqual => left.op(right, |a, b| self.boolean(a.tryord(b, ctx)? != Ordering::Less)),
+            BinaryOperator::Equal => left.op(right, |a, b| self.boolean(a.tryord(b, ctx)? == Ordering::Equal)),
+            BinaryOperator::NotEqual => left.op(right, |a, b| self.boolean(a.tryord(b, ctx)? != Ordering::Equal)),
+            BinaryOperator::Uncertainty => unreachable!("evaluated by Scope::measurement"),
+            BinaryOperator::And | BinaryOperator::Or => unreachable!("evaluated by Scope::logical"),
+        }.map_err(|err| self.arithmetic(err))
+    }
+
+    fn negate(&self, operand: &Answer<N>) -> Calculation<N> {
+        let negative_one = N::from_f64(-1f64, self.builtins)?;
+        operand.op(&negative_one, |a, b| a.mul(b, self.builtins))
+    }
+
+    /// `and` and `or`, leaving the right side out when the left one already decides, so that
+    /// `x != 0 and 1 / x < 2` does not divide by zero.
+    fn logical(&self, operator: BinaryOperator, left: &Node, right: &Node) -> BeanieResult<Value<N>> {
+        let is_and = operator == BinaryOperator::And;
+        let left = self.evaluate(left)?;
+
+        if let Value::Number(Answer::Single(value)) = &left {
+            if self.holds(value).map_err(|err| self.arithmetic(err))? != is_and {
+                return self.number(if is_and { 0f64 } else { 1f64 });
+            }
+        }
+
+        let right = self.evaluate(right)?;
+        left.zip_with(&right, &|left, right| left.op(right, |a, b| match is_and {
+            true => self.boolean(self.holds(a)? && self.holds(b)?),
+            false => self.boolean(self.holds(a)? || self.holds(b)?),
+        }).map_err(|err| self.arithmetic(err)))
+    }
+
+    fn condition(&self, node: &Node) -> BeanieResult<bool> {
+        match self.evaluate(node)? {
+            Value::Number(Answer::Single(value)) => self.holds(&value).map_err(|err| self.arithmetic(err)),
+            Value::Number(Answer::Multiple(values)) => Err(BeanieError::Type(format!("A condition must have a single value, got {} values in {}", values.len(), self.text))),
+            Value::Quantity(_, unit) => Err(BeanieError::Type(format!("A condition must be a plain number, got a quantity in {} in {}", unit, self.text))),
+            Value::Measured(measured) => Err(BeanieError::Type(format!("A condition must be a plain number, got the measured value {} in {}. Comparisons of measured values compare their values", measured, self.text))),
+            Value::List(_) => Err(BeanieError::Type(format!("A condition must have a single value, got a list in {}. Conditions on the elements of a list go in a function given to map or filter", self.text))),
+            Value::Matrix(_) => Err(BeanieError::Type(format!("A condition must have a single value, got a matrix in {}", self.text))),
+        }
+    }
+
+    /// Evaluates only the value of the case that applies, so the others may be undefined there.
+    fn piecewise(&self, cases: &[Case], otherwise: Option<&Node>) -> BeanieResult<Value<N>> {
+        for case in cases {
+            if self.condition(&case.condition)? {
+                return self.evaluate(&case.value);
+            }
+        }
+
+        match otherwise {
+            Some(otherwise) => self.evaluate(otherwise),
+            None => Err(BeanieError::Type(format!("None of the cases of {} applies and it has no otherwise case", self.text))),
+        }
+    }
+
+    /// Evaluates `body` once for every value of the index, in the number type of the expression, so
+    /// the result of a rational series is exact. The terms are combined from the first one on, so
+    /// a sum of quantities is one too.
+    fn series(&self, operator: SeriesOperator, index: &str, from: &Node, to: &Node, body: &Node) -> BeanieResult<Value<N>> {
+        let bounds = format!("The bounds of index {}", index);
+        let from = self.integer(&bounds, from)?;
+        let to = self.integer(&bounds, to)?;
+        let binary = match operator {
+            SeriesOperator::Sum => BinaryOperator::Add,
+            SeriesOperator::Product => BinaryOperator::Multiply,
+        };
+
+        let mut result: Option<Value<N>> = None;
+        let mut arguments = self.arguments.to_vec();
+        for value in from..=to {
+            arguments.push(self.number(value as f64)?);
+            let term = Scope { arguments: &arguments, ..*self }.evaluate(body)?;
+            arguments.pop();
+
+            result = Some(match result {
+                Some(result) => self.binary(binary, &result, &term)?,
+                None => term,
+            });
+        }
+
+        match result {
+            Some(result) => Ok(result),
+            None => self.number(if binary == BinaryOperator::Add { 0f64 } else { 1f64 }),
+        }
+    }
+
+    /// Evaluates `node`, which must give an integer such as a bound of a series or range. `what`
+    /// names it in errors.
+    fn integer(&self, what: &str, node: &Node) -> BeanieResult<i64> {
+        let value = match self.evaluate(node)? {
+            Value::Number(Answer::Single(value)) => value,
+            value => return Err(BeanieError::Type(format!("{} must be single values, got {} in {}", what, value, self.text))),
+        };
+
+        match value.to_real_f64() {
+            Some(bound) if bound.is_finite() && bound.fract() == 0f64 => Ok(bound as i64),
+            _ => Err(BeanieError::Type(format!("{} must be integers, got {} in {}", what, value, self.text))),
+        }
+    }
+
+    fn name(&self, name: &str) -> BeanieResult<Value<N>> {
+        if let Some((names, index)) = self.context.get_constant_names(name) {
+            let value = self.context.evaluate_constant(names, self.builtins)?;
+            if names.len() == 1 {
+                return Ok(value);
+            }
+
+            // each of the names assigned together, as in a, b = ±c, takes one of the values, which
+            // must be as many as the names
+            let mut branches = value.branches();
+            if branches.len() != names.len() {
+                return Err(BeanieError::Type(format!("Constants {} are assigned {} values but {} gives {}", names.join(", "), names.len(), self.context.constants[names].get_math().unwrap_or_default(), branches.len())));
+            }
+            return Ok(branches.swap_remove(index));
+        }
+
+        if let Some(value) = self.builtins.vars.get(name) {
+            return value.eval_ctx(self.builtins).map(Value::Number).map_err(|err| self.arithmetic(err));
+        }
+
+        match Unit::named(name) {
+            Some(unit) => self.unit(unit),
+            None => Err(BeanieError::Resolution(format!("Unknown identifier {}", name))),
+        }
+    }
+
+    fn call(&self, name: &str, arguments: Vec<Value<N>>) -> BeanieResult<Value<N>> {
+        if let Some(function) = self.context.functions.get(name) {
+            return Value::broadcast(&arguments, &|arguments| function.call(arguments.to_vec(), self.context, self.builtins));
+        }
+
+        if self.context.has_constant(name) {
+            // a constant followed by brackets, as in x(y + 1), is a multiplication
+            let value = self.name(name)?;
+            return match <[Value<N>; 1]>::try_from(arguments) {
+                Ok([argument]) => self.binary(BinaryOperator::Multiply, &value, &argument),
+                Err(_) => Err(BeanieError::Type(format!("{} is a constant and can not be called", name))),
+            };
+        }
+
+        if let Some(result) = self.matrix_builtin(name, &arguments) {
+            return result;
+        }
+
+        if let Some(result) = self.integer_builtin(name, &arguments) {
+            return result;
+        }
+
+        if let Some(result) = self.measurement_builtin(name, &arguments) {
+            return result;
+        }
+
+        match self.builtins.funcs.get(name) {
+            Some(function) => Value::broadcast(&arguments, &|arguments| {
+                if let Some(result) = self.quantity_builtin(name, arguments) {
+                    return result;
+                }
+                if let Some(result) = self.measured_function(name, arguments) {
+                    return result;
+                }
+
+                let arguments = arguments.iter().map(|argument| Ok(Term::Num(argument.clone().number()?))).collect::<BeanieResult<Vec<Term<N>>>>()?;
+                function.eval(&arguments, self.builtins).map(Value::Number).map_err(|err| self.arithmetic(err))
+            }),
+            None => Err(BeanieError::Resolution(format!("Unknown function {}", name))),
+        }
+    }
+
+    /// `len(list)`, `map(f, list)`, `filter(f, list)` and `reduce(f, list)` or
+    /// `reduce(f, list, initial)`, `f` being the name of a function.
+    fn list_builtin(&self, name: &str, arguments: &[Node]) -> BeanieResult<Value<N>> {
+        let usage = || BeanieError::Type(match name {
+            "len" => format!("len takes a list, in {}", self.text),
+            "reduce" => format!("reduce takes the name of a function of two arguments, a list and optionally the initial value, in {}", self.text),
+            _ => format!("{} takes the name of a function and a list, in {}", name, self.text),
+        });
+
+        if name == "len" {
+            return match arguments {
+                [list] => match self.evaluate(list)? {
+                    Value::List(list) => self.number(list.len() as f64),
+                    _ => Err(usage()),
+                },
+                _ => Err(usage()),
+            };
+        }
+
+        let (function, list, initial) = match arguments {
+            [Node { kind: NodeKind::Name(function), .. }, list] => (function, list, None),
+            [Node { kind: NodeKind::Name(function), .. }, list, initial] if name == "reduce" => (function, list, Some(initial)),
+            _ => return Err(usage()),
+        };
+        if !self.context.has_function(function) && !self.builtins.funcs.contains_key(function) {
+            return Err(BeanieError::Resolution(format!("Unknown function {}", function)));
+        }
+
+        let list = match self.evaluate(list)? {
+            Value::List(list) => list,
+            _ => return Err(usage()),
+        };
+
+        match name {
+            "map" => Ok(Value::List(list.into_iter().map(|element| self.call(function, vec![element])).collect::<BeanieResult<_>>()?)),
+            "filter" => {
+                let mut kept = Vec::new();
+                for element in list {
+                    let holds = match self.call(function, vec![element.clone()])? {
+                        Value::Number(Answer::Single(value)) => self.holds(&value).map_err(|err| self.arithmetic(err))?,
+                        _ => return Err(BeanieError::Type(format!("The function given to filter must give a single value for each element, in {}", self.text))),
+                    };
+                    if holds { kept.push(element); }
+                }
+                Ok(Value::List(kept))
+            }
+            _ => {
+                let mut elements = list.into_iter();
+                let mut result = match initial {
+                    Some(initial) => self.evaluate(initial)?,
+                    None => elements.next().ok_or_else(|| BeanieError::Type(format!("reduce needs an initial value for an empty list, in {}", self.text)))?,
+                };
+                for element in elements {
+                    result = self.call(function, vec![result, element])?;
+                }
+                Ok(result)
+            }
+        }
+    }
+}
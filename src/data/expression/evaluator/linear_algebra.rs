@@ -0,0 +1,349 @@
+use mexprp::{Answer, Calculation};
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::expression::evaluator::Scope;
+use crate::data::expression::syntax_tree::BinaryOperator;
+use crate::data::matrix::Matrix;
+use crate::data::number::BeanieNum;
+use crate::data::value::{matrix_element, Value};
+
+/// Builtins taking matrices, which a function of the file by the same name replaces.
+pub const MATRIX_BUILTINS: [&str; 5] = ["transpose", "det", "inverse", "solve", "eigenvalues"];
+
+/// Sweeps of the Jacobi method after which eigenvalues are given up on.
+const MAX_JACOBI_SWEEPS: usize = 100;
+
+/// Matrices are computed in the number type of the expression, so they are exact for rationals,
+/// except for eigenvalues which are found numerically.
+impl<'a, N: BeanieNum> Scope<'a, N> {
+    /// Multiplying and raising matrices, which are not done element by element. `None` for any
+    /// other operation, which is.
+    pub(super) fn matrix_operation(&self, operator: BinaryOperator, left: &Value<N>, right: &Value<N>) -> Option<BeanieResult<Value<N>>> {
+        let result = match (operator, left, right) {
+            (BinaryOperator::Multiply, Value::Matrix(left), Value::Matrix(right)) => self.product(left, right).map(Value::Matrix),
+            // a list is a column vector on the right of a matrix and a row vector on its left
+            (BinaryOperator::Multiply, Value::Matrix(left), Value::List(right)) => self.vector(right)
+                .and_then(Matrix::column)
+                .and_then(|right| self.product(left, &right))
+                .map(|product| Value::List(product.elements().iter().cloned().map(Value::single).collect())),
+            (BinaryOperator::Multiply, Value::List(left), Value::Matrix(right)) => self.vector(left)
+                .and_then(|left| Matrix::new(vec![left]))
+                .and_then(|left| self.product(&left, right))
+                .map(|product| Value::List(product.elements().iter().cloned().map(Value::single).collect())),
+            (BinaryOperator::Divide, Value::Matrix(_), Value::Matrix(_)) => Err(BeanieError::Type(format!("Matrices can not be divided, multiply by the inverse instead, in {}", self.text))),
+            (BinaryOperator::Power, Value::Matrix(matrix), Value::Number(Answer::Single(exponent))) => self.power(matrix, exponent).map(Value::Matrix),
+            (BinaryOperator::Power, _, Value::Matrix(_)) => Err(BeanieError::Type(format!("Nothing can be raised to a matrix, in {}", self.text))),
+            _ => return None,
+        };
+
+        Some(result)
+    }
+
+    /// Calls the matrix builtin `name`, `None` if there is none by that name.
+    pub(super) fn matrix_builtin(&self, name: &str, arguments: &[Value<N>]) -> Option<BeanieResult<Value<N>>> {
+        let result = match (name, arguments) {
+            ("transpose", [Value::Matrix(matrix)]) => Ok(Value::Matrix(matrix.transpose())),
+            ("det", [Value::Matrix(matrix)]) => self.determinant(matrix).map(Value::single),
+            ("inverse", [Value::Matrix(matrix)]) => self.inverse(matrix).map(Value::Matrix),
+            ("solve", [Value::Matrix(matrix), Value::Matrix(right)]) => self.solve(matrix, right.clone()).map(Value::Matrix),
+            ("solve", [Value::Matrix(matrix), Value::List(right)]) => self.vector(right)
+                .and_then(Matrix::column)
+                .and_then(|right| self.solve(matrix, right))
+                .map(|solution| Value::List(solution.elements().iter().cloned().map(Value::single).collect())),
+            ("eigenvalues", [Value::Matrix(matrix)]) => self.eigenvalues(matrix).map(|values| Value::List(values.into_iter().map(Value::single).collect())),
+            ("solve", _) => Err(BeanieError::Type(format!("solve takes a matrix and a list or matrix of the right-hand sides, in {}", self.text))),
+            (name, _) if MATRIX_BUILTINS.contains(&name) => Err(BeanieError::Type(format!("{} takes a matrix, in {}", name, self.text))),
+            _ => return None,
+        };
+
+        Some(result)
+    }
+
+    fn element(&self, result: Calculation<N>) -> BeanieResult<N> {
+        matrix_element(result.map_err(|err| self.arithmetic(err))?)
+    }
+
+    pub(super) fn constant(&self, value: f64) -> BeanieResult<N> {
+        self.element(N::from_f64(value, self.builtins))
+    }
+
+    fn is_zero(&self, element: &N) -> BeanieResult<bool> {
+        Ok(!self.holds(element).map_err(|err| self.arithmetic(err))?)
+    }
+
+    /// The elements of a list used as a vector, which must all be single numbers.
+    fn vector(&self, list: &[Value<N>]) -> BeanieResult<Vec<N>> {
+        list.iter()
+            .map(|element| match element {
+                Value::Number(Answer::Single(element)) => Ok(element.clone()),
+                element => Err(BeanieError::Type(format!("A list used as a vector must hold single numbers, got {} in {}", element, self.text))),
+            })
+            .collect()
+    }
+
+    fn square(&self, matrix: &Matrix<N>) -> BeanieResult<()> {
+        match matrix.is_square() {
+            true => Ok(()),
+            false => Err(BeanieError::Type(format!("Expected a square matrix, got a {} matrix in {}", matrix.shape(), self.text))),
+        }
+    }
+
+    fn identity(&self, size: usize) -> BeanieResult<Matrix<N>> {
+        let (zero, one) = (self.constant(0f64)?, self.constant(1f64)?);
+        Matrix::new((0..size).map(|row| (0..size).map(|column| if row == column { one.clone() } else { zero.clone() }).collect()).collect())
+    }
+
+    fn product(&self, left: &Matrix<N>, right: &Matrix<N>) -> BeanieResult<Matrix<N>> {
+        if left.columns() != right.rows() {
+            return Err(BeanieError::Type(format!("A {} matrix can not be multiplied by a {} matrix, in {}", left.shape(), right.shape(), self.text)));
+        }
+
+        let mut rows = Vec::with_capacity(left.rows());
+        for row in 0..left.rows() {
+            let mut cells = Vec::with_capacity(right.columns());
+            for column in 0..right.columns() {
+                let mut cell = self.constant(0f64)?;
+                for index in 0..left.columns() {
+                    let term = self.element(left.get(row, index).mul(right.get(index, column), self.builtins))?;
+                    cell = self.element(cell.add(&term, self.builtins))?;
+                }
+                cells.push(cell);
+            }
+            rows.push(cells);
+        }
+
+        Matrix::new(rows)
+    }
+
+    /// `matrix` raised to an integer, a negative one raising its inverse.
+    fn power(&self, matrix: &Matrix<N>, exponent: &N) -> BeanieResult<Matrix<N>> {
+        self.square(matrix)?;
+
+        let exponent = match exponent.to_real_f64() {
+            Some(exponent) if exponent.is_finite() && exponent.fract() == 0f64 => exponent as i64,
+            _ => return Err(BeanieError::Type(format!("A matrix can only be raised to an integer, got {} in {}", exponent, self.text))),
+        };
+
+        // by squaring, multiplying in the square for every bit set in the exponent
+        let mut base = if exponent < 0 { self.inverse(matrix)? } else { matrix.clone() };
+        let mut result = self.identity(matrix.rows())?;
+        let mut remaining = exponent.unsigned_abs();
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = self.product(&result, &base)?;
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                base = self.product(&base, &base)?;
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// The row from `from` down with the element of largest magnitude in `column`, to divide by,
+    /// or any row with one other than 0 when the magnitudes are not real.
+    fn pivot(&self, matrix: &Matrix<N>, column: usize, from: usize) -> BeanieResult<Option<usize>> {
+        let mut pivot: Option<(usize, f64)> = None;
+
+        for row in from..matrix.rows() {
+            let element = matrix.get(row, column);
+            if self.is_zero(element)? { continue; }
+
+            let magnitude = element.to_real_f64().map(f64::abs).unwrap_or(0f64);
+            if pivot.is_none_or(|(_, largest)| magnitude > largest) {
+                pivot = Some((row, magnitude));
+            }
+        }
+
+        Ok(pivot.map(|(row, _)| row))
+    }
+
+    /// The determinant, by Gaussian elimination.
+    fn determinant(&self, matrix: &Matrix<N>) -> BeanieResult<N> {
+        self.square(matrix)?;
+
+        let mut reduced = matrix.clone();
+        let mut determinant = self.constant(1f64)?;
+
+        for column in 0..reduced.columns() {
+            let pivot = match self.pivot(&reduced, column, column)? {
+                Some(pivot) => pivot,
+                None => return self.constant(0f64),
+            };
+            if pivot != column {
+                reduced.swap_rows(pivot, column);
+                determinant = self.element(determinant.mul(&self.constant(-1f64)?, self.builtins))?;
+            }
+
+            let diagonal = reduced.get(column, column).clone();
+            determinant = self.element(determinant.mul(&diagonal, self.builtins))?;
+
+            for row in column + 1..reduced.rows() {
+                let factor = self.element(reduced.get(row, column).div(&diagonal, self.builtins))?;
+                for index in column..reduced.columns() {
+                    let subtracted = self.element(factor.mul(reduced.get(column, index), self.builtins))?;
+                    let element = self.element(reduced.get(row, index).sub(&subtracted, self.builtins))?;
+                    reduced.set(row, index, element);
+                }
+            }
+        }
+
+        Ok(determinant)
+    }
+
+    fn inverse(&self, matrix: &Matrix<N>) -> BeanieResult<Matrix<N>> {
+        self.square(matrix)?;
+        self.solve(matrix, self.identity(matrix.rows())?)
+    }
+
+    /// The matrix `x` for which `matrix * x` is `right`, by Gauss-Jordan elimination.
+    fn solve(&self, matrix: &Matrix<N>, mut right: Matrix<N>) -> BeanieResult<Matrix<N>> {
+        self.square(matrix)?;
+        if right.rows() != matrix.rows() {
+            return Err(BeanieError::Type(format!("A system with a {} matrix needs {} right-hand sides per column, got a {} matrix in {}", matrix.shape(), matrix.rows(), right.shape(), self.text)));
+        }
+
+        let mut reduced = matrix.clone();
+
+        for column in 0..reduced.columns() {
+            let pivot = self.pivot(&reduced, column, column)?
+                .ok_or_else(|| BeanieError::Type(format!("The matrix {} is singular, in {}", matrix, self.text)))?;
+            reduced.swap_rows(pivot, column);
+            right.swap_rows(pivot, column);
+
+            let diagonal = reduced.get(column, column).clone();
+            for index in 0..reduced.columns() {
+                reduced.set(column, index, self.element(reduced.get(column, index).div(&diagonal, self.builtins))?);
+            }
+            for index in 0..right.columns() {
+                right.set(column, index, self.element(right.get(column, index).div(&diagonal, self.builtins))?);
+            }
+
+            for row in (0..reduced.rows()).filter(|row| *row != column) {
+                let factor = reduced.get(row, column).clone();
+                if self.is_zero(&factor)? { continue; }
+
+                for index in 0..reduced.columns() {
+                    let subtracted = self.element(factor.mul(reduced.get(column, index), self.builtins))?;
+                    reduced.set(row, index, self.element(reduced.get(row, index).sub(&subtracted, self.builtins))?);
+                }
+                for index in 0..right.columns() {
+                    let subtracted = self.element(factor.mul(right.get(column, index), self.builtins))?;
+                    right.set(row, index, self.element(right.get(row, index).sub(&subtracted, self.builtins))?);
+                }
+            }
+        }
+
+        Ok(right)
+    }
+
+    /// The eigenvalues of a real symmetric matrix in ascending order, by the Jacobi method. They are
+    /// found as f64 whatever the number type.
+    fn eigenvalues(&self, matrix: &Matrix<N>) -> BeanieResult<Vec<N>> {
+        self.square(matrix)?;
+        if *matrix != matrix.transpose() {
+            return Err(BeanieError::Type(format!("Eigenvalues are only found for symmetric matrices, got {} in {}", matrix, self.text)));
+        }
+
+        let size = matrix.rows();
+        let mut reduced = (0..size)
+            .map(|row| (0..size)
+                .map(|column| matrix.get(row, column).to_real_f64()
+                    .ok_or_else(|| BeanieError::Type(format!("Eigenvalues are only found for real matrices, got {} in {}", matrix, self.text))))
+                .collect::<BeanieResult<Vec<f64>>>())
+            .collect::<BeanieResult<Vec<Vec<f64>>>>()?;
+
+        let total: f64 = reduced.iter().flatten().map(|element| element * element).sum();
+        let converged = |reduced: &Vec<Vec<f64>>| {
+            let off_diagonal: f64 = (0..size).flat_map(|p| (0..size).filter(move |q| *q != p).map(move |q| (p, q)))
+                .map(|(p, q)| reduced[p][q] * reduced[p][q])
+                .sum();
+            off_diagonal <= f64::EPSILON * f64::EPSILON * total
+        };
+
+        for _ in 0..MAX_JACOBI_SWEEPS {
+            if converged(&reduced) {
+                break;
+            }
+
+            for p in 0..size {
+                for q in p + 1..size {
+                    if reduced[p][q] == 0f64 { continue; }
+
+                    // the rotation in the plane of p and q that zeroes reduced[p][q]
+                    let theta = (reduced[q][q] - reduced[p][p]) / (2f64 * reduced[p][q]);
+                    let tangent = match theta == 0f64 {
+                        true => 1f64,
+                        false => theta.signum() / (theta.abs() + (theta * theta + 1f64).sqrt()),
+                    };
+                    let cosine = 1f64 / (tangent * tangent + 1f64).sqrt();
+                    let sine = tangent * cosine;
+
+                    for row in reduced.iter_mut() {
+                        let (at_p, at_q) = (row[p], row[q]);
+                        row[p] = cosine * at_p - sine * at_q;
+                        row[q] = sine * at_p + cosine * at_q;
+                    }
+                    let (above, below) = reduced.split_at_mut(q);
+                    for (at_p, at_q) in above[p].iter_mut().zip(below[0].iter_mut()) {
+                        (*at_p, *at_q) = (cosine * *at_p - sine * *at_q, sine * *at_p + cosine * *at_q);
+                    }
+                }
+            }
+        }
+
+        if !converged(&reduced) {
+            return Err(BeanieError::Type(format!("The eigenvalues of {} did not converge within {} Jacobi sweeps, in {}", matrix, MAX_JACOBI_SWEEPS, self.text)));
+        }
+
+        let mut eigenvalues: Vec<f64> = (0..size).map(|index| reduced[index][index]).collect();
+        eigenvalues.sort_by(|a, b| a.total_cmp(b));
+        eigenvalues.into_iter().map(|eigenvalue| self.constant(eigenvalue)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rug::Rational;
+    use crate::data::context::BeanieRuntimeContext;
+    use crate::data::expression::evaluator;
+    use crate::data::expression::BeanieExpression;
+    use crate::data::number::{big_float, NumberType};
+    use super::*;
+
+    fn rational(text: &str) -> Value<Rational> {
+        let context = BeanieRuntimeContext::new(String::from("test.bn"));
+        let builtins = evaluator::builtins::<Rational>(big_float::DEFAULT_PRECISION);
+        BeanieExpression::math(text.to_string(), Some(NumberType::Rational), &[])
+            .evaluate(&context, &builtins)
+            .unwrap()
+    }
+
+    fn fraction(numerator: i32, denominator: i32) -> Rational {
+        Rational::from((numerator, denominator))
+    }
+
+    #[test]
+    fn determinants_of_rationals_are_exact() {
+        let determinant = rational("det([1/3, 1/7; 1/5, 1/11])").number().unwrap().unwrap_single();
+        assert_eq!(determinant, fraction(2, 1155));
+
+        let singular = rational("det([1/3, 2/3; 1/2, 1])").number().unwrap().unwrap_single();
+        assert_eq!(singular, fraction(0, 1));
+    }
+
+    #[test]
+    fn systems_of_rationals_are_solved_exactly() {
+        let solution = match rational("solve([2, 1; 1, 3], [1, 2])") {
+            Value::List(solution) => solution.into_iter().map(|x| x.number().unwrap().unwrap_single()).collect::<Vec<_>>(),
+            value => panic!("Expected a list, got {}", value),
+        };
+        assert_eq!(solution, vec![fraction(1, 5), fraction(3, 5)]);
+
+        let inverse = match rational("inverse([2, 1; 1, 3])") {
+            Value::Matrix(inverse) => inverse.elements().to_vec(),
+            value => panic!("Expected a matrix, got {}", value),
+        };
+        assert_eq!(inverse, vec![fraction(3, 5), fraction(-1, 5), fraction(-1, 5), fraction(2, 5)]);
+    }
+}
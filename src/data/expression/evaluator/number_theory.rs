@@ -0,0 +1,74 @@
+use mexprp::{Answer, MathError};
+use rug::integer::IsPrime;
+use rug::Integer;
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::expression::evaluator::Scope;
+use crate::data::number::BeanieNum;
+use crate::data::value::Value;
+
+/// Builtins taking integers, which a function of the file by the same name replaces.
+pub const INTEGER_BUILTINS: [&str; 8] = ["factorial", "gcd", "lcm", "powmod", "is_prime", "next_prime", "binomial", "div"];
+
+/// Rounds of the Miller-Rabin test is_prime does on numbers too large to be certain about, each
+/// letting a composite number through with a probability of at most 1/4.
+const PRIME_TEST_ROUNDS: u32 = 30;
+
+/// The builtins compute with rug integers whatever the number type of the expression, so they are
+/// exact for integers and rationals, and take any number that is an integer.
+impl<'a, N: BeanieNum> Scope<'a, N> {
+    /// Calls the integer builtin `name` for every element of lists among the arguments, `None` if
+    /// there is none by that name.
+    pub(super) fn integer_builtin(&self, name: &str, arguments: &[Value<N>]) -> Option<BeanieResult<Value<N>>> {
+        if !INTEGER_BUILTINS.contains(&name) {
+            return None;
+        }
+        Some(Value::broadcast(arguments, &|arguments| self.integer_function(name, arguments)))
+    }
+
+    /// The integer builtin `name` of single values, as `n!` is `factorial(n)`.
+    pub(super) fn integer_function(&self, name: &str, arguments: &[Value<N>]) -> BeanieResult<Value<N>> {
+        let arity = match name {
+            "powmod" => 3,
+            "gcd" | "lcm" | "binomial" | "div" => 2,
+            _ => 1,
+        };
+        if arguments.len() != arity {
+            return Err(BeanieError::Type(format!("{} takes {} integers but is given {} arguments, in {}", name, arity, arguments.len(), self.text)));
+        }
+
+        let integers = arguments.iter()
+            .map(|argument| match argument {
+                Value::Number(Answer::Single(value)) => value.to_integer()
+                    .ok_or_else(|| BeanieError::Type(format!("{} takes integers, got {} in {}", name, value, self.text))),
+                argument => Err(BeanieError::Type(format!("{} takes integers, got {} in {}", name, argument, self.text))),
+            })
+            .collect::<BeanieResult<Vec<Integer>>>()?;
+
+        let result = match (name, integers.as_slice()) {
+            ("factorial", [n]) => Integer::from(Integer::factorial(self.small_integer(name, n)?)),
+            ("gcd", [a, b]) => a.clone().gcd(b),
+            ("lcm", [a, b]) => a.clone().lcm(b),
+            ("powmod", [_, _, modulus]) if *modulus == 0 => return Err(self.arithmetic(MathError::DivideByZero)),
+            ("powmod", [base, exponent, modulus]) => base.clone().pow_mod(exponent, modulus)
+                .map_err(|_| BeanieError::Type(format!("{} has no inverse modulo {} to raise to {}, in {}", base, modulus, exponent, self.text)))?,
+            ("is_prime", [n]) => Integer::from((n.is_probably_prime(PRIME_TEST_ROUNDS) != IsPrime::No) as i32),
+            ("next_prime", [n]) => n.clone().next_prime(),
+            ("binomial", [n, k]) => n.clone().binomial(self.small_integer(name, k)?),
+            ("div", [_, divisor]) if *divisor == 0 => return Err(self.arithmetic(MathError::DivideByZero)),
+            ("div", [dividend, divisor]) => dividend.clone().div_rem_floor(divisor.clone()).0,
+            _ => unreachable!("the arity of {} is checked above", name),
+        };
+
+        N::from_integer(result, self.builtins)
+            .map(Value::Number)
+            .map_err(|err| self.arithmetic(err))
+    }
+
+    /// An argument such as the n of n!, which must not be negative and is at most 2^32 - 1.
+    fn small_integer(&self, name: &str, integer: &Integer) -> BeanieResult<u32> {
+        integer.to_u32().ok_or_else(|| match integer.cmp0().is_lt() {
+            true => BeanieError::Type(format!("{} is not defined for the negative {}, in {}", name, integer, self.text)),
+            false => BeanieError::Type(format!("{} of {} is too large, in {}", name, integer, self.text)),
+        })
+    }
+}
@@ -0,0 +1,205 @@
+use mexprp::Context;
+use mexprp::num::{ComplexFloat, ComplexRugRat};
+use rug::{Complex, Rational};
+use crate::data::context::BeanieRuntimeContext;
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::expression::evaluator::{self, Scope};
+use crate::data::expression::syntax_tree::{Node, NodeKind};
+use crate::data::expression::BeanieExpression;
+use crate::data::function::Function;
+use crate::data::number::big_float::BigFloat;
+use crate::data::number::big_integer::BigInteger;
+use crate::data::number::interval::Interval;
+use crate::data::number::{self, BeanieNum, NumberType};
+use crate::data::value::Value;
+
+/// Builtins converting a value to another number type, which a function of the file by the same
+/// name replaces. `as_decimal(x)` gives floats or intervals of the precision of an expression of
+/// those.
+pub const CONVERSION_BUILTINS: [&str; 3] = ["as_rational", "as_decimal", "as_complex"];
+
+/// The number type `expression` is evaluated as in `context`, the lowest type holding the types of
+/// its parts, so a constant keeps its meaning wherever it is used. Numbers and builtins are of the
+/// data type of the expression, constants of the type their own expression is evaluated as, and
+/// calls of the types of the function and of its arguments, the parameters of a function being of
+/// the type it is called with. A conversion such as `as_rational(x)` is of the type it converts to,
+/// whatever the type of its argument. An expression without any of those parts, as the name of a
+/// parameter alone, is of its own data type.
+///
+/// `calling` holds the functions whose types are being found, so recursive calls are skipped.
+pub fn expression_type(expression: &BeanieExpression, context: &BeanieRuntimeContext, calling: &mut Vec<String>) -> Option<NumberType> {
+    let math = match expression {
+        BeanieExpression::Math(math) => math,
+        _ => return None,
+    };
+    let data_type = math.number_type?;
+
+    match math.tree() {
+        Ok(tree) => Some(node_type(tree, data_type, context, calling).unwrap_or(data_type)),
+        Err(_) => Some(data_type),
+    }
+}
+
+/// The number type of `function` called from `context`, found in the file the function is written
+/// in.
+pub fn function_type(function: &Function, context: &BeanieRuntimeContext, calling: &mut Vec<String>) -> Option<NumberType> {
+    let file_context = function.external_context.as_ref().unwrap_or(context);
+    let key = format!("{}:{}", file_context.beanie_file_path, function.name);
+    if calling.contains(&key) {
+        return None;
+    }
+
+    calling.push(key);
+    let number_type = expression_type(&function.expression, file_context, calling);
+    calling.pop();
+    number_type
+}
+
+/// The type of the parts of `node`, `None` when it has none, `data_type` being the type of its
+/// numbers and builtins.
+fn node_type(node: &Node, data_type: NumberType, context: &BeanieRuntimeContext, calling: &mut Vec<String>) -> Option<NumberType> {
+    let mut number_type = None;
+
+    node.visit_until(&mut |node| {
+        let (part, below) = match &node.kind {
+            NodeKind::Number(_) => (Some(data_type), false),
+            NodeKind::Name(name) => (name_type(name, data_type, context, calling), false),
+            NodeKind::Call(name, _) if context.has_function(name) => (context.function_type(name, calling), true),
+            NodeKind::Call(name, _) if CONVERSION_BUILTINS.contains(&name.as_str()) => (Some(target(name, data_type)), false),
+            NodeKind::Call(name, _) => (name_type(name, data_type, context, calling), true),
+            _ => (None, true),
+        };
+
+        number_type = number::join(number_type, part);
+        below
+    });
+
+    number_type
+}
+
+/// The type of a constant, of a function passed by name as to map, or else of a builtin.
+fn name_type(name: &str, data_type: NumberType, context: &BeanieRuntimeContext, calling: &mut Vec<String>) -> Option<NumberType> {
+    match context.get_constant_names(name) {
+        Some((names, _)) => context.constant_type(names).ok().flatten(),
+        None if context.has_function(name) => context.function_type(name, calling),
+        None => Some(data_type),
+    }
+}
+
+/// The type the conversion builtin `name` converts to within an expression of `data_type`, which
+/// `as_decimal` keeps for floats and intervals.
+fn target(name: &str, data_type: NumberType) -> NumberType {
+    match (name, data_type) {
+        ("as_rational", _) => NumberType::Rational,
+        ("as_complex", _) => NumberType::Complex,
+        (_, NumberType::Float(_) | NumberType::Interval(_)) => data_type,
+        _ => NumberType::Decimal,
+    }
+}
+
+impl<'a, N: BeanieNum> Scope<'a, N> {
+    /// The conversion builtin `name`. Its argument is evaluated as the lowest type holding its own,
+    /// the one converted to and the type of the expression, which its parameters are of. So
+    /// `as_decimal(sqrt(2))` computes the root as a decimal within a rational expression, and
+    /// `as_rational(x)` gives the rational a decimal constant `x` is stored as.
+    pub(super) fn conversion(&self, name: &str, arguments: &[Node]) -> BeanieResult<Value<N>> {
+        let argument = match arguments {
+            [argument] => argument,
+            _ => return Err(BeanieError::Type(format!("{} takes 1 argument but is given {}, in {}", name, arguments.len(), self.text))),
+        };
+
+        let own_type = N::number_type(self.builtins);
+        let target = target(name, own_type);
+        let number_type = node_type(argument, own_type, self.context, &mut Vec::new())
+            .map_or(target, |number_type| number_type.join(target))
+            .join(own_type);
+        let precision = number_type.precision();
+
+        match number_type {
+            NumberType::Integer => self.converted(argument, target, &evaluator::builtins::<BigInteger>(precision)),
+            NumberType::Rational => self.converted(argument, target, &evaluator::builtins::<Rational>(precision)),
+            NumberType::Decimal => self.converted(argument, target, &evaluator::builtins::<f64>(precision)),
+            NumberType::Interval(_) => self.converted(argument, target, &evaluator::builtins::<Interval>(precision)),
+            NumberType::Float(_) => self.converted(argument, target, &evaluator::builtins::<BigFloat>(precision)),
+            NumberType::ComplexRational => self.converted(argument, target, &evaluator::builtins::<ComplexRugRat>(precision)),
+            NumberType::ImaginaryDecimal => self.converted(argument, target, &evaluator::builtins::<ComplexFloat>(precision)),
+            NumberType::Complex => self.converted(argument, target, &evaluator::builtins::<Complex>(precision)),
+        }
+    }
+
+    /// Evaluates `argument` with `builtins`, then converts it to `target` and to the type of the
+    /// expression.
+    fn converted<M: BeanieNum>(&self, argument: &Node, target: NumberType, builtins: &Context<M>) -> BeanieResult<Value<N>> {
+        let arguments = self.arguments.iter().map(|argument| argument.convert(builtins)).collect::<BeanieResult<Vec<Value<M>>>>()?;
+        let scope = Scope {
+            text: self.text,
+            span: self.span,
+            context: self.context,
+            arguments: &arguments,
+            builtins,
+        };
+        let value = scope.evaluate(argument)?;
+        let precision = target.precision();
+
+        match target {
+            NumberType::Integer => value.convert(&evaluator::builtins::<BigInteger>(precision))?.convert(self.builtins),
+            NumberType::Rational => value.convert(&evaluator::builtins::<Rational>(precision))?.convert(self.builtins),
+            NumberType::Decimal => value.convert(&evaluator::builtins::<f64>(precision))?.convert(self.builtins),
+            NumberType::Interval(_) => value.convert(&evaluator::builtins::<Interval>(precision))?.convert(self.builtins),
+            NumberType::Float(_) => value.convert(&evaluator::builtins::<BigFloat>(precision))?.convert(self.builtins),
+            NumberType::ComplexRational => value.convert(&evaluator::builtins::<ComplexRugRat>(precision))?.convert(self.builtins),
+            NumberType::ImaginaryDecimal => value.convert(&evaluator::builtins::<ComplexFloat>(precision))?.convert(self.builtins),
+            NumberType::Complex => value.convert(&evaluator::builtins::<Complex>(precision))?.convert(self.builtins),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn math(text: &str, number_type: NumberType) -> BeanieExpression {
+        BeanieExpression::math(text.to_string(), Some(number_type), &[])
+    }
+
+    fn context(constants: &[(&str, &str, NumberType)]) -> BeanieRuntimeContext {
+        let mut context = BeanieRuntimeContext::new(String::from("test.bn"));
+        for (name, text, number_type) in constants {
+            context.insert_constant(vec![name.to_string()], math(text, *number_type));
+        }
+        context
+    }
+
+    #[test]
+    fn an_expression_is_of_its_own_type_without_constants() {
+        let context = context(&[]);
+
+        assert_eq!(expression_type(&math("1 + 2", NumberType::Integer), &context, &mut Vec::new()), Some(NumberType::Integer));
+        assert_eq!(expression_type(&math("1 + 2", NumberType::Float(256)), &context, &mut Vec::new()), Some(NumberType::Float(256)));
+    }
+
+    #[test]
+    fn constants_promote_the_expressions_using_them() {
+        let context = context(&[
+            ("n", "2", NumberType::Integer),
+            ("q", "1 / 3", NumberType::Rational),
+            ("x", "0.5", NumberType::Decimal),
+            ("f", "1.5", NumberType::Float(256)),
+        ]);
+
+        assert_eq!(expression_type(&math("n + 1", NumberType::Integer), &context, &mut Vec::new()), Some(NumberType::Integer));
+        assert_eq!(expression_type(&math("n + q", NumberType::Integer), &context, &mut Vec::new()), Some(NumberType::Rational));
+        assert_eq!(expression_type(&math("n + q + x", NumberType::Integer), &context, &mut Vec::new()), Some(NumberType::Decimal));
+        assert_eq!(expression_type(&math("n * f", NumberType::Integer), &context, &mut Vec::new()), Some(NumberType::Float(256)));
+        assert_eq!(expression_type(&math("x + f + 1", NumberType::Float(512)), &context, &mut Vec::new()), Some(NumberType::Float(512)));
+    }
+
+    #[test]
+    fn conversions_are_of_the_type_they_convert_to() {
+        let context = context(&[("x", "0.5", NumberType::Decimal)]);
+
+        assert_eq!(expression_type(&math("as_rational(x)", NumberType::Integer), &context, &mut Vec::new()), Some(NumberType::Rational));
+        assert_eq!(expression_type(&math("as_decimal(1) + 1", NumberType::Integer), &context, &mut Vec::new()), Some(NumberType::Decimal));
+        assert_eq!(expression_type(&math("as_decimal(1)", NumberType::Interval(256)), &context, &mut Vec::new()), Some(NumberType::Interval(256)));
+    }
+}
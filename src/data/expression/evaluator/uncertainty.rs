@@ -0,0 +1,296 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use mexprp::{Answer, Calculation, Term};
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::expression::evaluator::{self, Scope};
+use crate::data::expression::syntax_tree::{BinaryOperator, UnaryOperator};
+use crate::data::measured::Measured;
+use crate::data::number::BeanieNum;
+use crate::data::value::Value;
+
+/// Builtins of measured numbers, which a function of the file by the same name replaces.
+/// `nominal(x)` is the value without its uncertainty and `uncertainty(x)` the standard uncertainty.
+pub const MEASUREMENT_BUILTINS: [&str; 2] = ["nominal", "uncertainty"];
+
+/// The uncertainty of a result is propagated to first order: each part of the uncertainty of an
+/// operand is multiplied by the derivative of the result with respect to that operand, and parts
+/// coming from the same measurement are added before they are combined with the others.
+impl<'a, N: BeanieNum> Scope<'a, N> {
+    /// `value +- uncertainty`, a new measurement.
+    pub(super) fn measurement(&self, value: &Value<N>, uncertainty: &Value<N>) -> BeanieResult<Value<N>> {
+        match (value, uncertainty) {
+            (Value::Number(Answer::Single(value)), Value::Number(Answer::Single(uncertainty))) => {
+                if let Ok(Ordering::Less) = uncertainty.tryord(&self.constant(0f64)?, self.builtins) {
+                    return Err(BeanieError::Type(format!("An uncertainty can not be negative, got {} in {}", uncertainty, self.text)));
+                }
+                Ok(Value::Measured(Measured::measurement(value.clone(), uncertainty.clone())))
+            }
+            (Value::Measured(measured), _) => Err(BeanieError::Type(format!("{} already has an uncertainty, in {}", measured, self.text))),
+            _ => Err(BeanieError::Type(format!("Only a single number can have an uncertainty, as in 9.81 +- 0.02, got {} +- {} in {}", value, uncertainty, self.text))),
+        }
+    }
+
+    /// Arithmetic on single values at least one of which is measured. Comparisons compare the
+    /// values without their uncertainties.
+    pub(super) fn measured_operation(&self, operator: BinaryOperator, left: &Value<N>, right: &Value<N>) -> BeanieResult<Value<N>> {
+        let (a, a_parts) = self.measured_parts(left)?;
+        let (b, b_parts) = self.measured_parts(right)?;
+        let ctx = self.builtins;
+
+        let value = match operator {
+            BinaryOperator::PlusMinus => return Err(BeanieError::Type(format!("± can not be applied to measured values, which are single numbers, in {}", self.text))),
+            BinaryOperator::Less | BinaryOperator::LessEqual | BinaryOperator::Greater | BinaryOperator::GreaterEqual | BinaryOperator::Equal | BinaryOperator::NotEqual => {
+                return self.numbers(operator, &Answer::Single(a), &Answer::Single(b)).map(Value::Number);
+            }
+            operator => self.single(self.numbers(operator, &Answer::Single(a.clone()), &Answer::Single(b.clone()))?)?,
+        };
+
+        let mut parts = BTreeMap::new();
+        match operator {
+            BinaryOperator::Add => {
+                self.propagate(&mut parts, &a_parts, || self.constant(1f64))?;
+                self.propagate(&mut parts, &b_parts, || self.constant(1f64))?;
+            }
+            BinaryOperator::Subtract => {
+                self.propagate(&mut parts, &a_parts, || self.constant(1f64))?;
+                self.propagate(&mut parts, &b_parts, || self.constant(-1f64))?;
+            }
+            BinaryOperator::Multiply => {
+                self.propagate(&mut parts, &a_parts, || Ok(b.clone()))?;
+                self.propagate(&mut parts, &b_parts, || Ok(a.clone()))?;
+            }
+            BinaryOperator::Divide => {
+                self.propagate(&mut parts, &a_parts, || self.calculate(self.constant(1f64)?.div(&b, ctx)))?;
+                self.propagate(&mut parts, &b_parts, || self.calculate(self.calculate(value.div(&b, ctx))?.mul(&self.constant(-1f64)?, ctx)))?;
+            }
+            BinaryOperator::Power => {
+                // b a^(b - 1) and a^b ln a
+                self.propagate(&mut parts, &a_parts, || {
+                    let exponent = self.calculate(b.sub(&self.constant(1f64)?, ctx))?;
+                    self.calculate(self.calculate(a.pow(&exponent, ctx))?.mul(&b, ctx))
+                })?;
+                self.propagate(&mut parts, &b_parts, || self.calculate(self.calculate(a.ln(ctx))?.mul(&value, ctx)))?;
+            }
+            BinaryOperator::Modulo => {
+                // a - b floor(a / b)
+                self.propagate(&mut parts, &a_parts, || self.constant(1f64))?;
+                self.propagate(&mut parts, &b_parts, || {
+                    let quotient = self.calculate(self.calculate(a.div(&b, ctx))?.floor(ctx))?;
+                    self.calculate(quotient.mul(&self.constant(-1f64)?, ctx))
+                })?;
+            }
+            _ => unreachable!("comparisons and ± are handled above, and +- and logic by the evaluator"),
+        }
+
+        Ok(Value::Measured(Measured { value, parts }))
+    }
+
+    /// `-x` of a measured number.
+    pub(super) fn measured_unary(&self, operator: UnaryOperator, measured: &Measured<N>) -> BeanieResult<Value<N>> {
+        match operator {
+            UnaryOperator::Negate => {
+                let negative_one = self.constant(-1f64)?;
+                let mut parts = BTreeMap::new();
+                self.propagate(&mut parts, &measured.parts, || Ok(negative_one.clone()))?;
+                Ok(Value::Measured(Measured { value: self.calculate(measured.value.mul(&negative_one, self.builtins))?, parts }))
+            }
+            UnaryOperator::PlusMinus => Err(BeanieError::Type(format!("± can not be applied to the measured value {}, in {}", measured, self.text))),
+            UnaryOperator::Not => Err(BeanieError::Type(format!("not takes a plain number, got the measured value {} in {}", measured, self.text))),
+            UnaryOperator::Factorial => unreachable!("evaluated as the factorial builtin"),
+        }
+    }
+
+    /// `nominal(x)` and `uncertainty(x)`, for every element of lists among the arguments, `None`
+    /// for any other builtin.
+    pub(super) fn measurement_builtin(&self, name: &str, arguments: &[Value<N>]) -> Option<BeanieResult<Value<N>>> {
+        if !MEASUREMENT_BUILTINS.contains(&name) {
+            return None;
+        }
+
+        Some(Value::broadcast(arguments, &|arguments| match (name, arguments) {
+            ("nominal", [Value::Measured(measured)]) => Ok(Value::single(measured.value.clone())),
+            ("uncertainty", [Value::Measured(measured)]) => match measured.uncertainty() {
+                Some(uncertainty) => self.constant(uncertainty).map(Value::single),
+                None => Err(BeanieError::Type(format!("The uncertainty of {} is not real, in {}", measured, self.text))),
+            },
+            ("nominal", [value @ Value::Number(Answer::Single(_))]) => Ok(value.clone()),
+            ("uncertainty", [Value::Number(Answer::Single(_))]) => self.constant(0f64).map(Value::single),
+            _ => Err(BeanieError::Type(format!("{} takes a single number, in {}", name, self.text))),
+        }))
+    }
+
+    /// A mexprp builtin of single values at least one of which is measured, `None` when none is.
+    pub(super) fn measured_function(&self, name: &str, arguments: &[Value<N>]) -> Option<BeanieResult<Value<N>>> {
+        if !arguments.iter().any(|argument| matches!(argument, Value::Measured(_))) {
+            return None;
+        }
+        Some(self.measured_call(name, arguments))
+    }
+
+    /// Square roots are taken as the principal root, so that a measured value stays a single one.
+    fn measured_call(&self, name: &str, arguments: &[Value<N>]) -> BeanieResult<Value<N>> {
+        let builtins = evaluator::principal_builtins::<N>(self.builtins.cfg.precision);
+        let ctx = builtins.as_ref();
+        let one = self.constant(1f64)?;
+        let arguments = arguments.iter().map(|argument| self.measured_parts(argument)).collect::<BeanieResult<Vec<_>>>()?;
+
+        if name == "max" || name == "min" {
+            let wanted = if name == "max" { Ordering::Greater } else { Ordering::Less };
+            let mut extreme = &arguments[0];
+            for argument in &arguments[1..] {
+                if argument.0.tryord(&extreme.0, ctx).map_err(|err| self.arithmetic(err))? == wanted {
+                    extreme = argument;
+                }
+            }
+            return Ok(Value::Measured(Measured { value: extreme.0.clone(), parts: extreme.1.clone() }));
+        }
+
+        let value = match ctx.funcs.get(name) {
+            Some(function) => {
+                let terms: Vec<Term<N>> = arguments.iter().map(|(value, _)| Term::Num(Answer::Single(value.clone()))).collect();
+                self.calculate(function.eval(&terms, ctx))?
+            }
+            None => return Err(BeanieError::Resolution(format!("Unknown function {}", name))),
+        };
+
+        let mut parts = BTreeMap::new();
+        match (name, arguments.as_slice()) {
+            (_, [(x, x_parts)]) => self.propagate(&mut parts, x_parts, || match name {
+                "sin" => self.calculate(x.cos(ctx)),
+                "cos" => self.calculate(self.calculate(x.sin(ctx))?.mul(&self.constant(-1f64)?, ctx)),
+                // 1 + tan² x
+                "tan" => self.calculate(self.calculate(value.mul(&value, ctx))?.add(&one, ctx)),
+                // ±1 / sqrt(1 - x²)
+                "asin" | "acos" => {
+                    let root = self.calculate(self.calculate(one.sub(&self.calculate(x.mul(x, ctx))?, ctx))?.sqrt(ctx))?;
+                    let sign = self.constant(if name == "asin" { 1f64 } else { -1f64 })?;
+                    self.calculate(sign.div(&root, ctx))
+                }
+                "atan" => self.calculate(one.div(&self.calculate(self.calculate(x.mul(x, ctx))?.add(&one, ctx))?, ctx)),
+                "sqrt" => self.calculate(one.div(&self.calculate(value.add(&value, ctx))?, ctx)),
+                "ln" => self.calculate(one.div(x, ctx)),
+                "abs" => match x.tryord(&self.constant(0f64)?, ctx) {
+                    Ok(Ordering::Less) => self.constant(-1f64),
+                    _ => Ok(one.clone()),
+                },
+                "floor" | "ceil" | "round" => self.constant(0f64),
+                _ => Err(self.not_propagated(name)),
+            })?,
+            // the angle of the point (x, y): x / (x² + y²) and -y / (x² + y²)
+            ("atan2", [(y, y_parts), (x, x_parts)]) => {
+                let squares = self.calculate(self.calculate(x.mul(x, ctx))?.add(&self.calculate(y.mul(y, ctx))?, ctx))?;
+                self.propagate(&mut parts, y_parts, || self.calculate(x.div(&squares, ctx)))?;
+                self.propagate(&mut parts, x_parts, || self.calculate(self.calculate(y.div(&squares, ctx))?.mul(&self.constant(-1f64)?, ctx)))?;
+            }
+            // ln x / ln b: 1 / (x ln b) and -log(x, b) / (b ln b)
+            ("log", [(x, x_parts), (base, base_parts)]) => {
+                let ln_base = self.calculate(base.ln(ctx))?;
+                self.propagate(&mut parts, x_parts, || self.calculate(one.div(&self.calculate(x.mul(&ln_base, ctx))?, ctx)))?;
+                self.propagate(&mut parts, base_parts, || {
+                    let denominator = self.calculate(self.calculate(base.mul(&ln_base, ctx))?.mul(&self.constant(-1f64)?, ctx))?;
+                    self.calculate(value.div(&denominator, ctx))
+                })?;
+            }
+            // x^(1 / n): x^(1 / n) / (n x) and -x^(1 / n) ln x / n²
+            ("nrt", [(x, x_parts), (n, n_parts)]) => {
+                self.propagate(&mut parts, x_parts, || self.calculate(value.div(&self.calculate(n.mul(x, ctx))?, ctx)))?;
+                self.propagate(&mut parts, n_parts, || {
+                    let numerator = self.calculate(self.calculate(value.mul(&self.calculate(x.ln(ctx))?, ctx))?.mul(&self.constant(-1f64)?, ctx))?;
+                    self.calculate(numerator.div(&self.calculate(n.mul(n, ctx))?, ctx))
+                })?;
+            }
+            _ => return Err(self.not_propagated(name)),
+        }
+
+        Ok(Value::Measured(Measured { value, parts }))
+    }
+
+    /// Adds the parts of the uncertainty of an operand, times the derivative of the result with
+    /// respect to it, to those of the result. The derivative is only worked out when the operand
+    /// has an uncertainty, so that ln a is not needed for a^2.
+    fn propagate<F: FnOnce() -> BeanieResult<N>>(&self, into: &mut BTreeMap<usize, N>, parts: &BTreeMap<usize, N>, derivative: F) -> BeanieResult<()> {
+        if parts.is_empty() {
+            return Ok(());
+        }
+
+        let derivative = derivative()?;
+        for (measurement, part) in parts {
+            let part = self.calculate(derivative.mul(part, self.builtins))?;
+            let part = match into.remove(measurement) {
+                Some(other) => self.calculate(other.add(&part, self.builtins))?,
+                None => part,
+            };
+            into.insert(*measurement, part);
+        }
+        Ok(())
+    }
+
+    /// The value and the parts of the uncertainty of a single number, which has none when it is
+    /// not measured.
+    fn measured_parts(&self, value: &Value<N>) -> BeanieResult<(N, BTreeMap<usize, N>)> {
+        match value {
+            Value::Number(Answer::Single(number)) => Ok((number.clone(), BTreeMap::new())),
+            Value::Measured(measured) => Ok((measured.value.clone(), measured.parts.clone())),
+            value => Err(BeanieError::Type(format!("{} can not be combined with a measured value, in {}", value, self.text))),
+        }
+    }
+
+    /// The single number a calculation on measured values gives.
+    fn calculate(&self, calculation: Calculation<N>) -> BeanieResult<N> {
+        self.single(calculation.map_err(|err| self.arithmetic(err))?)
+    }
+
+    fn single(&self, answer: Answer<N>) -> BeanieResult<N> {
+        match answer {
+            Answer::Single(value) => Ok(value),
+            Answer::Multiple(values) => Err(BeanieError::Type(format!("A measured value must be a single number, got {} values in {}", values.len(), self.text))),
+        }
+    }
+
+    fn not_propagated(&self, name: &str) -> BeanieError {
+        BeanieError::Type(format!("{} does not propagate uncertainties, in {}", name, self.text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::context::BeanieRuntimeContext;
+    use crate::data::expression::BeanieExpression;
+    use crate::data::number::{big_float, NumberType};
+    use super::*;
+
+    fn math(text: &str) -> BeanieExpression {
+        BeanieExpression::math(text.to_string(), Some(NumberType::Decimal), &[])
+    }
+
+    fn context() -> BeanieRuntimeContext {
+        let mut context = BeanieRuntimeContext::new(String::from("test.bn"));
+        context.insert_constant(vec![String::from("g")], math("9.81 +- 0.02"));
+        context.insert_constant(vec![String::from("h")], math("9.81 +- 0.02"));
+        context
+    }
+
+    fn uncertainty(text: &str, context: &BeanieRuntimeContext) -> f64 {
+        let builtins = evaluator::builtins::<f64>(big_float::DEFAULT_PRECISION);
+        match math(text).evaluate(context, &builtins).unwrap() {
+            Value::Measured(measured) => measured.uncertainty().unwrap(),
+            value => panic!("Expected a measured value from {}, got {}", text, value),
+        }
+    }
+
+    #[test]
+    fn a_measurement_used_twice_is_correlated_with_itself() {
+        let context = context();
+
+        assert_eq!(uncertainty("g - g", &context), 0f64);
+        assert!((uncertainty("2 * g - g", &context) - 0.02).abs() < 1e-12);
+        assert!((uncertainty("g * g", &context) - 2f64 * 9.81 * 0.02).abs() < 1e-12);
+    }
+
+    #[test]
+    fn independent_measurements_add_in_quadrature() {
+        let context = context();
+
+        assert!((uncertainty("g - h", &context) - 0.02 * 2f64.sqrt()).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,209 @@
+use mexprp::{Answer, Calculation, Term};
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::expression::evaluator::Scope;
+use crate::data::expression::syntax_tree::BinaryOperator;
+use crate::data::number::BeanieNum;
+use crate::data::unit::{Dimension, Unit};
+use crate::data::value::Value;
+
+/// Powers of ten tried for writing the scale of a unit as a fraction, so that converting rationals
+/// between units stays exact.
+const MAX_SCALE_DIGITS: i32 = 12;
+
+/// A quantity keeps the unit it was written in through addition and through multiplying by plain
+/// numbers, the other side being converted to it. Multiplying or dividing two quantities gives one
+/// in SI units, which have a name where there is one, as in kN·m giving J.
+impl<'a, N: BeanieNum> Scope<'a, N> {
+    /// One of `unit`, which a number before it multiplies, as in 5 m.
+    pub(super) fn unit(&self, unit: Unit) -> BeanieResult<Value<N>> {
+        let one = N::from_f64(1f64, self.builtins).map_err(|err| self.arithmetic(err))?;
+        Ok(Value::Quantity(one, unit))
+    }
+
+    /// `value to unit`, for every element of a list.
+    pub(super) fn convert(&self, value: &Value<N>, unit: &Unit) -> BeanieResult<Value<N>> {
+        Value::broadcast(std::slice::from_ref(value), &|values| match &values[0] {
+            Value::Quantity(number, from) if from.dimension == unit.dimension => Ok(Value::Quantity(self.rescaled(number, from.scale, unit.scale)?, unit.clone())),
+            Value::Quantity(_, from) => Err(BeanieError::Type(format!("A quantity in {} can not be converted to {}, in {}", from, unit, self.text))),
+            value => Err(BeanieError::Type(format!("Only quantities can be converted to {}, got {} in {}", unit, value, self.text))),
+        })
+    }
+
+    /// Arithmetic on single values at least one of which is a quantity.
+    pub(super) fn quantity_operation(&self, operator: BinaryOperator, left: &Value<N>, right: &Value<N>) -> BeanieResult<Value<N>> {
+        let (left_number, left_unit) = self.quantity_parts(left)?;
+        let (right_number, right_unit) = self.quantity_parts(right)?;
+
+        match operator {
+            BinaryOperator::Multiply | BinaryOperator::Divide => {
+                let number = self.numbers(operator, left_number, right_number)?;
+                let sign = if operator == BinaryOperator::Multiply { 1 } else { -1 };
+
+                match (left_unit, right_unit) {
+                    (Some(unit), None) => Ok(Value::Quantity(number, unit.clone())),
+                    (None, Some(unit)) if sign > 0 => Ok(Value::Quantity(number, unit.clone())),
+                    (Some(left), Some(right)) if sign > 0 && left == right => self.quantity(number, left.powi(2)),
+                    _ => {
+                        let none = Unit::si([0; 7]);
+                        let left = left_unit.unwrap_or(&none);
+                        let right = right_unit.unwrap_or(&none);
+
+                        let number = self.rescaled(&number, left.scale, 1f64)?;
+                        let number = match sign {
+                            1 => self.rescaled(&number, right.scale, 1f64)?,
+                            _ => self.rescaled(&number, 1f64, right.scale)?,
+                        };
+                        Ok(self.si_quantity(number, left.combined_dimension(right, sign)))
+                    }
+                }
+            }
+            BinaryOperator::Power => match (left_unit, right) {
+                (Some(unit), Value::Number(Answer::Single(exponent))) => match exponent.to_real_f64() {
+                    Some(exponent) if exponent.fract() == 0f64 => self.quantity(self.numbers(operator, left_number, right_number)?, unit.powi(exponent as i32)),
+                    _ => Err(BeanieError::Type(format!("A quantity can only be raised to an integer, got {} in {}", exponent, self.text))),
+                },
+                (Some(_), _) => Err(BeanieError::Type(format!("A quantity can only be raised to a single integer, got {} in {}", right, self.text))),
+                (None, _) => Err(BeanieError::Type(format!("An exponent can not have a unit, got {} in {}", right, self.text))),
+            },
+            // adding, taking remainders and comparing, which need the same dimension on both sides
+            _ => match (left_unit, right_unit) {
+                (Some(left), Some(right)) if left.dimension == right.dimension => {
+                    let right_number = self.rescaled(right_number, right.scale, left.scale)?;
+                    let number = self.numbers(operator, left_number, &right_number)?;
+
+                    match operator {
+                        BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::PlusMinus | BinaryOperator::Modulo => Ok(Value::Quantity(number, left.clone())),
+                        _ => Ok(Value::Number(number)),
+                    }
+                }
+                _ => Err(BeanieError::Type(format!("Can not combine {} with {} as their dimensions differ, in {}", describe(left_unit), describe(right_unit), self.text))),
+            },
+        }
+    }
+
+    /// `abs` and `sqrt` of a quantity. `None` for any other builtin, or when no argument is a
+    /// quantity.
+    pub(super) fn quantity_builtin(&self, name: &str, arguments: &[Value<N>]) -> Option<BeanieResult<Value<N>>> {
+        let (number, unit) = match arguments {
+            [Value::Quantity(number, unit)] if name == "abs" || name == "sqrt" => (number, unit),
+            _ => return None,
+        };
+        let apply = |number: Answer<N>| match self.builtins.funcs.get(name) {
+            Some(function) => function.eval(&[Term::Num(number)], self.builtins).map_err(|err| self.arithmetic(err)),
+            None => Err(BeanieError::Resolution(format!("Unknown function {}", name))),
+        };
+
+        let result = match name {
+            "abs" => apply(number.clone()).map(|number| Value::Quantity(number, unit.clone())),
+            _ => match unit.sqrt() {
+                Some(root) => self.rescaled(number, unit.scale, root.scale.powi(2))
+                    .and_then(apply)
+                    .map(|number| Value::Quantity(number, root)),
+                None => Err(BeanieError::Type(format!("The square root of a quantity in {} has no unit, in {}", unit, self.text))),
+            },
+        };
+
+        Some(result)
+    }
+
+    fn quantity_parts<'v>(&self, value: &'v Value<N>) -> BeanieResult<(&'v Answer<N>, Option<&'v Unit>)> {
+        match value {
+            Value::Number(number) => Ok((number, None)),
+            Value::Quantity(number, unit) => Ok((number, Some(unit))),
+            value => Err(BeanieError::Type(format!("{} can not be combined with a quantity, in {}", value, self.text))),
+        }
+    }
+
+    /// `number` of `unit`, which is a plain number when the unit has no dimension, as in m^0.
+    fn quantity(&self, number: Answer<N>, unit: Unit) -> BeanieResult<Value<N>> {
+        match unit.is_dimensionless() {
+            true => self.rescaled(&number, unit.scale, 1f64).map(Value::Number),
+            false => Ok(Value::Quantity(number, unit)),
+        }
+    }
+
+    /// A number in SI units of `dimension`, which is a plain number when the units cancel out.
+    fn si_quantity(&self, number: Answer<N>, dimension: Dimension) -> Value<N> {
+        let unit = Unit::si(dimension);
+        match unit.is_dimensionless() {
+            true => Value::Number(number),
+            false => Value::Quantity(number, unit),
+        }
+    }
+
+    /// `number` in a unit of scale `from` given in a unit of scale `to`.
+    fn rescaled(&self, number: &Answer<N>, from: f64, to: f64) -> BeanieResult<Answer<N>> {
+        if from == to {
+            return Ok(number.clone());
+        }
+
+        let factor = self.scale(from).and_then(|from| from.op(&self.scale(to)?, |a, b| a.div(b, self.builtins)));
+        factor.and_then(|factor| number.op(&factor, |a, b| a.mul(b, self.builtins)))
+            .map_err(|err| self.arithmetic(err))
+    }
+
+    /// The scale of a unit as a fraction of integers where it has few enough digits, as 0.3048 for
+    /// ft is, so that it is exact for rationals.
+    fn scale(&self, scale: f64) -> Calculation<N> {
+        let digits = (0..=MAX_SCALE_DIGITS)
+            .map(|digits| 10f64.powi(digits))
+            .find(|power| {
+                let scaled = scale * power;
+                (scaled - scaled.round()).abs() <= scaled.abs() * 1e-12
+            });
+
+        match digits {
+            Some(power) => N::from_f64((scale * power).round(), self.builtins)?.op(&N::from_f64(power, self.builtins)?, |a, b| a.div(b, self.builtins)),
+            None => N::from_f64(scale, self.builtins),
+        }
+    }
+}
+
+/// A unit, or what a plain number is called, for messages.
+fn describe(unit: Option<&Unit>) -> String {
+    match unit {
+        Some(unit) => format!("a quantity in {}", unit),
+        None => String::from("a plain number"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::context::BeanieRuntimeContext;
+    use crate::data::expression::evaluator;
+    use crate::data::expression::BeanieExpression;
+    use crate::data::number::{big_float, NumberType};
+    use super::*;
+
+    fn evaluate(text: &str) -> BeanieResult<Value<f64>> {
+        let context = BeanieRuntimeContext::new(String::from("test.bn"));
+        let builtins = evaluator::builtins::<f64>(big_float::DEFAULT_PRECISION);
+        BeanieExpression::math(text.to_string(), Some(NumberType::Decimal), &[]).evaluate(&context, &builtins)
+    }
+
+    fn dimension_error(text: &str) -> String {
+        match evaluate(text) {
+            Err(BeanieError::Type(message)) => message,
+            result => panic!("Expected a dimension error from {}, got {:?}", text, result),
+        }
+    }
+
+    #[test]
+    fn quantities_of_different_dimensions_do_not_add() {
+        assert!(dimension_error("5 m + 3 s").contains("dimensions differ"));
+        assert!(dimension_error("5 m - 3").contains("dimensions differ"));
+        assert!(dimension_error("5 m < 3 s").contains("dimensions differ"));
+        assert!(dimension_error("5 m to s").contains("can not be converted"));
+    }
+
+    #[test]
+    fn quantities_of_the_same_dimension_add_in_the_unit_of_the_left() {
+        match evaluate("5 m + 20 cm").unwrap() {
+            Value::Quantity(Answer::Single(number), unit) => {
+                assert!((number - 5.2).abs() < 1e-12);
+                assert_eq!(unit, Unit::named("m").unwrap());
+            }
+            value => panic!("Expected a quantity, got {}", value),
+        }
+    }
+}
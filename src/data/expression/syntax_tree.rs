@@ -0,0 +1,894 @@
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::CharIndices;
+use crate::data::error::BeanieError;
+use crate::data::unit::Unit;
+
+/// A math expression parsed once, with the parameters of the function it belongs to already
+/// resolved, so evaluating it only has to walk the tree.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    /// The bytes of the parsed text the node was read from, to point errors at it.
+    pub range: Range<usize>,
+}
+
+/// Trees are the same whatever the spacing of the text they were parsed from.
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        self.kind == other.kind
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    /// A number literal as written, so that each number type reads it exactly.
+    Number(String),
+    /// A parameter of the surrounding function, by position. The name is kept for messages.
+    Parameter(usize, String),
+    /// A constant, function or builtin, looked up in the runtime context when evaluated.
+    Name(String),
+    Call(String, Vec<Node>),
+    Unary(UnaryOperator, Box<Node>),
+    Binary(BinaryOperator, Box<Node>, Box<Node>),
+    /// `{ a if c; b otherwise }` or `if(c, a, b)`, the value of the first case whose condition
+    /// holds, else of the otherwise case.
+    Piecewise(Vec<Case>, Option<Box<Node>>),
+    /// `sum(i, a, b, body)` or `prod(i, a, b, body)`, the body taken for every integer `i` from `a`
+    /// to `b`. Within the body the index is a parameter, following those of the surrounding
+    /// function and of the series the body is in.
+    Series {
+        operator: SeriesOperator,
+        index: String,
+        from: Box<Node>,
+        to: Box<Node>,
+        body: Box<Node>,
+    },
+    /// `[a, b, c]`.
+    List(Vec<Node>),
+    /// `[a, b; c, d]`, by rows, which all have the same number of elements.
+    Matrix(Vec<Vec<Node>>),
+    /// `a..b`, the list of the integers from `a` to `b`.
+    Range(Box<Node>, Box<Node>),
+    /// `a to km/h`, the quantity `a` in the unit, which is read when parsing so that a constant
+    /// named like a unit does not stand in for it.
+    Convert(Box<Node>, Unit),
+}
+
+/// An expression that could not be parsed, with the bytes of its text that are wrong.
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub error: BeanieError,
+    pub range: Range<usize>,
+}
+
+type ParseResult<T> = Result<T, SyntaxError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    pub value: Node,
+    pub condition: Node,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeriesOperator {
+    Sum,
+    Product,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Negate,
+    /// `±a`, giving both `a` and `-a`.
+    PlusMinus,
+    Not,
+    /// `n!`, written after the operand.
+    Factorial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    /// `a ± b`, giving both `a + b` and `a - b`.
+    PlusMinus,
+    /// `a +- b`, the measured value `a` of standard uncertainty `b`.
+    Uncertainty,
+    Multiply,
+    Divide,
+    Power,
+    /// `a mod b`, the remainder of dividing `a` by `b`, of the sign of `b`.
+    Modulo,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+/// Words that can not be used as names, as they are part of conditions, piecewise expressions, unit
+/// conversions and arithmetic.
+const KEYWORDS: [&str; 7] = ["and", "or", "not", "if", "otherwise", "to", "mod"];
+
+impl Node {
+    /// Parses `text`, turning the names in `parameters` into [`NodeKind::Parameter`]s.
+    pub fn parse(text: &str, parameters: &[String]) -> ParseResult<Node> {
+        let (tokens, ranges) = tokenize(text)?.into_iter().unzip();
+        let mut parser = Parser {
+            text,
+            tokens,
+            ranges,
+            position: 0,
+            parameters: parameters.to_vec(),
+        };
+
+        let node = parser.expression()?;
+        match parser.peek() {
+            Some(token) => Err(parser.error(format!("Unexpected {}", token.describe()).as_str())),
+            None => Ok(node),
+        }
+    }
+
+    /// Calls `visitor` on this node and every node below it.
+    pub fn visit<F: FnMut(&Node)>(&self, visitor: &mut F) {
+        self.visit_until(&mut |node| {
+            visitor(node);
+            true
+        });
+    }
+
+    /// Calls `visitor` on this node, then on the nodes below it if `visitor` returns true for it.
+    pub fn visit_until<F: FnMut(&Node) -> bool>(&self, visitor: &mut F) {
+        if !visitor(self) { return; }
+
+        match &self.kind {
+            NodeKind::Call(_, arguments) => arguments.iter().for_each(|argument| argument.visit_until(visitor)),
+            NodeKind::Unary(_, operand) => operand.visit_until(visitor),
+            NodeKind::Binary(_, left, right) => {
+                left.visit_until(visitor);
+                right.visit_until(visitor);
+            }
+            NodeKind::Piecewise(cases, otherwise) => {
+                for case in cases {
+                    case.value.visit_until(visitor);
+                    case.condition.visit_until(visitor);
+                }
+                if let Some(otherwise) = otherwise {
+                    otherwise.visit_until(visitor);
+                }
+            }
+            NodeKind::Series { from, to, body, .. } => {
+                from.visit_until(visitor);
+                to.visit_until(visitor);
+                body.visit_until(visitor);
+            }
+            NodeKind::List(elements) => elements.iter().for_each(|element| element.visit_until(visitor)),
+            NodeKind::Matrix(rows) => rows.iter().flatten().for_each(|element| element.visit_until(visitor)),
+            NodeKind::Range(from, to) => {
+                from.visit_until(visitor);
+                to.visit_until(visitor);
+            }
+            NodeKind::Convert(value, _) => value.visit_until(visitor),
+            NodeKind::Number(_) | NodeKind::Parameter(_, _) | NodeKind::Name(_) => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(String),
+    Identifier(String),
+    Keyword(&'static str),
+    Operator(char),
+    Comparison(BinaryOperator, &'static str),
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Semicolon,
+    Range,
+    /// `+-`, written without a space, which is not an addition of a negative number.
+    Uncertainty,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Number(number) => format!("number {}", number),
+            Token::Identifier(name) => format!("name {}", name),
+            Token::Keyword(keyword) => keyword.to_string(),
+            Token::Operator(operator) => format!("operator {}", operator),
+            Token::Comparison(_, symbol) => format!("comparison {}", symbol),
+            Token::LeftParen => String::from("("),
+            Token::RightParen => String::from(")"),
+            Token::LeftBrace => String::from("{"),
+            Token::RightBrace => String::from("}"),
+            Token::LeftBracket => String::from("["),
+            Token::RightBracket => String::from("]"),
+            Token::Range => String::from(".."),
+            Token::Comma => String::from(","),
+            Token::Semicolon => String::from(";"),
+            Token::Uncertainty => String::from("+-"),
+        }
+    }
+
+    /// Whether the token can start an operand written right after another one, as in `2x` or
+    /// `3(x + 1)`, which is read as a multiplication.
+    fn starts_implicit_operand(&self) -> bool {
+        matches!(self, Token::Number(_) | Token::Identifier(_) | Token::LeftParen)
+    }
+}
+
+/// The length of the exponent `text` starts with, such as the e-3 of 1.5e-3, `None` when it starts
+/// with none. An e not followed by digits is the constant e, as in 2e.
+fn exponent_length(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix(['e', 'E'])?;
+    let unsigned = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+    let digits = unsigned.len() - unsigned.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+    (digits > 0).then_some(text.len() - unsigned.len() + digits)
+}
+
+/// The tokens of `text`, each with the bytes it was read from.
+fn tokenize(text: &str) -> ParseResult<Vec<(Token, Range<usize>)>> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<CharIndices> = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '.' if chars.next_if(|(_, next)| *next == '.').is_some() => Token::Range,
+            '0'..='9' | '.' => {
+                let mut end = start + c.len_utf8();
+                while let Some((index, c)) = chars.peek().copied() {
+                    if !c.is_ascii_digit() && c != '.' { break; }
+                    // the .. of a range such as 1..10
+                    if c == '.' && text[index + 1..].starts_with('.') { break; }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+
+                if let Some(length) = exponent_length(&text[end..]) {
+                    end += length;
+                    while chars.next_if(|(index, _)| *index < end).is_some() {}
+                }
+
+                let number = &text[start..end];
+                if number.parse::<f64>().is_err() {
+                    return Err(syntax_error(text, start..end, format!("{} is not a number", number).as_str()));
+                }
+                Token::Number(number.to_string())
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut end = start + c.len_utf8();
+                while let Some((index, c)) = chars.peek().copied() {
+                    if !c.is_ascii_alphanumeric() && c != '_' { break; }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+
+                let word = &text[start..end];
+                match KEYWORDS.iter().find(|keyword| **keyword == word) {
+                    Some(keyword) => Token::Keyword(keyword),
+                    None => Token::Identifier(word.to_string()),
+                }
+            }
+            '+' if chars.next_if(|(_, next)| *next == '-').is_some() => Token::Uncertainty,
+            '+' | '-' | '*' | '/' | '^' | '±' => Token::Operator(c),
+            // as units are printed, as in kg·m^2
+            '·' => Token::Operator('*'),
+            '<' | '>' | '=' | '!' => {
+                let or_equal = chars.next_if(|(_, next)| *next == '=').is_some();
+
+                match (c, or_equal) {
+                    ('<', false) => Token::Comparison(BinaryOperator::Less, "<"),
+                    ('<', true) => Token::Comparison(BinaryOperator::LessEqual, "<="),
+                    ('>', false) => Token::Comparison(BinaryOperator::Greater, ">"),
+                    ('>', true) => Token::Comparison(BinaryOperator::GreaterEqual, ">="),
+                    ('=', true) => Token::Comparison(BinaryOperator::Equal, "=="),
+                    ('!', true) => Token::Comparison(BinaryOperator::NotEqual, "!="),
+                    ('!', false) => Token::Operator('!'),
+                    _ => return Err(syntax_error(text, start..start + 1, format!("Unexpected character {}, comparisons are written == and !=", c).as_str())),
+                }
+            }
+            '≤' => Token::Comparison(BinaryOperator::LessEqual, "≤"),
+            '≥' => Token::Comparison(BinaryOperator::GreaterEqual, "≥"),
+            '≠' => Token::Comparison(BinaryOperator::NotEqual, "≠"),
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '{' => Token::LeftBrace,
+            '}' => Token::RightBrace,
+            '[' => Token::LeftBracket,
+            ']' => Token::RightBracket,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            c => return Err(syntax_error(text, start..start + c.len_utf8(), format!("Unexpected character {}", c).as_str())),
+        };
+
+        let end = chars.peek().map_or(text.len(), |(index, _)| *index);
+        tokens.push((token, start..end));
+    }
+
+    Ok(tokens)
+}
+
+/// An error in `text`, pointing at the bytes `range` of it.
+fn syntax_error(text: &str, range: Range<usize>, message: &str) -> SyntaxError {
+    SyntaxError {
+        error: BeanieError::Parse(format!("Invalid expression {}. {}", text, message)),
+        range,
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<Token>,
+    /// The bytes of the text each token was read from.
+    ranges: Vec<Range<usize>>,
+    /// The index of the next token, past the last one once the end was read.
+    position: usize,
+    /// The parameters of the function, followed by the indices of the series being parsed.
+    parameters: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn error_at(&self, range: Range<usize>, message: &str) -> SyntaxError {
+        syntax_error(self.text, range, message)
+    }
+
+    /// An error pointing at the next token, or at the end of the text when there is none.
+    fn error(&self, message: &str) -> SyntaxError {
+        self.error_at(self.start()..self.ranges.get(self.position).map_or(self.text.len(), |range| range.end), message)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    /// Where the next token starts.
+    fn start(&self) -> usize {
+        self.ranges.get(self.position).map_or(self.text.len(), |range| range.start)
+    }
+
+    /// Where the token read last ends.
+    fn end(&self) -> usize {
+        self.ranges[..self.position.min(self.ranges.len())].last().map_or(0, |range| range.end)
+    }
+
+    /// The bytes of the token read last, or the end of the text once it was read.
+    fn previous(&self) -> Range<usize> {
+        match self.position.checked_sub(1).and_then(|index| self.ranges.get(index)) {
+            Some(range) => range.clone(),
+            None => self.text.len()..self.text.len(),
+        }
+    }
+
+    /// A node read from `start` up to the token read last.
+    fn node(&self, start: usize, kind: NodeKind) -> Node {
+        Node { kind, range: start..self.end() }
+    }
+
+    fn expression(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+        let value = self.disjunction()?;
+
+        if self.eat(&Token::Keyword("to")) {
+            let unit = self.unit()?;
+            return Ok(self.node(start, NodeKind::Convert(Box::new(value), unit)));
+        }
+        Ok(value)
+    }
+
+    /// The unit after `to`, made of unit names multiplied, divided and raised to integers, as in
+    /// kN·m or m/s^2.
+    fn unit(&mut self) -> ParseResult<Unit> {
+        let mut unit = self.unit_factor()?;
+
+        loop {
+            let sign = match self.peek() {
+                Some(Token::Operator('*')) => 1,
+                Some(Token::Operator('/')) => -1,
+                Some(Token::Identifier(_)) | Some(Token::LeftParen) => {
+                    unit = unit.combine(&self.unit_factor()?, 1);
+                    continue;
+                }
+                _ => return Ok(unit),
+            };
+            self.next();
+
+            unit = unit.combine(&self.unit_factor()?, sign);
+        }
+    }
+
+    fn unit_factor(&mut self) -> ParseResult<Unit> {
+        let unit = match self.next() {
+            Some(Token::Identifier(name)) => Unit::named(&name).ok_or_else(|| self.error_at(self.previous(), format!("Unknown unit {}", name).as_str()))?,
+            Some(Token::LeftParen) => {
+                let unit = self.unit()?;
+                if !self.eat(&Token::RightParen) {
+                    return Err(self.error("Missing )"));
+                }
+                unit
+            }
+            _ => return Err(self.error_at(self.previous(), "Expected a unit after to, as in x to km/h")),
+        };
+
+        if !self.eat(&Token::Operator('^')) {
+            return Ok(unit);
+        }
+        let negative = self.eat(&Token::Operator('-'));
+        match self.next() {
+            Some(Token::Number(exponent)) => match exponent.parse::<i32>() {
+                Ok(exponent) => Ok(unit.powi(if negative { -exponent } else { exponent })),
+                Err(_) => Err(self.error_at(self.previous(), "A unit can only be raised to an integer")),
+            },
+            _ => Err(self.error_at(self.previous(), "A unit can only be raised to an integer")),
+        }
+    }
+
+    fn disjunction(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+        let mut node = self.conjunction()?;
+
+        while self.eat(&Token::Keyword("or")) {
+            let right = self.conjunction()?;
+            node = self.node(start, NodeKind::Binary(BinaryOperator::Or, Box::new(node), Box::new(right)));
+        }
+        Ok(node)
+    }
+
+    fn conjunction(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+        let mut node = self.negation()?;
+
+        while self.eat(&Token::Keyword("and")) {
+            let right = self.negation()?;
+            node = self.node(start, NodeKind::Binary(BinaryOperator::And, Box::new(node), Box::new(right)));
+        }
+        Ok(node)
+    }
+
+    fn negation(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+
+        if self.eat(&Token::Keyword("not")) {
+            let operand = self.negation()?;
+            return Ok(self.node(start, NodeKind::Unary(UnaryOperator::Not, Box::new(operand))));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+        let left = self.range()?;
+
+        let operator = match self.peek() {
+            Some(Token::Comparison(operator, _)) => *operator,
+            _ => return Ok(left),
+        };
+        self.next();
+
+        let right = self.range()?;
+        if let Some(Token::Comparison(_, _)) = self.peek() {
+            // 0 < x < 1 would otherwise compare the result of 0 < x with 1
+            return Err(self.error("Comparisons can not be chained, join them with and"));
+        }
+
+        Ok(self.node(start, NodeKind::Binary(operator, Box::new(left), Box::new(right))))
+    }
+
+    fn range(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+        let from = self.additive()?;
+
+        if self.eat(&Token::Range) {
+            let to = self.additive()?;
+            return Ok(self.node(start, NodeKind::Range(Box::new(from), Box::new(to))));
+        }
+        Ok(from)
+    }
+
+    fn additive(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+        let mut node = self.multiplicative()?;
+
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Operator('+')) => BinaryOperator::Add,
+                Some(Token::Operator('-')) => BinaryOperator::Subtract,
+                Some(Token::Operator('±')) => BinaryOperator::PlusMinus,
+                Some(Token::Uncertainty) => BinaryOperator::Uncertainty,
+                _ => return Ok(node),
+            };
+            self.next();
+
+            let right = self.multiplicative()?;
+            node = self.node(start, NodeKind::Binary(operator, Box::new(node), Box::new(right)));
+        }
+    }
+
+    fn multiplicative(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+        let mut node = self.quantity()?;
+
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Operator('*')) => BinaryOperator::Multiply,
+                Some(Token::Operator('/')) => BinaryOperator::Divide,
+                Some(Token::Keyword("mod")) => BinaryOperator::Modulo,
+                Some(token) if token.starts_implicit_operand() => {
+                    let right = self.power()?;
+                    node = self.node(start, NodeKind::Binary(BinaryOperator::Multiply, Box::new(node), Box::new(right)));
+                    continue;
+                }
+                _ => return Ok(node),
+            };
+            self.next();
+
+            let right = self.quantity()?;
+            node = self.node(start, NodeKind::Binary(operator, Box::new(node), Box::new(right)));
+        }
+    }
+
+    /// An operand followed by the units it is in, as in `2 h` or `3 kg m^2`, which bind tighter
+    /// than a division so that `100 km / 2 h` is a speed.
+    fn quantity(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+        let mut node = self.unary()?;
+
+        while self.at_unit() {
+            let unit = self.power()?;
+            node = self.node(start, NodeKind::Binary(BinaryOperator::Multiply, Box::new(node), Box::new(unit)));
+        }
+        Ok(node)
+    }
+
+    /// Whether the next token names a unit, rather than a parameter or a function called.
+    fn at_unit(&self) -> bool {
+        match self.peek() {
+            Some(Token::Identifier(name)) => Unit::is_named(name)
+                && !self.parameters.contains(name)
+                && self.tokens.get(self.position + 1) != Some(&Token::LeftParen),
+            _ => false,
+        }
+    }
+
+    fn unary(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+        let operator = match self.peek() {
+            Some(Token::Operator('-')) => Some(UnaryOperator::Negate),
+            Some(Token::Operator('±')) => Some(UnaryOperator::PlusMinus),
+            Some(Token::Operator('+')) => None,
+            _ => return self.power(),
+        };
+        self.next();
+
+        let operand = self.unary()?;
+        Ok(match operator {
+            Some(operator) => self.node(start, NodeKind::Unary(operator, Box::new(operand))),
+            None => operand,
+        })
+    }
+
+    fn power(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+        let mut base = self.primary()?;
+
+        // binds before the power, so 2^3! is 2^6
+        while self.eat(&Token::Operator('!')) {
+            base = self.node(start, NodeKind::Unary(UnaryOperator::Factorial, Box::new(base)));
+        }
+
+        if self.eat(&Token::Operator('^')) {
+            // right associative, and the exponent may have a sign of its own as in 2^-1
+            let exponent = self.unary()?;
+            return Ok(self.node(start, NodeKind::Binary(BinaryOperator::Power, Box::new(base), Box::new(exponent))));
+        }
+
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> ParseResult<Node> {
+        let start = self.start();
+
+        match self.next() {
+            Some(Token::Number(number)) => Ok(self.node(start, NodeKind::Number(number))),
+            Some(Token::Identifier(name)) if self.peek() == Some(&Token::LeftParen) && (name == "sum" || name == "prod") => {
+                self.series(start, if name == "sum" { SeriesOperator::Sum } else { SeriesOperator::Product })
+            }
+            Some(Token::Identifier(name)) => {
+                // an index shadows a parameter or outer index of the same name
+                let parameter = self.parameters.iter().rposition(|parameter| *parameter == name);
+
+                if self.peek() != Some(&Token::LeftParen) {
+                    return Ok(self.node(start, match parameter {
+                        Some(index) => NodeKind::Parameter(index, name),
+                        None => NodeKind::Name(name),
+                    }));
+                }
+
+                let name_range = self.previous();
+                let arguments = self.arguments()?;
+                match parameter {
+                    // a parameter can not be called, so p(x + 1) multiplies
+                    Some(index) => match <[Node; 1]>::try_from(arguments) {
+                        Ok([argument]) => {
+                            let parameter = Node { kind: NodeKind::Parameter(index, name), range: name_range };
+                            Ok(self.node(start, NodeKind::Binary(BinaryOperator::Multiply, Box::new(parameter), Box::new(argument))))
+                        }
+                        Err(_) => Err(self.error_at(start..self.end(), format!("{} is a parameter and can not be called", name).as_str())),
+                    },
+                    None => Ok(self.node(start, NodeKind::Call(name, arguments))),
+                }
+            }
+            Some(Token::LeftParen) => {
+                let node = self.expression()?;
+                if !self.eat(&Token::RightParen) {
+                    return Err(self.error("Missing )"));
+                }
+                Ok(node)
+            }
+            Some(Token::LeftBrace) => self.piecewise(start),
+            Some(Token::LeftBracket) => self.list(start),
+            Some(Token::Keyword("if")) if self.peek() == Some(&Token::LeftParen) => {
+                match <[Node; 3]>::try_from(self.arguments()?) {
+                    Ok([condition, value, otherwise]) => Ok(self.node(start, NodeKind::Piecewise(vec![Case { value, condition }], Some(Box::new(otherwise))))),
+                    Err(_) => Err(self.error_at(start..self.end(), "if takes a condition, its value if it holds and its value otherwise")),
+                }
+            }
+            Some(token) => Err(self.error_at(self.previous(), format!("Unexpected {}", token.describe()).as_str())),
+            None => Err(self.error("Unexpected end of expression")),
+        }
+    }
+
+    fn arguments(&mut self) -> ParseResult<Vec<Node>> {
+        self.next();
+
+        let mut arguments = Vec::new();
+        if self.eat(&Token::RightParen) {
+            return Ok(arguments);
+        }
+
+        loop {
+            arguments.push(self.expression()?);
+
+            if self.eat(&Token::RightParen) {
+                return Ok(arguments);
+            }
+            if !self.eat(&Token::Comma) {
+                return Err(self.error("Missing ) after the arguments"));
+            }
+        }
+    }
+
+    /// A list `[a, b, c]`, or a matrix `[a, b; c, d]` when its elements are split into rows by
+    /// semicolons, after the opening bracket at `start`.
+    fn list(&mut self, start: usize) -> ParseResult<Node> {
+        let mut rows = vec![Vec::new()];
+        let mut is_matrix = false;
+
+        if self.eat(&Token::RightBracket) {
+            return Ok(self.node(start, NodeKind::List(Vec::new())));
+        }
+
+        loop {
+            rows.last_mut().unwrap().push(self.expression()?);
+
+            if self.eat(&Token::Semicolon) {
+                is_matrix = true;
+                if self.eat(&Token::RightBracket) { break; }
+                rows.push(Vec::new());
+                continue;
+            }
+            if self.eat(&Token::RightBracket) { break; }
+            if !self.eat(&Token::Comma) {
+                return Err(self.error("Missing ] after the elements of the list"));
+            }
+        }
+
+        if !is_matrix {
+            return Ok(self.node(start, NodeKind::List(rows.pop().unwrap())));
+        }
+        if rows.iter().any(|row| row.len() != rows[0].len()) {
+            return Err(self.error_at(start..self.end(), "The rows of a matrix must have the same number of elements"));
+        }
+        Ok(self.node(start, NodeKind::Matrix(rows)))
+    }
+
+    /// The arguments of `sum(i, a, b, body)` or `prod(i, a, b, body)`, after the name at `start`.
+    fn series(&mut self, start: usize, operator: SeriesOperator) -> ParseResult<Node> {
+        self.next();
+
+        let usage = "sum and prod take an index, its first and last value, and the expression summed or multiplied";
+        let index = match (self.next(), self.next()) {
+            (Some(Token::Identifier(index)), Some(Token::Comma)) => index,
+            _ => return Err(self.error_at(self.previous(), usage)),
+        };
+
+        let from = self.expression()?;
+        if !self.eat(&Token::Comma) { return Err(self.error(usage)); }
+        let to = self.expression()?;
+        if !self.eat(&Token::Comma) { return Err(self.error(usage)); }
+
+        self.parameters.push(index.clone());
+        let body = self.expression();
+        self.parameters.pop();
+        let body = body?;
+
+        if !self.eat(&Token::RightParen) { return Err(self.error(usage)); }
+
+        Ok(self.node(start, NodeKind::Series {
+            operator,
+            index,
+            from: Box::new(from),
+            to: Box::new(to),
+            body: Box::new(body),
+        }))
+    }
+
+    /// The cases of `{ a if c; b if d; e otherwise }`, after the opening brace at `start`.
+    fn piecewise(&mut self, start: usize) -> ParseResult<Node> {
+        let mut cases = Vec::new();
+
+        loop {
+            let value = self.expression()?;
+
+            if self.eat(&Token::Keyword("otherwise")) {
+                self.eat(&Token::Semicolon);
+                if !self.eat(&Token::RightBrace) {
+                    return Err(self.error("The otherwise case must be the last one"));
+                }
+                return Ok(self.node(start, NodeKind::Piecewise(cases, Some(Box::new(value)))));
+            }
+
+            if !self.eat(&Token::Keyword("if")) {
+                return Err(self.error("Missing if or otherwise after the value of a case"));
+            }
+            cases.push(Case { value, condition: self.expression()? });
+
+            let separated = self.eat(&Token::Semicolon);
+            if self.eat(&Token::RightBrace) {
+                return Ok(self.node(start, NodeKind::Piecewise(cases, None)));
+            }
+            if !separated {
+                return Err(self.error("Missing ; between the cases"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Node {
+        Node::parse(text, &[String::from("x")]).unwrap()
+    }
+
+    /// A node of `kind`, its range left out as trees are compared without it.
+    fn node(kind: NodeKind) -> Box<Node> {
+        Box::new(Node { kind, range: 0..0 })
+    }
+
+    fn number(text: &str) -> Box<Node> {
+        node(NodeKind::Number(text.to_string()))
+    }
+
+    fn x() -> Box<Node> {
+        node(NodeKind::Parameter(0, String::from("x")))
+    }
+
+    fn binary(operator: BinaryOperator, left: Box<Node>, right: Box<Node>) -> Box<Node> {
+        node(NodeKind::Binary(operator, left, right))
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        assert_eq!(parse("1 + 2 * 3"), *binary(BinaryOperator::Add, number("1"), binary(BinaryOperator::Multiply, number("2"), number("3"))));
+        assert_eq!(parse("1 - 2 - 3"), *binary(BinaryOperator::Subtract, binary(BinaryOperator::Subtract, number("1"), number("2")), number("3")));
+    }
+
+    #[test]
+    fn powers_are_right_associative_and_bind_tighter_than_negation() {
+        assert_eq!(parse("2^3^2"), *binary(BinaryOperator::Power, number("2"), binary(BinaryOperator::Power, number("3"), number("2"))));
+        assert_eq!(parse("-2^2"), *node(NodeKind::Unary(UnaryOperator::Negate, binary(BinaryOperator::Power, number("2"), number("2")))));
+        assert_eq!(parse("2^-1"), *binary(BinaryOperator::Power, number("2"), node(NodeKind::Unary(UnaryOperator::Negate, number("1")))));
+    }
+
+    #[test]
+    fn factorial_binds_before_the_power() {
+        assert_eq!(parse("2^3!"), *binary(BinaryOperator::Power, number("2"), node(NodeKind::Unary(UnaryOperator::Factorial, number("3")))));
+    }
+
+    #[test]
+    fn operands_written_together_multiply() {
+        assert_eq!(parse("2x"), *binary(BinaryOperator::Multiply, number("2"), x()));
+        assert_eq!(parse("2x^2"), *binary(BinaryOperator::Multiply, number("2"), binary(BinaryOperator::Power, x(), number("2"))));
+        assert_eq!(parse("3(x + 1)"), *binary(BinaryOperator::Multiply, number("3"), binary(BinaryOperator::Add, x(), number("1"))));
+        // a parameter can not be called
+        assert_eq!(parse("x(x + 1)"), *binary(BinaryOperator::Multiply, x(), binary(BinaryOperator::Add, x(), number("1"))));
+        assert_eq!(parse("1 / 2x"), *binary(BinaryOperator::Multiply, binary(BinaryOperator::Divide, number("1"), number("2")), x()));
+    }
+
+    #[test]
+    fn units_bind_to_their_value_before_a_division() {
+        let quantity = |value: &str, unit: &str| binary(BinaryOperator::Multiply, number(value), node(NodeKind::Name(unit.to_string())));
+
+        assert_eq!(parse("100 km / 2 h"), *binary(BinaryOperator::Divide, quantity("100", "km"), quantity("2", "h")));
+        assert_eq!(parse("3 m / s"), *binary(BinaryOperator::Divide, quantity("3", "m"), node(NodeKind::Name(String::from("s")))));
+        // a name that is not a unit still multiplies after the division
+        assert_eq!(parse("1 / 2 y"), *binary(BinaryOperator::Multiply, binary(BinaryOperator::Divide, number("1"), number("2")), node(NodeKind::Name(String::from("y")))));
+    }
+
+    #[test]
+    fn names_that_are_not_parameters_are_looked_up_or_called() {
+        assert_eq!(parse("pi"), *node(NodeKind::Name(String::from("pi"))));
+        assert_eq!(parse("sin(x)"), *node(NodeKind::Call(String::from("sin"), vec![*x()])));
+    }
+
+    #[test]
+    fn numbers_keep_their_exponent_and_digits() {
+        assert_eq!(parse("1e5"), *number("1e5"));
+        assert_eq!(parse("1.5E-3"), *number("1.5E-3"));
+        assert_eq!(parse("12345678901234567891"), *number("12345678901234567891"));
+        // an e without digits is the constant
+        assert_eq!(parse("2e"), *binary(BinaryOperator::Multiply, number("2"), node(NodeKind::Name(String::from("e")))));
+        assert_eq!(parse("1..1e2"), *node(NodeKind::Range(number("1"), number("1e2"))));
+    }
+
+    #[test]
+    fn series_indices_are_parameters_of_their_body() {
+        let series = Node::parse("sum(i, 1, 3, i x)", &[String::from("x")]).unwrap();
+
+        assert_eq!(series, *node(NodeKind::Series {
+            operator: SeriesOperator::Sum,
+            index: String::from("i"),
+            from: number("1"),
+            to: number("3"),
+            body: binary(BinaryOperator::Multiply, node(NodeKind::Parameter(1, String::from("i"))), x()),
+        }));
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        for text in ["0 < x < 1", "(1 + 2", "1 +", "1.2.3", "[1, 2; 3]", "x to parsecs"] {
+            assert!(matches!(Node::parse(text, &[String::from("x")]), Err(SyntaxError { error: BeanieError::Parse(_), .. })), "{} parsed", text);
+        }
+    }
+
+    #[test]
+    fn nodes_and_errors_keep_where_they_were_written() {
+        let tree = parse("1 + 2 * sin(x)");
+        let right = match tree.kind {
+            NodeKind::Binary(_, _, right) => right,
+            kind => panic!("{:?} is not a sum", kind),
+        };
+        assert_eq!(right.range, 4..14);
+
+        let error = |text: &str| Node::parse(text, &[String::from("x")]).unwrap_err().range;
+        assert_eq!(error("0 < x < 1"), 6..7);
+        assert_eq!(error("(1 + 2"), 6..6);
+        assert_eq!(error("x to parsecs"), 5..12);
+        assert_eq!(error("1 + 1.2.3"), 4..9);
+    }
+}
@@ -1,62 +1,159 @@
+use std::cell::Cell;
 use std::fmt::{Debug, Display, Formatter};
-use mexprp::{Answer, Calculation, Context, Func, MathError, Num, Term};
+use std::sync::atomic::Ordering;
+use mexprp::{Answer, Context};
 use tree_sitter_beanie::data::context::FuncSignature;
 use crate::data::context::BeanieRuntimeContext;
+use crate::data::error::{BeanieError, BeanieResult, Frame};
+use crate::data::expression::evaluator::{self, promotion};
 use crate::data::expression::BeanieExpression;
+use crate::data::number::big_float::{self, BigFloat};
+use crate::data::number::big_integer::BigInteger;
+use crate::data::number::interval::Interval;
+use crate::data::number::{BeanieNum, NumberType};
+use crate::data::span::Span;
+use crate::data::value::Value;
+use crate::{MAX_RECURSION_DEPTH, MEMOIZE};
+
+thread_local! {
+    /// Number of function calls being evaluated within one another.
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
 
 #[derive(Clone)]
 pub struct Function {
+    /// The name the function is called by, used when reporting errors.
+    pub name: String,
     pub parameters: Vec<String>,
     pub expression: BeanieExpression,
     pub external_context: Option<BeanieRuntimeContext>,
+    /// Where the function was defined, or used from when it is the output of another file.
+    pub span: Option<Span>,
 }
 
 impl Function {
     pub fn new(parameters: Vec<String>, expression: BeanieExpression) -> Function {
         Function {
+            name: String::new(),
             parameters,
             expression,
             external_context: None,
+            span: None,
         }
     }
-    
-    fn evaluate_internal<N: Num + 'static>(&self, parameters: Vec<Answer<N>>, current_file_bn_context: Option<&BeanieRuntimeContext>, _: Option<&Context<N>>) -> Calculation<N> {
-        if parameters.len() != self.parameters.len() { return Err(MathError::IncorrectArguments) }
 
-        let mut parameters_ctx = Context::empty();
+    /// Calls the function from an expression evaluated against `current_file_bn_context` with
+    /// `builtins`. Calls may recurse, directly or through other functions, up to the maximum
+    /// recursion depth.
+    pub fn call<N: BeanieNum>(&self, arguments: Vec<Value<N>>, current_file_bn_context: &BeanieRuntimeContext, builtins: &Context<N>) -> BeanieResult<Value<N>> {
+        let frame = Frame {
+            file_path: self.file_path(),
+            name: self.name.clone(),
+            arguments: Some(arguments.iter().map(|argument| argument.to_string()).collect()),
+        };
 
-        for index in 0..parameters.len() {
-            parameters_ctx.set_var(&self.parameters[index], parameters[index].clone());
+        let depth = CALL_DEPTH.with(Cell::get);
+        let max_depth = MAX_RECURSION_DEPTH.load(Ordering::Relaxed);
+        if depth >= max_depth {
+            return Err(BeanieError::Recursion(format!("Function calls are nested deeper than the maximum recursion depth of {}, which --max_recursion_depth changes", max_depth))
+                .called_from(frame));
         }
-        
-        if let Some(external_ctx) = &self.external_context {
-            // if external ctx is present, meaning this function is external call to another bn
-            // file. Therefore when evaluating this expression, we should not take in consideration
-            // of the context in the currently evaluating bn file.
 
-            // and the external file context itself
-            return Ok(self.expression.evaluate_with_extra(Some(&external_ctx), vec![&parameters_ctx]));
+        // kept by the calling context, which outlives the call, under the file the function is
+        // written in, so functions of the same name in different files do not share values
+        let number_type = N::number_type(builtins);
+        let file_path = &self.external_context.as_ref().unwrap_or(current_file_bn_context).beanie_file_path;
+        let memo_key = MEMOIZE.load(Ordering::Relaxed).then(|| memo_key(&arguments, builtins)).flatten();
+        if let Some(value) = memo_key.as_ref().and_then(|key| current_file_bn_context.call_value::<N>(file_path, &self.name, key, number_type)) {
+            return Ok(value);
         }
-        
-        // let mut ctxs = vec![&parameters_ctx];
-        // if let Some(current_evaluation_context) = current_evaluation_context {
-        //     ctxs.push(current_evaluation_context);
-        // }
-        // 
-        // Ok(self.expression.evaluate_with_extra(current_file_bn_context, ctxs))
-
-        Ok(self.expression.evaluate_with_extra(current_file_bn_context, vec![&parameters_ctx]))
+
+        CALL_DEPTH.with(|call_depth| call_depth.set(depth + 1));
+        let result = self.evaluate_internal(arguments, current_file_bn_context, builtins);
+        CALL_DEPTH.with(|call_depth| call_depth.set(depth));
+
+        let value = result.map_err(|err| err.called_from(frame))?;
+        if let Some(key) = memo_key {
+            current_file_bn_context.memoize_call(file_path, &self.name, key, number_type, value.clone());
+        }
+        Ok(value)
     }
     
-    pub fn evaluate(&self, parameters: Vec<f64>, bn_ctx: &BeanieRuntimeContext) -> f64 {
-        self.evaluate_internal::<f64>(parameters.iter().map(|s| Answer::Single(*s)).collect(), Some(bn_ctx), None).unwrap().unwrap_single()
+    fn evaluate_internal<N: BeanieNum>(&self, arguments: Vec<Value<N>>, current_file_bn_context: &BeanieRuntimeContext, builtins: &Context<N>) -> BeanieResult<Value<N>> {
+        if arguments.len() != self.parameters.len() {
+            return Err(BeanieError::Type(format!("Function with parameters ({}) takes {} arguments but was given {}", self.parameters.join(", "), self.parameters.len(), arguments.len())));
+        }
+
+        // if external ctx is present, meaning this function is external call to another bn
+        // file. Therefore when evaluating this expression, we should not take in consideration
+        // of the context in the currently evaluating bn file.
+        if let Some(external_ctx) = &self.external_context {
+            // the inputs of the other file are bound the way its in instructions would, so its own
+            // constants can use them too
+            external_ctx.bind_inputs(self.parameters.iter().cloned().zip(arguments).collect(), builtins);
+
+            return self.expression.evaluate(external_ctx, builtins);
+        }
+
+        // a function of this file is called from an expression of this file, whose context is
+        // therefore the one the function was defined in
+        self.expression.evaluate_with_arguments(current_file_bn_context, &arguments, builtins)
+    }
+
+    /// The file the function is written in.
+    fn file_path(&self) -> String {
+        match (&self.external_context, &self.span) {
+            (Some(external_ctx), _) => external_ctx.beanie_file_path.clone(),
+            (None, Some(span)) => span.file_path.clone(),
+            (None, None) => String::new(),
+        }
     }
-}
 
-impl<N: Num + 'static> Func<N> for Function {
-    fn eval(&self, args: &[Term<N>], ctx: &Context<N>) -> Calculation<N> {
-        self.evaluate_internal(args.iter().map(|arg| arg.eval_ctx(ctx).unwrap()).collect(), None, Some(ctx))
+    /// Evaluates the function at `parameters`, as a graph does, giving the lower and upper bound of
+    /// every value ± gives it, which are the value itself for any type but intervals. Integers,
+    /// floats and intervals are computed as such before being turned into f64s.
+    pub fn evaluate(&self, parameters: Vec<f64>, bn_ctx: &BeanieRuntimeContext) -> BeanieResult<Vec<(f64, f64)>> {
+        match promotion::function_type(self, bn_ctx, &mut Vec::new()) {
+            Some(NumberType::Integer) => self.evaluate_as(parameters, bn_ctx, &evaluator::builtins::<BigInteger>(big_float::DEFAULT_PRECISION)),
+            Some(NumberType::Float(precision)) => self.evaluate_as(parameters, bn_ctx, &evaluator::builtins::<BigFloat>(precision)),
+            Some(NumberType::Interval(precision)) => self.evaluate_as(parameters, bn_ctx, &evaluator::builtins::<Interval>(precision)),
+            _ => self.evaluate_as(parameters, bn_ctx, &evaluator::builtins::<f64>(big_float::DEFAULT_PRECISION)),
+        }
+    }
+
+    fn evaluate_as<N: BeanieNum>(&self, parameters: Vec<f64>, bn_ctx: &BeanieRuntimeContext, builtins: &Context<N>) -> BeanieResult<Vec<(f64, f64)>> {
+        let arguments = parameters.iter()
+            .map(|parameter| N::from_f64(*parameter, builtins).map(Value::Number).map_err(|error| BeanieError::Arithmetic {
+                expression: self.expression.get_math().unwrap_or_default(),
+                error,
+            }))
+            .collect::<BeanieResult<_>>()?;
+
+        match self.call(arguments, bn_ctx, builtins)? {
+            Value::Number(Answer::Single(ans)) => Ok(vec![self.real(ans)?]),
+            Value::Number(Answer::Multiple(values)) => values.into_iter().map(|ans| self.real(ans)).collect(),
+            value => Err(BeanieError::Type(format!("Expected a number, got {} from {}", value, self.expression.get_math().unwrap_or_default()))),
+        }
     }
+
+    fn real<N: BeanieNum>(&self, ans: N) -> BeanieResult<(f64, f64)> {
+        ans.to_real_bounds()
+            .ok_or_else(|| BeanieError::Type(format!("Expected a real value, got {} from {}", ans, self.expression.get_math().unwrap_or_default())))
+    }
+}
+
+/// The arguments of a call as the key its value is memoized by. Only calls with integer arguments
+/// are memoized, as recursions such as fib(n - 1) + fib(n - 2) repeat those.
+fn memo_key<N: BeanieNum>(arguments: &[Value<N>], builtins: &Context<N>) -> Option<Vec<String>> {
+    arguments.iter()
+        .map(|argument| match argument {
+            Value::Number(Answer::Single(value)) => match value.floor(builtins) {
+                Ok(Answer::Single(floor)) if floor == *value => Some(value.to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
 }
 
 impl Debug for Function {
@@ -67,12 +164,47 @@ impl Debug for Function {
 
 impl Display for Function {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Parameters: {:?}, Expression: {}", self.parameters, self.expression.get_math().unwrap().as_str())
+        write!(f, "Parameters: {:?}, Expression: {}", self.parameters, self.expression.get_math().unwrap_or_default())
     }
 }
 
 impl From<FuncSignature> for Function {
     fn from(value: FuncSignature) -> Self {
-        Function::new(value.parameters, BeanieExpression::from(value.evaluation))
+        let expression = BeanieExpression::math(value.evaluation.evaluation, NumberType::of(&value.evaluation.data_type), &value.parameters);
+        Function::new(value.parameters, expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calls_nested_past_the_maximum_depth_fail_with_a_frame_for_each() {
+        let mut context = BeanieRuntimeContext::new(String::from("test.bn"));
+        let mut function = Function::new(vec![String::from("x")], BeanieExpression::math(String::from("f(x + 1)"), Some(NumberType::Decimal), &[String::from("x")]));
+        function.name = String::from("f");
+        context.insert_function(String::from("f"), function.clone());
+
+        // as if the calls were already nested close to the limit, so the test does not need the
+        // stack of a thousand of them
+        let max_depth = MAX_RECURSION_DEPTH.load(Ordering::Relaxed);
+        CALL_DEPTH.with(|call_depth| call_depth.set(max_depth - 3));
+
+        let builtins = evaluator::builtins::<f64>(big_float::DEFAULT_PRECISION);
+        let result = function.call(vec![Value::Number(Answer::Single(0f64))], &context, &builtins);
+        let depth = CALL_DEPTH.with(Cell::get);
+        CALL_DEPTH.with(|call_depth| call_depth.set(0));
+
+        match result {
+            Err(BeanieError::Traced { frames, error }) => {
+                assert!(matches!(*error, BeanieError::Recursion(_)));
+                // the call that went too deep and the three it was nested in
+                assert_eq!(frames.len(), 4);
+                assert!(frames.iter().all(|frame| frame.name == "f"));
+            }
+            result => panic!("{:?} is not a recursion error", result.map(|value| value.to_string())),
+        }
+        assert_eq!(depth, max_depth - 3);
     }
 }
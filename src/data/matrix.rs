@@ -0,0 +1,137 @@
+use std::fmt::{Display, Formatter};
+use crate::data::error::{BeanieError, BeanieResult};
+
+/// A matrix of numbers, its elements stored row after row. The arithmetic on it is left to the
+/// evaluator, which knows the number type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix<N> {
+    rows: usize,
+    columns: usize,
+    elements: Vec<N>,
+}
+
+impl<N: Clone + Display> Matrix<N> {
+    /// A matrix with `rows`, which must all have the same number of elements.
+    pub fn new(rows: Vec<Vec<N>>) -> BeanieResult<Matrix<N>> {
+        let columns = rows.first().map(|row| row.len()).unwrap_or(0);
+        if columns == 0 || rows.iter().any(|row| row.len() != columns) {
+            return Err(BeanieError::Type(String::from("The rows of a matrix must have the same number of elements, and at least one")));
+        }
+
+        Ok(Matrix {
+            rows: rows.len(),
+            columns,
+            elements: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    /// A matrix of a single column.
+    pub fn column(elements: Vec<N>) -> BeanieResult<Matrix<N>> {
+        Matrix::new(elements.into_iter().map(|element| vec![element]).collect())
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.columns
+    }
+
+    /// The element at `row` and `column`, both starting at 0.
+    pub fn get(&self, row: usize, column: usize) -> &N {
+        &self.elements[row * self.columns + column]
+    }
+
+    pub fn set(&mut self, row: usize, column: usize, element: N) {
+        self.elements[row * self.columns + column] = element;
+    }
+
+    pub fn swap_rows(&mut self, first: usize, second: usize) {
+        for column in 0..self.columns {
+            self.elements.swap(first * self.columns + column, second * self.columns + column);
+        }
+    }
+
+    pub fn elements(&self) -> &[N] {
+        &self.elements
+    }
+
+    pub fn transpose(&self) -> Matrix<N> {
+        let elements = (0..self.columns)
+            .flat_map(|column| (0..self.rows).map(move |row| (row, column)))
+            .map(|(row, column)| self.get(row, column).clone())
+            .collect();
+
+        Matrix {
+            rows: self.columns,
+            columns: self.rows,
+            elements,
+        }
+    }
+
+    /// A matrix of the same shape with `operation` applied to every element.
+    pub fn map<M, F: Fn(&N) -> BeanieResult<M>>(&self, operation: F) -> BeanieResult<Matrix<M>> {
+        Ok(Matrix {
+            rows: self.rows,
+            columns: self.columns,
+            elements: self.elements.iter().map(operation).collect::<BeanieResult<_>>()?,
+        })
+    }
+
+    /// Applies `operation` to the elements at the same positions, the matrices being of the same
+    /// shape.
+    pub fn zip_with<F: Fn(&N, &N) -> BeanieResult<N>>(&self, other: &Matrix<N>, operation: F) -> BeanieResult<Matrix<N>> {
+        if self.rows != other.rows || self.columns != other.columns {
+            return Err(BeanieError::Type(format!("Matrices of {} and {} can not be combined element by element", self.shape(), other.shape())));
+        }
+
+        Ok(Matrix {
+            rows: self.rows,
+            columns: self.columns,
+            elements: self.elements.iter().zip(&other.elements).map(|(a, b)| operation(a, b)).collect::<BeanieResult<_>>()?,
+        })
+    }
+
+    /// The number of rows and columns, as in 2×3, for messages.
+    pub fn shape(&self) -> String {
+        format!("{}×{}", self.rows, self.columns)
+    }
+
+    /// The matrix over one line per row, its columns aligned, for printing.
+    pub fn pretty(&self) -> String {
+        let elements: Vec<String> = self.elements.iter().map(|element| element.to_string()).collect();
+        let widths: Vec<usize> = (0..self.columns)
+            .map(|column| (0..self.rows).map(|row| elements[row * self.columns + column].chars().count()).max().unwrap_or(0))
+            .collect();
+
+        let lines: Vec<String> = (0..self.rows)
+            .map(|row| {
+                let cells: Vec<String> = (0..self.columns)
+                    .map(|column| format!("{:>width$}", elements[row * self.columns + column], width = widths[column]))
+                    .collect();
+                format!("│ {} │", cells.join("  "))
+            })
+            .collect();
+
+        lines.join("\n")
+    }
+}
+
+/// The matrix on one line as it is written in an expression, as in `[1, 2; 3, 4]`.
+impl<N: Clone + Display> Display for Matrix<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let rows: Vec<String> = (0..self.rows)
+            .map(|row| {
+                let cells: Vec<String> = (0..self.columns).map(|column| self.get(row, column).to_string()).collect();
+                cells.join(", ")
+            })
+            .collect();
+
+        write!(f, "[{}]", rows.join("; "))
+    }
+}
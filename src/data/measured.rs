@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::data::number::BeanieNum;
+
+/// Identifies the measurements written with +-, each evaluation of one being a new measurement.
+static NEXT_MEASUREMENT: AtomicUsize = AtomicUsize::new(0);
+
+/// A number known up to a standard uncertainty, as in 9.81 +- 0.02, which arithmetic and builtins
+/// propagate to first order.
+///
+/// The uncertainty is kept as the part of it coming from each measurement the value depends on, the
+/// change of the value for a change of one standard uncertainty of the measurement. The parts of a
+/// measurement used several times add up before being combined with the others, so g - g, with g a
+/// constant evaluated once, has no uncertainty rather than that of two independent measurements.
+#[derive(Debug, Clone)]
+pub struct Measured<N: BeanieNum> {
+    pub value: N,
+    pub parts: BTreeMap<usize, N>,
+}
+
+impl<N: BeanieNum> Measured<N> {
+    /// A new measurement of `value` with the standard uncertainty `uncertainty`, independent of
+    /// every other.
+    pub fn measurement(value: N, uncertainty: N) -> Measured<N> {
+        let measurement = NEXT_MEASUREMENT.fetch_add(1, Ordering::Relaxed);
+        Measured {
+            value,
+            parts: BTreeMap::from([(measurement, uncertainty)]),
+        }
+    }
+
+    /// The standard uncertainty, as an f64, `None` when a part of it is not real.
+    pub fn uncertainty(&self) -> Option<f64> {
+        let squares = self.parts.values()
+            .map(|part| part.to_real_f64().map(|part| part * part))
+            .sum::<Option<f64>>()?;
+        Some(squares.sqrt())
+    }
+}
+
+/// `value ± uncertainty`, the uncertainty rounded to two significant figures and the value to the
+/// same decimal place.
+impl<N: BeanieNum> Display for Measured<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (value, uncertainty) = match (self.value.to_real_f64(), self.uncertainty()) {
+            (Some(value), Some(uncertainty)) if value.is_finite() && uncertainty.is_finite() && uncertainty > 0f64 => (value, uncertainty),
+            (_, Some(uncertainty)) => return write!(f, "{} ± {}", self.value, uncertainty),
+            (_, None) => return write!(f, "{} ± ?", self.value),
+        };
+
+        // the power of ten of the last digit shown
+        let place = uncertainty.log10().floor() as i32 - 1;
+        let round = |number: f64| (number / 10f64.powi(place)).round() * 10f64.powi(place);
+        let decimals = (-place).max(0) as usize;
+        write!(f, "{:.*} ± {:.*}", decimals, round(value), decimals, round(uncertainty))
+    }
+}
@@ -0,0 +1,549 @@
+use std::any::Any;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use mexprp::{Answer, Calculation, Context, Func, MathError, Num, Term};
+use mexprp::num::{ComplexFloat, ComplexRugRat};
+use rug::float::Round;
+use rug::{Complex, Integer, Rational};
+use tree_sitter_beanie::data::expression::data_type::DataType;
+use crate::data::number::big_float::BigFloat;
+use crate::data::number::big_integer::BigInteger;
+use crate::data::number::interval::Interval;
+
+pub mod big_float;
+pub mod big_integer;
+pub mod interval;
+
+/// The number types values are promoted along when expressions of different data types meet:
+/// integers, rationals, decimals, then complex numbers. A type holds every value of the types
+/// below it, and two types neither holds meet in the one holding both, which is at most
+/// `Complex`.
+///
+/// Integers, floats and intervals are parsed as decimals by the grammar, and so are chosen by a
+/// file or definition with `#type integer`, `#type float:<bits>` or `#type interval:<bits>` rather
+/// than by the grammar. Integers are exact rug integers. Floats are decimals of a precision in
+/// bits, which an expression carries from the pragma, and hold the decimals and the floats of a
+/// lower precision. Intervals have bounds of a precision and hold the decimals and the floats and
+/// intervals of a lower precision, a float meeting an interval in the interval of the higher
+/// precision of the two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumberType {
+    Integer,
+    Rational,
+    Decimal,
+    Float(u32),
+    Interval(u32),
+    ComplexRational,
+    ImaginaryDecimal,
+    Complex,
+}
+
+impl NumberType {
+    /// The number type of math expressions of `data_type`, `None` for the data types that are not
+    /// numbers.
+    pub fn of(data_type: &DataType) -> Option<NumberType> {
+        match data_type {
+            DataType::Decimal => Some(NumberType::Decimal),
+            DataType::Rational => Some(NumberType::Rational),
+            DataType::ComplexRational => Some(NumberType::ComplexRational),
+            DataType::ImaginaryDecimal => Some(NumberType::ImaginaryDecimal),
+            DataType::Complex => Some(NumberType::Complex),
+            DataType::Boolean | DataType::String => None,
+        }
+    }
+
+    /// The data type the grammar parses math of this type as.
+    pub fn data_type(self) -> DataType {
+        match self {
+            NumberType::Integer | NumberType::Decimal | NumberType::Float(_) | NumberType::Interval(_) => DataType::Decimal,
+            NumberType::Rational => DataType::Rational,
+            NumberType::ComplexRational => DataType::ComplexRational,
+            NumberType::ImaginaryDecimal => DataType::ImaginaryDecimal,
+            NumberType::Complex => DataType::Complex,
+        }
+    }
+
+    /// The precision in bits values of the type are computed with, the default one for the types
+    /// that have none.
+    pub fn precision(self) -> u32 {
+        match self {
+            NumberType::Float(precision) | NumberType::Interval(precision) => precision,
+            _ => big_float::DEFAULT_PRECISION,
+        }
+    }
+
+    /// Whether every value of `other` is a value of this type.
+    pub fn holds(self, other: NumberType) -> bool {
+        let real = matches!(other, NumberType::Integer | NumberType::Rational | NumberType::Decimal);
+        let exact = matches!(other, NumberType::Integer | NumberType::Rational | NumberType::ComplexRational);
+
+        self == other || match (self, other) {
+            (NumberType::Float(precision), NumberType::Float(other)) => other <= precision,
+            (NumberType::Interval(precision), NumberType::Float(other) | NumberType::Interval(other)) => other <= precision,
+            (NumberType::Float(_) | NumberType::Interval(_), _) => real,
+            (NumberType::Integer, _) => false,
+            (NumberType::Rational, _) => other == NumberType::Integer,
+            (NumberType::Decimal, _) => real && exact,
+            (NumberType::ComplexRational, _) => exact,
+            (NumberType::ImaginaryDecimal, _) => real,
+            (NumberType::Complex, _) => true,
+        }
+    }
+
+    /// The lowest type holding both types.
+    pub fn join(self, other: NumberType) -> NumberType {
+        match (self, other) {
+            _ if self.holds(other) => self,
+            _ if other.holds(self) => other,
+            (NumberType::Float(precision) | NumberType::Interval(precision), NumberType::Float(other) | NumberType::Interval(other)) => NumberType::Interval(precision.max(other)),
+            _ => NumberType::Complex,
+        }
+    }
+
+    /// The type of math the grammar parsed as `data_type` in a file it parsed as `parsed_as`, when
+    /// this type was chosen for it: this type when `data_type` is `parsed_as`, so decimals are
+    /// floats where floats were chosen, and the type of `data_type` otherwise, as for the complex
+    /// numbers of a decimal file.
+    pub fn within(self, data_type: &DataType, parsed_as: &DataType) -> Option<NumberType> {
+        let number_type = NumberType::of(data_type)?;
+        match NumberType::of(parsed_as) == Some(number_type) {
+            true => Some(self),
+            false => Some(number_type),
+        }
+    }
+}
+
+impl Display for NumberType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NumberType::Float(precision) => write!(f, "Float({} bits)", precision),
+            NumberType::Interval(precision) => write!(f, "Interval({} bits)", precision),
+            number_type => write!(f, "{:?}", number_type),
+        }
+    }
+}
+
+/// Reads a data type of the grammar such as `rational`, `integer`, or `float` or `interval` with an
+/// optional precision as in `float:256` or `interval:50d`.
+impl FromStr for NumberType {
+    type Err = String;
+
+    fn from_str(number_type: &str) -> Result<NumberType, String> {
+        match number_type.split_once(':') {
+            Some((name @ ("float" | "interval"), precision)) => match big_float::parse_precision(precision) {
+                Some(precision) if name == "float" => Ok(NumberType::Float(precision)),
+                Some(precision) => Ok(NumberType::Interval(precision)),
+                None => Err(format!("Invalid precision {}, expected a number of bits from 53 such as {}:256 or of digits such as {}:50d", precision, name, name)),
+            },
+            None if number_type == "float" => Ok(NumberType::Float(big_float::DEFAULT_PRECISION)),
+            None if number_type == "interval" => Ok(NumberType::Interval(big_float::DEFAULT_PRECISION)),
+            None if number_type == "integer" => Ok(NumberType::Integer),
+            _ => number_type.parse::<DataType>().ok()
+                .and_then(|data_type| NumberType::of(&data_type))
+                .ok_or_else(|| format!("Unknown data type {}, expected a data type of the grammar such as decimal, rational or complex, integer, or float or interval with an optional precision such as float:256", number_type)),
+        }
+    }
+}
+
+/// The lowest type holding both, either being `None` when it has no type of its own.
+pub fn join(a: Option<NumberType>, b: Option<NumberType>) -> Option<NumberType> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.join(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// `value` as an `M`, going through its exact real and imaginary parts, which `M` rounds when it
+/// has fewer digits. `None` when `M` has no such value, such as an integer for 1/2 or a real
+/// number for 2i, and for values that are not a single finite number.
+pub fn convert<N: BeanieNum, M: BeanieNum>(value: &N, ctx: &Context<M>) -> Option<M> {
+    if let Some(value) = (value as &dyn Any).downcast_ref::<M>() {
+        return Some(value.clone());
+    }
+
+    let (real, imaginary) = value.to_parts()?;
+    M::from_parts(&real, &imaginary, ctx)
+}
+
+/// The exact value of the number literal `text`, such as 0.1 or 1.5e-3, `None` when it is not one.
+/// Literals are read through it rather than as f64s, so 0.1 is exactly 1/10 for rationals and
+/// 12345678901234567891 keeps all its digits for integers.
+pub fn parse_literal(text: &str) -> Option<Rational> {
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(index) => (&text[..index], text[index + 1..].parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let digits = Integer::from_str_radix(&format!("{}{}", whole, fraction), 10).ok()?;
+    let scale = exponent.checked_sub(fraction.len() as i64)?;
+    let power = Integer::from(Integer::u_pow_u(10, u32::try_from(scale.unsigned_abs()).ok()?));
+
+    Some(match scale >= 0 {
+        true => Rational::from(digits * power),
+        false => Rational::from((digits, power)),
+    })
+}
+
+pub type UnaryMethod<N> = fn(&N, &Context<N>) -> Calculation<N>;
+pub type BinaryMethod<N> = fn(&N, &N, &Context<N>) -> Calculation<N>;
+
+/// A mexprp builtin of one argument, going to a method of `Num`.
+pub struct Unary<N: Num>(pub UnaryMethod<N>);
+
+/// A mexprp builtin of two arguments, going to a method of `Num`.
+pub struct Binary<N: Num>(pub BinaryMethod<N>);
+
+impl<N: Num + 'static> Func<N> for Unary<N> {
+    fn eval(&self, args: &[Term<N>], ctx: &Context<N>) -> Calculation<N> {
+        match args {
+            [value] => value.eval_ctx(ctx)?.unop(|value| (self.0)(value, ctx)),
+            _ => Err(MathError::IncorrectArguments),
+        }
+    }
+}
+
+impl<N: Num + 'static> Func<N> for Binary<N> {
+    fn eval(&self, args: &[Term<N>], ctx: &Context<N>) -> Calculation<N> {
+        match args {
+            [left, right] => left.eval_ctx(ctx)?.op(&right.eval_ctx(ctx)?, |left, right| (self.0)(left, right, ctx)),
+            _ => Err(MathError::IncorrectArguments),
+        }
+    }
+}
+
+/// mexprp's builtin functions other than max and min, going straight to the methods of `Num`.
+/// Unlike `Context::new`, it has no constants, which mexprp computes from f64s and complex numbers
+/// that not every type has.
+pub fn method_builtins<N: Num + 'static>() -> Context<N> {
+    let mut context = Context::empty();
+
+    let unary: [(&str, UnaryMethod<N>); 12] = [
+        ("sin", Num::sin), ("cos", Num::cos), ("tan", Num::tan),
+        ("asin", Num::asin), ("acos", Num::acos), ("atan", Num::atan),
+        ("floor", Num::floor), ("ceil", Num::ceil), ("round", Num::round),
+        ("sqrt", Num::sqrt), ("abs", Num::abs), ("ln", Num::ln),
+    ];
+    for (name, method) in unary {
+        context.set_func(name, Unary(method));
+    }
+    context.set_func("atan2", Binary(Num::atan2));
+    context.set_func("nrt", Binary(Num::nrt));
+    context.set_func("log", Binary(Num::log));
+
+    context
+}
+
+/// A number type expressions are evaluated as, one for each math `DataType`, with what beanie
+/// needs of it beyond mexprp's `Num`.
+pub trait BeanieNum: Num + 'static {
+    /// The value as an f64, or `None` when it has an imaginary part.
+    fn to_real_f64(&self) -> Option<f64>;
+
+    /// The lowest and highest real number the value stands for, as f64s, which are the value itself
+    /// for any type but intervals. `None` when it has an imaginary part.
+    fn to_real_bounds(&self) -> Option<(f64, f64)> {
+        self.to_real_f64().map(|value| (value, value))
+    }
+
+    /// Where the type is in the promotion lattice, for values computed in `ctx`, whose precision
+    /// tells the floats of different precisions apart.
+    fn number_type(ctx: &Context<Self>) -> NumberType;
+
+    /// The exact real and imaginary parts of the value, `None` when it is not a finite number.
+    fn to_parts(&self) -> Option<(Rational, Rational)>;
+
+    /// The value of real part `real` and imaginary part `imaginary`, rounded if the type has fewer
+    /// digits, `None` when the type has no such value.
+    fn from_parts(real: &Rational, imaginary: &Rational, ctx: &Context<Self>) -> Option<Self>;
+
+    /// The mexprp context with the builtin functions and constants of the type, at `precision`
+    /// for the types that have one.
+    fn builtins(_precision: u32) -> Context<Self> {
+        Context::new()
+    }
+
+    /// The value as an integer, or `None` when it is not one.
+    fn to_integer(&self) -> Option<Integer> {
+        self.to_real_f64()
+            .filter(|value| value.fract() == 0f64)
+            .and_then(Integer::from_f64)
+    }
+
+    /// `integer` in the type, rounded if the type has fewer digits.
+    fn from_integer(integer: Integer, ctx: &Context<Self>) -> Calculation<Self> {
+        Self::from_f64(integer.to_f64(), ctx)
+    }
+
+    /// The remainder of dividing by `other`, of the sign of `other` as in -7 mod 3 = 2.
+    fn modulo(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        let quotient = self.div(other, ctx)?.unop(|quotient| quotient.floor(ctx))?;
+        let multiple = quotient.unop(|quotient| quotient.mul(other, ctx))?;
+        multiple.unop(|multiple| self.sub(multiple, ctx))
+    }
+}
+
+impl BeanieNum for f64 {
+    fn to_real_f64(&self) -> Option<f64> {
+        Some(*self)
+    }
+
+    fn number_type(_ctx: &Context<Self>) -> NumberType {
+        NumberType::Decimal
+    }
+
+    fn to_parts(&self) -> Option<(Rational, Rational)> {
+        Rational::from_f64(*self).map(|real| (real, Rational::new()))
+    }
+
+    fn from_parts(real: &Rational, imaginary: &Rational, _ctx: &Context<Self>) -> Option<Self> {
+        imaginary.cmp0().is_eq().then(|| real.to_f64())
+    }
+}
+
+impl BeanieNum for ComplexFloat {
+    fn to_real_f64(&self) -> Option<f64> {
+        (self.i == 0f64).then_some(self.r)
+    }
+
+    fn number_type(_ctx: &Context<Self>) -> NumberType {
+        NumberType::ImaginaryDecimal
+    }
+
+    fn to_parts(&self) -> Option<(Rational, Rational)> {
+        Some((Rational::from_f64(self.r)?, Rational::from_f64(self.i)?))
+    }
+
+    fn from_parts(real: &Rational, imaginary: &Rational, _ctx: &Context<Self>) -> Option<Self> {
+        Some(ComplexFloat { r: real.to_f64(), i: imaginary.to_f64() })
+    }
+}
+
+impl BeanieNum for Rational {
+    fn to_real_f64(&self) -> Option<f64> {
+        Some(self.to_f64())
+    }
+
+    fn number_type(_ctx: &Context<Self>) -> NumberType {
+        NumberType::Rational
+    }
+
+    fn to_parts(&self) -> Option<(Rational, Rational)> {
+        Some((self.clone(), Rational::new()))
+    }
+
+    fn from_parts(real: &Rational, imaginary: &Rational, _ctx: &Context<Self>) -> Option<Self> {
+        imaginary.cmp0().is_eq().then(|| real.clone())
+    }
+
+    fn to_integer(&self) -> Option<Integer> {
+        self.is_integer().then(|| self.numer().clone())
+    }
+
+    fn from_integer(integer: Integer, _ctx: &Context<Self>) -> Calculation<Self> {
+        Ok(Answer::Single(Rational::from(integer)))
+    }
+}
+
+impl BeanieNum for ComplexRugRat {
+    fn to_real_f64(&self) -> Option<f64> {
+        self.i.cmp0().is_eq().then(|| self.r.to_f64())
+    }
+
+    fn number_type(_ctx: &Context<Self>) -> NumberType {
+        NumberType::ComplexRational
+    }
+
+    fn to_parts(&self) -> Option<(Rational, Rational)> {
+        Some((self.r.clone(), self.i.clone()))
+    }
+
+    fn from_parts(real: &Rational, imaginary: &Rational, _ctx: &Context<Self>) -> Option<Self> {
+        Some(ComplexRugRat { r: real.clone(), i: imaginary.clone() })
+    }
+
+    fn to_integer(&self) -> Option<Integer> {
+        (self.i.cmp0().is_eq() && self.r.is_integer()).then(|| self.r.numer().clone())
+    }
+
+    fn from_integer(integer: Integer, _ctx: &Context<Self>) -> Calculation<Self> {
+        Ok(Answer::Single(ComplexRugRat { r: Rational::from(integer), i: Rational::new() }))
+    }
+}
+
+impl BeanieNum for BigFloat {
+    fn to_real_f64(&self) -> Option<f64> {
+        Some(self.0.to_f64())
+    }
+
+    fn number_type(ctx: &Context<Self>) -> NumberType {
+        NumberType::Float(ctx.cfg.precision)
+    }
+
+    fn to_parts(&self) -> Option<(Rational, Rational)> {
+        self.0.to_rational().map(|real| (real, Rational::new()))
+    }
+
+    fn from_parts(real: &Rational, imaginary: &Rational, ctx: &Context<Self>) -> Option<Self> {
+        imaginary.cmp0().is_eq().then(|| BigFloat(rug::Float::with_val(ctx.cfg.precision, real)))
+    }
+
+    fn builtins(precision: u32) -> Context<Self> {
+        BigFloat::builtins(precision)
+    }
+
+    fn to_integer(&self) -> Option<Integer> {
+        self.0.to_integer().filter(|_| self.0.is_integer())
+    }
+
+    fn from_integer(integer: Integer, ctx: &Context<Self>) -> Calculation<Self> {
+        Ok(Answer::Single(BigFloat(rug::Float::with_val(ctx.cfg.precision, integer))))
+    }
+}
+
+impl BeanieNum for BigInteger {
+    fn to_real_f64(&self) -> Option<f64> {
+        Some(self.0.to_f64())
+    }
+
+    fn number_type(_ctx: &Context<Self>) -> NumberType {
+        NumberType::Integer
+    }
+
+    fn to_parts(&self) -> Option<(Rational, Rational)> {
+        Some((Rational::from(self.0.clone()), Rational::new()))
+    }
+
+    fn from_parts(real: &Rational, imaginary: &Rational, _ctx: &Context<Self>) -> Option<Self> {
+        (imaginary.cmp0().is_eq() && real.is_integer()).then(|| BigInteger(real.numer().clone()))
+    }
+
+    fn builtins(_precision: u32) -> Context<Self> {
+        BigInteger::builtins()
+    }
+
+    fn to_integer(&self) -> Option<Integer> {
+        Some(self.0.clone())
+    }
+
+    fn from_integer(integer: Integer, _ctx: &Context<Self>) -> Calculation<Self> {
+        Ok(Answer::Single(BigInteger(integer)))
+    }
+
+    fn modulo(&self, other: &Self, _ctx: &Context<Self>) -> Calculation<Self> {
+        self.remainder(other)
+    }
+}
+
+impl BeanieNum for Interval {
+    /// The number of an interval of a single number, `None` for any other, which is not one value.
+    fn to_real_f64(&self) -> Option<f64> {
+        (self.lower == self.upper).then(|| self.lower.to_f64())
+    }
+
+    fn to_real_bounds(&self) -> Option<(f64, f64)> {
+        Some((self.lower.to_f64_round(Round::Down), self.upper.to_f64_round(Round::Up)))
+    }
+
+    fn number_type(ctx: &Context<Self>) -> NumberType {
+        NumberType::Interval(ctx.cfg.precision)
+    }
+
+    /// The number of an interval of a single number, `None` for any other.
+    fn to_parts(&self) -> Option<(Rational, Rational)> {
+        (self.lower == self.upper).then(|| self.lower.to_rational()).flatten().map(|real| (real, Rational::new()))
+    }
+
+    /// The narrowest interval around `real`.
+    fn from_parts(real: &Rational, imaginary: &Rational, ctx: &Context<Self>) -> Option<Self> {
+        let bound = |round| rug::Float::with_val_round(ctx.cfg.precision, real, round).0;
+        imaginary.cmp0().is_eq().then(|| Interval { lower: bound(Round::Down), upper: bound(Round::Up) })
+    }
+
+    fn builtins(precision: u32) -> Context<Self> {
+        Interval::builtins(precision)
+    }
+
+    fn to_integer(&self) -> Option<Integer> {
+        self.lower.to_integer().filter(|_| self.lower == self.upper && self.lower.is_integer())
+    }
+
+    fn from_integer(integer: Integer, ctx: &Context<Self>) -> Calculation<Self> {
+        let bound = |round| rug::Float::with_val_round(ctx.cfg.precision, &integer, round).0;
+        Ok(Answer::Single(Interval { lower: bound(Round::Down), upper: bound(Round::Up) }))
+    }
+}
+
+impl BeanieNum for Complex {
+    fn to_real_f64(&self) -> Option<f64> {
+        self.imag().is_zero().then(|| self.real().to_f64())
+    }
+
+    fn number_type(_ctx: &Context<Self>) -> NumberType {
+        NumberType::Complex
+    }
+
+    fn to_parts(&self) -> Option<(Rational, Rational)> {
+        Some((self.real().to_rational()?, self.imag().to_rational()?))
+    }
+
+    fn from_parts(real: &Rational, imaginary: &Rational, ctx: &Context<Self>) -> Option<Self> {
+        Some(Complex::with_val(ctx.cfg.precision, (real, imaginary)))
+    }
+
+    fn to_integer(&self) -> Option<Integer> {
+        self.real().to_integer().filter(|_| self.imag().is_zero() && self.real().is_integer())
+    }
+
+    fn from_integer(integer: Integer, ctx: &Context<Self>) -> Calculation<Self> {
+        Ok(Answer::Single(Complex::with_val(ctx.cfg.precision, integer)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn types_are_promoted_along_the_lattice() {
+        assert_eq!(NumberType::Integer.join(NumberType::Rational), NumberType::Rational);
+        assert_eq!(NumberType::Rational.join(NumberType::Decimal), NumberType::Decimal);
+        assert_eq!(NumberType::Integer.join(NumberType::Decimal), NumberType::Decimal);
+        assert_eq!(NumberType::Rational.join(NumberType::ImaginaryDecimal), NumberType::ImaginaryDecimal);
+        assert_eq!(NumberType::Decimal.join(NumberType::ComplexRational), NumberType::Complex);
+    }
+
+    #[test]
+    fn floats_and_intervals_meet_at_the_higher_precision() {
+        assert_eq!(NumberType::Decimal.join(NumberType::Float(256)), NumberType::Float(256));
+        assert_eq!(NumberType::Float(128).join(NumberType::Float(256)), NumberType::Float(256));
+        assert_eq!(NumberType::Float(512).join(NumberType::Interval(128)), NumberType::Interval(512));
+        assert_eq!(NumberType::Interval(256).join(NumberType::Float(128)), NumberType::Interval(256));
+        assert_eq!(NumberType::Rational.join(NumberType::Interval(128)), NumberType::Interval(128));
+        assert_eq!(NumberType::Float(128).join(NumberType::ImaginaryDecimal), NumberType::Complex);
+    }
+
+    #[test]
+    fn every_type_of_the_lattice_can_be_chosen() {
+        assert_eq!("integer".parse::<NumberType>(), Ok(NumberType::Integer));
+        assert_eq!("rational".parse::<NumberType>(), Ok(NumberType::Rational));
+        assert_eq!("float".parse::<NumberType>(), Ok(NumberType::Float(big_float::DEFAULT_PRECISION)));
+        assert_eq!("float:256".parse::<NumberType>(), Ok(NumberType::Float(256)));
+        assert_eq!("interval".parse::<NumberType>(), Ok(NumberType::Interval(big_float::DEFAULT_PRECISION)));
+        assert_eq!("interval:50d".parse::<NumberType>(), Ok(NumberType::Interval(167)));
+        assert!("float:20".parse::<NumberType>().is_err());
+        assert!("interval:many".parse::<NumberType>().is_err());
+    }
+
+    #[test]
+    fn the_chosen_type_replaces_the_one_the_grammar_parsed_the_file_as() {
+        assert_eq!(NumberType::Float(256).within(&DataType::Decimal, &DataType::Decimal), Some(NumberType::Float(256)));
+        assert_eq!(NumberType::Integer.within(&DataType::Decimal, &DataType::Decimal), Some(NumberType::Integer));
+        assert_eq!(NumberType::Float(256).within(&DataType::Complex, &DataType::Decimal), Some(NumberType::Complex));
+        assert_eq!(NumberType::Interval(128).within(&DataType::Rational, &DataType::Rational), Some(NumberType::Interval(128)));
+        assert_eq!(NumberType::Integer.within(&DataType::Boolean, &DataType::Decimal), None);
+    }
+}
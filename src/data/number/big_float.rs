@@ -0,0 +1,221 @@
+use std::cmp::Ordering;
+use std::f64::consts::LOG10_2;
+use std::fmt::{Display, Formatter};
+use mexprp::{Answer, Calculation, Context, MathError, Num};
+use rug::float::{Constant, Round};
+use rug::ops::Pow;
+use rug::Float;
+
+/// Precision decimals are computed with when `float` is given as the data type without one.
+pub const DEFAULT_PRECISION: u32 = 128;
+
+/// A real number with as many bits as the precision of the mexprp context it is computed in, which
+/// is what expressions of the float number types are evaluated as.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct BigFloat(pub Float);
+
+/// Parses the precision of `float:<precision>`, in bits as in `float:256` or in significant digits
+/// as in `float:50d`.
+pub fn parse_precision(precision: &str) -> Option<u32> {
+    let bits = match precision.strip_suffix('d') {
+        Some(digits) => (digits.parse::<u32>().ok()? as f64 / LOG10_2).ceil() as u32,
+        None => precision.parse::<u32>().ok()?,
+    };
+
+    // below the precision of an f64 the results would be worse than computing with f64s
+    (53..=1 << 20).contains(&bits).then_some(bits)
+}
+
+impl BigFloat {
+    /// mexprp's builtins, with its constants computed at `precision` rather than from f64s.
+    pub fn builtins(precision: u32) -> Context<BigFloat> {
+        let mut context = Context::new();
+        context.cfg.precision = precision;
+
+        context.set_var("pi", BigFloat(Float::with_val(precision, Constant::Pi)));
+        context.set_var("e", BigFloat(Float::with_val(precision, 1).exp()));
+        context
+    }
+
+    /// The number of significant digits the precision holds.
+    fn digits(&self) -> usize {
+        ((self.0.prec() as f64 * LOG10_2).floor() as usize).max(1)
+    }
+}
+
+fn result(value: Float) -> Calculation<BigFloat> {
+    match value.is_nan() {
+        true => Err(MathError::NaN),
+        false => Ok(Answer::Single(BigFloat(value))),
+    }
+}
+
+impl Num for BigFloat {
+    /// Goes through the shortest decimal that gives `t`, so that a literal such as 0.1 is as
+    /// precise as the context rather than an f64.
+    fn from_f64(t: f64, ctx: &Context<Self>) -> Calculation<Self> {
+        let value = match t.is_finite() {
+            true => Float::parse(t.to_string())
+                .map(|parsed| Float::with_val(ctx.cfg.precision, parsed))
+                .map_err(|_| MathError::Other)?,
+            false => Float::with_val(ctx.cfg.precision, t),
+        };
+        result(value)
+    }
+
+    /// Not a number when there is an imaginary part, so that using i gives an error.
+    fn from_f64_complex((real, imaginary): (f64, f64), ctx: &Context<Self>) -> Calculation<Self> {
+        match imaginary {
+            0f64 => Self::from_f64(real, ctx),
+            _ => Ok(Answer::Single(BigFloat(Float::with_val(ctx.cfg.precision, f64::NAN)))),
+        }
+    }
+
+    fn typename() -> String {
+        String::from("BigFloat")
+    }
+
+    fn tryord(&self, other: &Self, _ctx: &Context<Self>) -> Result<Ordering, MathError> {
+        self.0.partial_cmp(&other.0).ok_or(MathError::CmpError)
+    }
+
+    fn add(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        result(Float::with_val(ctx.cfg.precision, &self.0 + &other.0))
+    }
+
+    fn sub(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        result(Float::with_val(ctx.cfg.precision, &self.0 - &other.0))
+    }
+
+    fn mul(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        result(Float::with_val(ctx.cfg.precision, &self.0 * &other.0))
+    }
+
+    fn div(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        if other.0.is_zero() {
+            return Err(MathError::DivideByZero);
+        }
+        result(Float::with_val(ctx.cfg.precision, &self.0 / &other.0))
+    }
+
+    fn pow(&self, other: &Self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().pow(&other.0))
+    }
+
+    fn sqrt(&self, ctx: &Context<Self>) -> Calculation<Self> {
+        let root = self.0.clone().sqrt();
+        match ctx.cfg.sqrt_both && !root.is_nan() && !root.is_zero() {
+            true => Ok(Answer::Multiple(vec![BigFloat(root.clone()), BigFloat(-root)])),
+            false => result(root),
+        }
+    }
+
+    fn nrt(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        let one = Float::with_val(ctx.cfg.precision, 1);
+        let exponent = Float::with_val(ctx.cfg.precision, &one / &other.0);
+        result(self.0.clone().pow(&exponent))
+    }
+
+    fn abs(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().abs())
+    }
+
+    fn sin(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().sin())
+    }
+
+    fn cos(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().cos())
+    }
+
+    fn tan(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().tan())
+    }
+
+    fn asin(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().asin())
+    }
+
+    fn acos(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().acos())
+    }
+
+    fn atan(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().atan())
+    }
+
+    fn atan2(&self, other: &Self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().atan2(&other.0))
+    }
+
+    fn floor(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().floor())
+    }
+
+    fn ceil(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().ceil())
+    }
+
+    fn round(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().round())
+    }
+
+    /// The logarithm of the number in base `other`.
+    fn log(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        let base = other.0.clone().ln();
+        if base.is_zero() {
+            return Err(MathError::DivideByZero);
+        }
+        result(Float::with_val(ctx.cfg.precision, &self.0.clone().ln() / &base))
+    }
+
+    fn ln(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.0.clone().ln())
+    }
+}
+
+/// `value` at `digits` significant digits, rounded towards `round`, without trailing zeros, and in
+/// scientific notation only when it is very large or small, as f64s are shown.
+pub fn format(value: &Float, digits: usize, round: Round) -> String {
+    if !value.is_finite() || value.is_zero() {
+        return value.to_f64().to_string();
+    }
+
+    let text = value.to_string_radix_round(10, Some(digits), round);
+    let (mantissa, exponent) = match text.split_once('e') {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().unwrap_or(0)),
+        None => (text.as_str(), 0),
+    };
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+
+    // the digits with the decimal point after the `point`th of them
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let all_digits = format!("{}{}", whole, fraction);
+    let point = whole.len() as i64 + exponent;
+    let trimmed = all_digits.trim_end_matches('0');
+    let leading = trimmed.len() - trimmed.trim_start_matches('0').len();
+    let (trimmed, point) = (&trimmed[leading..], point - leading as i64);
+
+    if point > digits as i64 || point < -5 {
+        let (first, rest) = trimmed.split_at(1);
+        let rest = if rest.is_empty() { String::new() } else { format!(".{}", rest) };
+        return format!("{}{}{}e{}", sign, first, rest, point - 1);
+    }
+
+    let text = match point {
+        point if point <= 0 => format!("0.{}{}", "0".repeat(-point as usize), trimmed),
+        point if point as usize >= trimmed.len() => format!("{}{}", trimmed, "0".repeat(point as usize - trimmed.len())),
+        point => format!("{}.{}", &trimmed[..point as usize], &trimmed[point as usize..]),
+    };
+    format!("{}{}", sign, text)
+}
+
+/// The number at the significant digits of its precision.
+impl Display for BigFloat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format(&self.0, self.digits(), Round::Nearest))
+    }
+}
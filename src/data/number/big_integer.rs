@@ -0,0 +1,270 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use mexprp::{Answer, Calculation, Context, Func, MathError, Num, Term};
+use rug::ops::Pow;
+use rug::Integer;
+use crate::data::number;
+
+/// An exact integer of any size. Operations whose result is not an integer, such as 2 / 3 or
+/// sqrt(2), fail instead of rounding.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct BigInteger(pub Integer);
+
+/// The error of an operation whose result is not an integer, `operation` being written as in the
+/// expression, as in sqrt(2).
+pub fn not_integer(operation: String) -> MathError {
+    MathError::Unimplemented { op: operation, num_type: BigInteger::typename() }
+}
+
+/// The most bits the result of a power may have, about 8 MB, beyond which it is refused instead of
+/// taking minutes or running out of memory.
+pub const MAX_POWER_BITS: u64 = 1 << 26;
+
+/// What [`too_large`] marks its errors with in place of the name of a type.
+pub const TOO_LARGE: &str = "too large";
+
+/// The error of an operation whose result would have more than [`MAX_POWER_BITS`] bits.
+pub fn too_large(operation: String) -> MathError {
+    MathError::Unimplemented { op: operation, num_type: String::from(TOO_LARGE) }
+}
+
+fn single(value: Integer) -> Calculation<BigInteger> {
+    Ok(Answer::Single(BigInteger(value)))
+}
+
+/// `max` or `min` of any number of arguments.
+struct Extreme(Ordering);
+
+impl Func<BigInteger> for Extreme {
+    fn eval(&self, args: &[Term<BigInteger>], ctx: &Context<BigInteger>) -> Calculation<BigInteger> {
+        let mut values = Vec::new();
+        for arg in args {
+            values.extend(arg.eval_ctx(ctx)?.to_vec());
+        }
+
+        let extreme = values.into_iter().reduce(|extreme, value| match value.0.cmp(&extreme.0) == self.0 {
+            true => value,
+            false => extreme,
+        });
+        extreme.map(Answer::Single).ok_or(MathError::IncorrectArguments)
+    }
+}
+
+impl BigInteger {
+    /// mexprp's builtin functions on integers. They are not taken from mexprp, which computes its
+    /// constants such as pi as numbers of the type.
+    pub fn builtins() -> Context<BigInteger> {
+        let mut context = number::method_builtins();
+        context.set_func("max", Extreme(Ordering::Greater));
+        context.set_func("min", Extreme(Ordering::Less));
+        context
+    }
+
+    /// The remainder of dividing by `other`, of the sign of `other`.
+    pub fn remainder(&self, other: &BigInteger) -> Calculation<BigInteger> {
+        if other.0 == 0 {
+            return Err(MathError::DivideByZero);
+        }
+        single(self.0.clone().div_rem_floor(other.0.clone()).1)
+    }
+}
+
+impl Num for BigInteger {
+    fn from_f64(t: f64, _ctx: &Context<Self>) -> Calculation<Self> {
+        match Integer::from_f64(t).filter(|_| t.fract() == 0f64) {
+            Some(integer) => single(integer),
+            None => Err(not_integer(t.to_string())),
+        }
+    }
+
+    fn from_f64_complex((real, imaginary): (f64, f64), ctx: &Context<Self>) -> Calculation<Self> {
+        match imaginary {
+            0f64 => Self::from_f64(real, ctx),
+            _ => Err(not_integer(format!("{} + {}i", real, imaginary))),
+        }
+    }
+
+    fn typename() -> String {
+        String::from("Integer")
+    }
+
+    fn tryord(&self, other: &Self, _ctx: &Context<Self>) -> Result<Ordering, MathError> {
+        Ok(self.0.cmp(&other.0))
+    }
+
+    fn add(&self, other: &Self, _ctx: &Context<Self>) -> Calculation<Self> {
+        single(Integer::from(&self.0 + &other.0))
+    }
+
+    fn sub(&self, other: &Self, _ctx: &Context<Self>) -> Calculation<Self> {
+        single(Integer::from(&self.0 - &other.0))
+    }
+
+    fn mul(&self, other: &Self, _ctx: &Context<Self>) -> Calculation<Self> {
+        single(Integer::from(&self.0 * &other.0))
+    }
+
+    fn div(&self, other: &Self, _ctx: &Context<Self>) -> Calculation<Self> {
+        if other.0 == 0 {
+            return Err(MathError::DivideByZero);
+        }
+        match self.0.is_divisible(&other.0) {
+            true => single(Integer::from(&self.0 / &other.0)),
+            false => Err(not_integer(format!("{} / {}", self, other))),
+        }
+    }
+
+    /// The power, refused when it would have more than [`MAX_POWER_BITS`] bits. 0, 1 and -1 are
+    /// raised to any exponent, and only 1 and -1 stay integers when raised to a negative one.
+    fn pow(&self, other: &Self, _ctx: &Context<Self>) -> Calculation<Self> {
+        match (self.0.to_i32(), other.0.cmp0()) {
+            (Some(0), Ordering::Less) => return Err(MathError::DivideByZero),
+            (Some(0), Ordering::Equal) | (Some(1), _) => return single(Integer::from(1)),
+            (Some(0), Ordering::Greater) => return single(Integer::new()),
+            (Some(-1), _) => return single(Integer::from(if other.0.is_even() { 1 } else { -1 })),
+            (_, Ordering::Less) => return Err(not_integer(format!("{}^{}", self, other))),
+            _ => {}
+        }
+
+        let bits = other.0.to_u64()
+            .and_then(|exponent| exponent.checked_mul(u64::from(self.0.significant_bits())))
+            .filter(|bits| *bits <= MAX_POWER_BITS);
+        match (bits, other.0.to_u32()) {
+            (Some(_), Some(exponent)) => single(self.0.clone().pow(exponent)),
+            _ => Err(too_large(format!("{}^{}", self, other))),
+        }
+    }
+
+    fn sqrt(&self, ctx: &Context<Self>) -> Calculation<Self> {
+        if self.0.cmp0() == Ordering::Less || !self.0.is_perfect_square() {
+            return Err(not_integer(format!("sqrt({})", self)));
+        }
+
+        let root = self.0.clone().sqrt();
+        match ctx.cfg.sqrt_both && root != 0 {
+            true => Ok(Answer::Multiple(vec![BigInteger(root.clone()), BigInteger(-root)])),
+            false => single(root),
+        }
+    }
+
+    /// The `other`th root, which must be an integer.
+    fn nrt(&self, other: &Self, _ctx: &Context<Self>) -> Calculation<Self> {
+        let not_root = || not_integer(format!("nrt({}, {})", self, other));
+        let degree = other.0.to_u32().filter(|degree| *degree > 0).ok_or_else(not_root)?;
+        if self.0.cmp0() == Ordering::Less && degree % 2 == 0 {
+            return Err(not_root());
+        }
+
+        let root = self.0.clone().root(degree);
+        match root.clone().pow(degree) == self.0 {
+            true => single(root),
+            false => Err(not_root()),
+        }
+    }
+
+    fn abs(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        single(self.0.clone().abs())
+    }
+
+    fn sin(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        Err(not_integer(format!("sin({})", self)))
+    }
+
+    fn cos(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        Err(not_integer(format!("cos({})", self)))
+    }
+
+    fn tan(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        Err(not_integer(format!("tan({})", self)))
+    }
+
+    fn asin(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        Err(not_integer(format!("asin({})", self)))
+    }
+
+    fn acos(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        Err(not_integer(format!("acos({})", self)))
+    }
+
+    fn atan(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        Err(not_integer(format!("atan({})", self)))
+    }
+
+    fn atan2(&self, other: &Self, _ctx: &Context<Self>) -> Calculation<Self> {
+        Err(not_integer(format!("atan2({}, {})", self, other)))
+    }
+
+    fn floor(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        single(self.0.clone())
+    }
+
+    fn ceil(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        single(self.0.clone())
+    }
+
+    fn round(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        single(self.0.clone())
+    }
+
+    /// The logarithm in base `other`, when it is an integer, as log(1000, 10) is.
+    fn log(&self, other: &Self, _ctx: &Context<Self>) -> Calculation<Self> {
+        let not_log = || not_integer(format!("log({}, {})", self, other));
+        if self.0.cmp0() != Ordering::Greater || other.0 <= 1 {
+            return Err(not_log());
+        }
+
+        let mut power = Integer::from(1);
+        let mut exponent = 0u32;
+        while power < self.0 {
+            power *= &other.0;
+            exponent += 1;
+        }
+        match power == self.0 {
+            true => single(Integer::from(exponent)),
+            false => Err(not_log()),
+        }
+    }
+
+    fn ln(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        match self.0 == 1 {
+            true => single(Integer::new()),
+            false => Err(not_integer(format!("ln({})", self))),
+        }
+    }
+}
+
+impl Display for BigInteger {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power(base: i64, exponent: i64) -> Result<Integer, MathError> {
+        BigInteger(Integer::from(base)).pow(&BigInteger(Integer::from(exponent)), &BigInteger::builtins())
+            .map(|power| power.unwrap_single().0)
+    }
+
+    #[test]
+    fn zero_and_one_are_raised_to_any_exponent() {
+        let huge = 1i64 << 40;
+
+        assert_eq!(power(1, huge).unwrap(), Integer::from(1));
+        assert_eq!(power(0, huge).unwrap(), Integer::from(0));
+        assert_eq!(power(0, 0).unwrap(), Integer::from(1));
+        assert_eq!(power(-1, huge + 1).unwrap(), Integer::from(-1));
+        assert_eq!(power(-1, -huge).unwrap(), Integer::from(1));
+        assert!(matches!(power(0, -1), Err(MathError::DivideByZero)));
+    }
+
+    #[test]
+    fn powers_too_large_to_compute_are_refused() {
+        assert_eq!(power(3, 5).unwrap(), Integer::from(243));
+        assert!(matches!(power(3, 4_000_000_000), Err(MathError::Unimplemented { num_type, .. }) if num_type == TOO_LARGE));
+        assert!(matches!(power(2, 1i64 << 40), Err(MathError::Unimplemented { num_type, .. }) if num_type == TOO_LARGE));
+        assert!(matches!(power(2, -1), Err(MathError::Unimplemented { num_type, .. }) if num_type == BigInteger::typename()));
+    }
+}
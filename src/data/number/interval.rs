@@ -0,0 +1,571 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use mexprp::{Answer, Calculation, Context, Func, MathError, Num, Term};
+use rug::float::{Constant, Round, Special};
+use rug::ops::{AssignRound, Pow};
+use rug::{Float, Integer};
+use crate::data::number;
+use crate::data::number::big_float;
+use crate::data::number::Unary;
+
+/// Builtins of intervals only, which a function of the file by the same name replaces.
+pub const INTERVAL_BUILTINS: [&str; 5] = ["interval", "lower", "upper", "mid", "width"];
+
+/// Significant digits the bounds are shown with. Fewer than the precision holds, so that 1.9 is
+/// not shown with all the digits of the binary number just below it.
+const DISPLAY_DIGITS: usize = 15;
+
+/// The real numbers from `lower` to `upper`, bounds included, which is what expressions of the
+/// interval number types are evaluated as. Every operation rounds the lower bound of its result down and the
+/// upper bound up, so that the result holds every value the operation can give for values of the
+/// operands, whatever the precision.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interval {
+    pub lower: Float,
+    pub upper: Float,
+}
+
+/// `value` rounded towards `round` at `precision` bits.
+fn rounded<T>(precision: u32, value: T, round: Round) -> Float
+where
+    Float: AssignRound<T, Round = Round, Ordering = Ordering>,
+{
+    Float::with_val_round(precision, value, round).0
+}
+
+fn smallest(values: impl IntoIterator<Item = Float>) -> Float {
+    values.into_iter().reduce(|smallest, value| if value < smallest { value } else { smallest }).unwrap()
+}
+
+fn largest(values: impl IntoIterator<Item = Float>) -> Float {
+    values.into_iter().reduce(|largest, value| if value > largest { value } else { largest }).unwrap()
+}
+
+/// The product of two bounds rounded towards `round`, where zero times an infinite bound is zero as
+/// in the product of [0, 0] and [1, inf].
+fn product(precision: u32, a: &Float, b: &Float, round: Round) -> Float {
+    match a.is_zero() || b.is_zero() {
+        true => Float::new(precision),
+        false => rounded(precision, a * b, round),
+    }
+}
+
+fn result(lower: Float, upper: Float) -> Calculation<Interval> {
+    match lower.is_nan() || upper.is_nan() {
+        true => Err(MathError::NaN),
+        false => Ok(Answer::Single(Interval { lower, upper })),
+    }
+}
+
+/// The single value of an operation on single intervals.
+fn single(answer: Answer<Interval>) -> Result<Interval, MathError> {
+    match answer {
+        Answer::Single(value) => Ok(value),
+        Answer::Multiple(_) => Err(MathError::Other),
+    }
+}
+
+/// An interval builtin of any number of arguments, `lower` and `upper` going from the bounds of
+/// two intervals to those of the result, as `max` takes the larger of each.
+struct Bounds(fn(Float, Float) -> Float, fn(Float, Float) -> Float);
+
+impl Func<Interval> for Bounds {
+    fn eval(&self, args: &[Term<Interval>], ctx: &Context<Interval>) -> Calculation<Interval> {
+        let mut values = Vec::new();
+        for arg in args {
+            values.extend(arg.eval_ctx(ctx)?.to_vec());
+        }
+
+        let bounds = values.into_iter().reduce(|bounds, value| Interval {
+            lower: (self.0)(bounds.lower, value.lower),
+            upper: (self.1)(bounds.upper, value.upper),
+        });
+        bounds.map(Answer::Single).ok_or(MathError::IncorrectArguments)
+    }
+}
+
+fn min(a: Float, b: Float) -> Float {
+    smallest([a, b])
+}
+
+fn max(a: Float, b: Float) -> Float {
+    largest([a, b])
+}
+
+impl Interval {
+    /// The interval of the single number `value`.
+    pub fn point(value: Float) -> Interval {
+        Interval { lower: value.clone(), upper: value }
+    }
+
+    /// The interval of every real number.
+    fn entire(precision: u32) -> Interval {
+        Interval {
+            lower: Float::with_val(precision, Special::NegInfinity),
+            upper: Float::with_val(precision, Special::Infinity),
+        }
+    }
+
+    /// pi between the numbers of the precision next to it.
+    fn pi(precision: u32) -> Interval {
+        Interval {
+            lower: rounded(precision, Constant::Pi, Round::Down),
+            upper: rounded(precision, Constant::Pi, Round::Up),
+        }
+    }
+
+    /// mexprp's builtins on intervals, with pi and e between the numbers of the precision next to
+    /// them, and the interval builtins: `interval(a, b, ...)` is the smallest interval holding all
+    /// of its arguments, `lower`, `upper`, `mid` and `width` give a bound, the middle and the width
+    /// of an interval. The bounds are of `precision` bits.
+    pub fn builtins(precision: u32) -> Context<Interval> {
+        let mut context = number::method_builtins();
+        context.cfg.precision = precision;
+
+        let one = Float::with_val(precision, 1);
+        context.set_var("pi", Interval::pi(precision));
+        context.set_var("e", Interval {
+            lower: rounded(precision, one.exp_ref(), Round::Down),
+            upper: rounded(precision, one.exp_ref(), Round::Up),
+        });
+
+        context.set_func("max", Bounds(max, max));
+        context.set_func("min", Bounds(min, min));
+        context.set_func("interval", Bounds(min, max));
+        context.set_func("lower", Unary(Interval::lower_bound));
+        context.set_func("upper", Unary(Interval::upper_bound));
+        context.set_func("mid", Unary(Interval::mid));
+        context.set_func("width", Unary(Interval::width));
+        context
+    }
+
+    fn lower_bound(&self, _ctx: &Context<Interval>) -> Calculation<Interval> {
+        result(self.lower.clone(), self.lower.clone())
+    }
+
+    fn upper_bound(&self, _ctx: &Context<Interval>) -> Calculation<Interval> {
+        result(self.upper.clone(), self.upper.clone())
+    }
+
+    /// The number in the middle, which is not rounded outward, being one number of the interval
+    /// rather than bounds.
+    fn mid(&self, ctx: &Context<Interval>) -> Calculation<Interval> {
+        let mid = rounded(ctx.cfg.precision, &self.lower + &self.upper, Round::Nearest) / 2u32;
+        result(mid.clone(), mid)
+    }
+
+    fn width(&self, ctx: &Context<Interval>) -> Calculation<Interval> {
+        result(
+            rounded(ctx.cfg.precision, &self.upper - &self.lower, Round::Down),
+            rounded(ctx.cfg.precision, &self.upper - &self.lower, Round::Up),
+        )
+    }
+
+    fn is_point(&self) -> bool {
+        self.lower == self.upper
+    }
+
+    fn contains_zero(&self) -> bool {
+        self.lower.cmp0() != Some(Ordering::Greater) && self.upper.cmp0() != Some(Ordering::Less)
+    }
+
+    fn holds_integer(&self) -> bool {
+        self.lower.clone().ceil() <= self.upper
+    }
+
+    /// The interval of `function` of the values in it, for a function increasing over them.
+    fn increasing<'a, T>(&'a self, precision: u32, function: impl Fn(&'a Float) -> T) -> Calculation<Interval>
+    where
+        Float: AssignRound<T, Round = Round, Ordering = Ordering>,
+    {
+        result(rounded(precision, function(&self.lower), Round::Down), rounded(precision, function(&self.upper), Round::Up))
+    }
+
+    /// The interval of `function` of the values in it, for a function decreasing over them.
+    fn decreasing<'a, T>(&'a self, precision: u32, function: impl Fn(&'a Float) -> T) -> Calculation<Interval>
+    where
+        Float: AssignRound<T, Round = Round, Ordering = Ordering>,
+    {
+        result(rounded(precision, function(&self.upper), Round::Down), rounded(precision, function(&self.lower), Round::Up))
+    }
+
+    /// The part of the interval from `lower`, or `None` when it is all below it, as sqrt and ln
+    /// leave out negative numbers.
+    fn above(&self, lower: Float) -> Option<Interval> {
+        match self.upper >= lower {
+            true => Some(Interval { lower: max(self.lower.clone(), lower), upper: self.upper.clone() }),
+            false => None,
+        }
+    }
+
+    /// The part of the interval from -1 to 1, the domain of asin and acos.
+    fn within_one(&self, ctx: &Context<Interval>) -> Result<Interval, MathError> {
+        let upper = Float::with_val(ctx.cfg.precision, 1);
+        let part = self.above(-upper.clone()).ok_or(MathError::NaN)?;
+        match part.lower <= upper {
+            true => Ok(Interval { lower: part.lower, upper: min(part.upper, upper) }),
+            false => Err(MathError::NaN),
+        }
+    }
+
+    /// The interval of sin or cos of the values in it, which are 1 at `quarter_turns` times pi / 2
+    /// plus a whole turn, and -1 half a turn away.
+    fn periodic<'a, T>(&'a self, ctx: &Context<Interval>, quarter_turns: u32, function: impl Fn(&'a Float) -> T) -> Calculation<Interval>
+    where
+        Float: AssignRound<T, Round = Round, Ordering = Ordering>,
+    {
+        let precision = ctx.cfg.precision;
+        let mut lower = min(rounded(precision, function(&self.lower), Round::Down), rounded(precision, function(&self.upper), Round::Down));
+        let mut upper = max(rounded(precision, function(&self.lower), Round::Up), rounded(precision, function(&self.upper), Round::Up));
+
+        if single(self.turns(ctx, quarter_turns)?)?.holds_integer() {
+            upper = Float::with_val(precision, 1);
+        }
+        if single(self.turns(ctx, quarter_turns + 2)?)?.holds_integer() {
+            lower = Float::with_val(precision, -1);
+        }
+        result(lower, upper)
+    }
+
+    /// The whole turns from `quarter_turns` times pi / 2 to the values in the interval, which holds
+    /// an integer when the interval holds a point a whole number of turns away from it.
+    fn turns(&self, ctx: &Context<Interval>, quarter_turns: u32) -> Calculation<Interval> {
+        let pi = Interval::pi(ctx.cfg.precision);
+        let quarter = Interval::point(Float::with_val(ctx.cfg.precision, quarter_turns) / 2u32);
+        let turn = Interval::point(Float::with_val(ctx.cfg.precision, 2));
+
+        let start = single(quarter.mul(&pi, ctx)?)?;
+        let offset = single(self.sub(&start, ctx)?)?;
+        offset.div(&single(turn.mul(&pi, ctx)?)?, ctx)
+    }
+
+    /// The interval raised to the integer `exponent`.
+    fn integer_pow(&self, exponent: &Integer, ctx: &Context<Interval>) -> Calculation<Interval> {
+        let precision = ctx.cfg.precision;
+        if exponent.cmp0() == Ordering::Less {
+            let one = Interval::point(Float::with_val(precision, 1));
+            return one.div(&single(self.integer_pow(&Integer::from(-exponent), ctx)?)?, ctx);
+        }
+
+        let power = |bound: &Float, round: Round| rounded(precision, bound.pow(exponent), round);
+        if exponent.is_odd() {
+            return result(power(&self.lower, Round::Down), power(&self.upper, Round::Up));
+        }
+
+        // an even power is smallest where the number is closest to zero
+        let (lower, upper) = (self.lower.clone().abs(), self.upper.clone().abs());
+        let (nearest, furthest) = match self.contains_zero() {
+            true => (Float::new(precision), max(lower, upper)),
+            false => (min(lower.clone(), upper.clone()), max(lower, upper)),
+        };
+        result(power(&nearest, Round::Down), power(&furthest, Round::Up))
+    }
+}
+
+impl Num for Interval {
+    /// The interval between the numbers of the precision next to the shortest decimal that gives
+    /// `t`, so that a literal such as 0.1 holds the decimal rather than an f64 near it.
+    fn from_f64(t: f64, ctx: &Context<Self>) -> Calculation<Self> {
+        if !t.is_finite() {
+            return result(Float::with_val(ctx.cfg.precision, t), Float::with_val(ctx.cfg.precision, t));
+        }
+
+        let text = t.to_string();
+        let bound = |round: Round| Float::parse(&text)
+            .map(|parsed| rounded(ctx.cfg.precision, parsed, round))
+            .map_err(|_| MathError::Other);
+        result(bound(Round::Down)?, bound(Round::Up)?)
+    }
+
+    fn from_f64_complex((real, imaginary): (f64, f64), ctx: &Context<Self>) -> Calculation<Self> {
+        match imaginary {
+            0f64 => Self::from_f64(real, ctx),
+            _ => Err(MathError::Unimplemented { op: format!("{} + {}i", real, imaginary), num_type: Self::typename() }),
+        }
+    }
+
+    fn typename() -> String {
+        String::from("Interval")
+    }
+
+    /// The order of two intervals all of whose values are in that order, an error for intervals
+    /// that overlap, unless they are the same single number.
+    fn tryord(&self, other: &Self, _ctx: &Context<Self>) -> Result<Ordering, MathError> {
+        self.partial_cmp(other).ok_or(MathError::CmpError)
+    }
+
+    fn add(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        result(
+            rounded(ctx.cfg.precision, &self.lower + &other.lower, Round::Down),
+            rounded(ctx.cfg.precision, &self.upper + &other.upper, Round::Up),
+        )
+    }
+
+    fn sub(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        result(
+            rounded(ctx.cfg.precision, &self.lower - &other.upper, Round::Down),
+            rounded(ctx.cfg.precision, &self.upper - &other.lower, Round::Up),
+        )
+    }
+
+    fn mul(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        let corners = [(&self.lower, &other.lower), (&self.lower, &other.upper), (&self.upper, &other.lower), (&self.upper, &other.upper)];
+        result(
+            smallest(corners.map(|(a, b)| product(ctx.cfg.precision, a, b, Round::Down))),
+            largest(corners.map(|(a, b)| product(ctx.cfg.precision, a, b, Round::Up))),
+        )
+    }
+
+    /// The quotient, which is every real number when dividing by an interval holding zero inside
+    /// it, and unbounded on one side when it is a bound.
+    fn div(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        let precision = ctx.cfg.precision;
+        if other.lower.is_zero() && other.upper.is_zero() {
+            return Err(MathError::DivideByZero);
+        }
+
+        if !other.contains_zero() {
+            let corners = [(&self.lower, &other.lower), (&self.lower, &other.upper), (&self.upper, &other.lower), (&self.upper, &other.upper)];
+            return result(
+                smallest(corners.map(|(a, b)| rounded(precision, a / b, Round::Down))),
+                largest(corners.map(|(a, b)| rounded(precision, a / b, Round::Up))),
+            );
+        }
+
+        let reciprocal = match (other.lower.is_zero(), other.upper.is_zero()) {
+            (true, false) => Interval {
+                lower: rounded(precision, 1 / &other.upper, Round::Down),
+                upper: Float::with_val(precision, Special::Infinity),
+            },
+            (false, true) => Interval {
+                lower: Float::with_val(precision, Special::NegInfinity),
+                upper: rounded(precision, 1 / &other.lower, Round::Up),
+            },
+            _ => Interval::entire(precision),
+        };
+        self.mul(&reciprocal, ctx)
+    }
+
+    /// The power, which for an exponent other than an integer leaves out negative numbers of the
+    /// base, where it is not real.
+    fn pow(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        if other.is_point() {
+            if let Some(exponent) = other.lower.to_integer().filter(|_| other.lower.is_integer()) {
+                return self.integer_pow(&exponent, ctx);
+            }
+        }
+
+        let base = self.above(Float::new(ctx.cfg.precision)).ok_or(MathError::NaN)?;
+        let corners = [(&base.lower, &other.lower), (&base.lower, &other.upper), (&base.upper, &other.lower), (&base.upper, &other.upper)];
+        result(
+            smallest(corners.map(|(a, b)| rounded(ctx.cfg.precision, a.pow(b), Round::Down))),
+            largest(corners.map(|(a, b)| rounded(ctx.cfg.precision, a.pow(b), Round::Up))),
+        )
+    }
+
+    fn sqrt(&self, ctx: &Context<Self>) -> Calculation<Self> {
+        let root = self.above(Float::new(ctx.cfg.precision))
+            .ok_or(MathError::NaN)?
+            .increasing(ctx.cfg.precision, Float::sqrt_ref)?;
+
+        match (ctx.cfg.sqrt_both, root) {
+            (true, Answer::Single(root)) if !root.upper.is_zero() => {
+                let negative = Interval { lower: -root.upper.clone(), upper: -root.lower.clone() };
+                Ok(Answer::Multiple(vec![root, negative]))
+            }
+            (_, root) => Ok(root),
+        }
+    }
+
+    /// The `other`th root, for a single positive integer, the power of 1 / `other` otherwise.
+    fn nrt(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        let degree = other.lower.to_u32_saturating().filter(|degree| other.is_point() && other.lower.is_integer() && *degree > 0);
+        match degree {
+            Some(degree) if degree % 2 == 1 => self.increasing(ctx.cfg.precision, |bound| bound.root_ref(degree)),
+            Some(degree) => self.above(Float::new(ctx.cfg.precision))
+                .ok_or(MathError::NaN)?
+                .increasing(ctx.cfg.precision, |bound| bound.root_ref(degree)),
+            None => {
+                let one = Interval::point(Float::with_val(ctx.cfg.precision, 1));
+                self.pow(&single(one.div(other, ctx)?)?, ctx)
+            }
+        }
+    }
+
+    fn abs(&self, ctx: &Context<Self>) -> Calculation<Self> {
+        match (self.lower.cmp0(), self.upper.cmp0()) {
+            (Some(Ordering::Equal | Ordering::Greater), _) => Ok(Answer::Single(self.clone())),
+            (_, Some(Ordering::Equal | Ordering::Less)) => result(-self.upper.clone(), -self.lower.clone()),
+            _ => result(Float::new(ctx.cfg.precision), max(-self.lower.clone(), self.upper.clone())),
+        }
+    }
+
+    fn sin(&self, ctx: &Context<Self>) -> Calculation<Self> {
+        self.periodic(ctx, 1, Float::sin_ref)
+    }
+
+    fn cos(&self, ctx: &Context<Self>) -> Calculation<Self> {
+        self.periodic(ctx, 0, Float::cos_ref)
+    }
+
+    /// The tangent, which is every real number when the interval holds a pole.
+    fn tan(&self, ctx: &Context<Self>) -> Calculation<Self> {
+        let pi = Interval::pi(ctx.cfg.precision);
+        let half = Interval::point(Float::with_val(ctx.cfg.precision, 0.5));
+        let poles = single(self.div(&pi, ctx)?)?;
+        match single(poles.sub(&half, ctx)?)?.holds_integer() {
+            true => Ok(Answer::Single(Interval::entire(ctx.cfg.precision))),
+            false => self.increasing(ctx.cfg.precision, Float::tan_ref),
+        }
+    }
+
+    fn asin(&self, ctx: &Context<Self>) -> Calculation<Self> {
+        self.within_one(ctx)?.increasing(ctx.cfg.precision, Float::asin_ref)
+    }
+
+    fn acos(&self, ctx: &Context<Self>) -> Calculation<Self> {
+        self.within_one(ctx)?.decreasing(ctx.cfg.precision, Float::acos_ref)
+    }
+
+    fn atan(&self, ctx: &Context<Self>) -> Calculation<Self> {
+        self.increasing(ctx.cfg.precision, Float::atan_ref)
+    }
+
+    /// The angle of the points with the interval as y and `other` as x, which is the whole turn
+    /// from -pi to pi when they reach the negative x axis, where the angle jumps.
+    fn atan2(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        let precision = ctx.cfg.precision;
+        if other.lower.cmp0() != Some(Ordering::Greater) && self.contains_zero() {
+            let pi = Interval::pi(precision);
+            return result(-pi.upper.clone(), pi.upper);
+        }
+
+        let corners = [(&self.lower, &other.lower), (&self.lower, &other.upper), (&self.upper, &other.lower), (&self.upper, &other.upper)];
+        result(
+            smallest(corners.map(|(y, x)| rounded(precision, y.atan2_ref(x), Round::Down))),
+            largest(corners.map(|(y, x)| rounded(precision, y.atan2_ref(x), Round::Up))),
+        )
+    }
+
+    fn floor(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.lower.clone().floor(), self.upper.clone().floor())
+    }
+
+    fn ceil(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.lower.clone().ceil(), self.upper.clone().ceil())
+    }
+
+    fn round(&self, _ctx: &Context<Self>) -> Calculation<Self> {
+        result(self.lower.clone().round(), self.upper.clone().round())
+    }
+
+    /// The logarithm in base `other`.
+    fn log(&self, other: &Self, ctx: &Context<Self>) -> Calculation<Self> {
+        let base = single(other.ln(ctx)?)?;
+        single(self.ln(ctx)?)?.div(&base, ctx)
+    }
+
+    /// The natural logarithm, leaving out the numbers that are not positive.
+    fn ln(&self, ctx: &Context<Self>) -> Calculation<Self> {
+        self.above(Float::new(ctx.cfg.precision))
+            .filter(|positive| !positive.upper.is_zero())
+            .ok_or(MathError::NaN)?
+            .increasing(ctx.cfg.precision, Float::ln_ref)
+    }
+}
+
+
+/// Intervals are ordered when all the values of one are below all those of the other. Two intervals
+/// are equal only when they are the same single number.
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.upper < other.lower {
+            Some(Ordering::Less)
+        } else if self.lower > other.upper {
+            Some(Ordering::Greater)
+        } else if self.is_point() && self == other {
+            Some(Ordering::Equal)
+        } else {
+            None
+        }
+    }
+}
+
+/// The bounds as `[lower, upper]`, each rounded outward, so that the interval shown holds the one
+/// computed.
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let digits = DISPLAY_DIGITS.min(((self.lower.prec() as f64 * std::f64::consts::LOG10_2).floor() as usize).max(1));
+        write!(f, "[{}, {}]", big_float::format(&self.lower, digits, Round::Down), big_float::format(&self.upper, digits, Round::Up))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRECISION: u32 = 64;
+
+    /// Enough more bits than the intervals have to stand for the exact value next to them.
+    const EXACT: u32 = 512;
+
+    fn point(value: i32) -> Interval {
+        Interval::point(Float::with_val(PRECISION, value))
+    }
+
+    fn holds(interval: &Interval, exact: &Float) {
+        assert!(interval.lower <= *exact && *exact <= interval.upper, "{} does not hold {}", interval, exact);
+        assert!(interval.lower < interval.upper, "{} is not rounded outward", interval);
+    }
+
+    #[test]
+    fn division_is_rounded_outward() {
+        let ctx = Interval::builtins(PRECISION);
+        let third = point(1).div(&point(3), &ctx).unwrap().unwrap_single();
+
+        let exact = Float::with_val(EXACT, 1) / 3u32;
+        holds(&third, &exact);
+    }
+
+    #[test]
+    fn division_by_an_interval_ending_at_zero_is_unbounded() {
+        let ctx = Interval::builtins(PRECISION);
+        let divisor = Interval { lower: Float::new(PRECISION), upper: Float::with_val(PRECISION, 2) };
+        let quotient = point(1).div(&divisor, &ctx).unwrap().unwrap_single();
+
+        assert_eq!(quotient.lower, Float::with_val(PRECISION, 0.5));
+        assert_eq!(quotient.upper, Float::with_val(PRECISION, Special::Infinity));
+        assert!(matches!(point(1).div(&point(0), &ctx), Err(MathError::DivideByZero)));
+    }
+
+    #[test]
+    fn sine_is_rounded_outward_and_reaches_its_maximum() {
+        let ctx = Interval::builtins(PRECISION);
+        let tenth = Interval::from_f64(0.1, &ctx).unwrap().unwrap_single();
+        let sine = tenth.sin(&ctx).unwrap().unwrap_single();
+
+        let exact = Float::with_val(EXACT, Float::parse("0.1").unwrap()).sin();
+        holds(&sine, &exact);
+
+        // pi / 2 is between 1 and 2, so the sine of the interval goes up to 1 exactly
+        let around_peak = Interval { lower: Float::with_val(PRECISION, 1), upper: Float::with_val(PRECISION, 2) };
+        let sine = around_peak.sin(&ctx).unwrap().unwrap_single();
+        assert_eq!(sine.upper, Float::with_val(PRECISION, 1));
+        assert!(sine.lower < Float::with_val(PRECISION, 1));
+    }
+
+    #[test]
+    fn powers_are_rounded_outward() {
+        let ctx = Interval::builtins(PRECISION);
+        let half = Interval::point(Float::with_val(PRECISION, 0.5));
+        let root = point(2).pow(&half, &ctx).unwrap().unwrap_single();
+
+        let exact = Float::with_val(EXACT, 2).sqrt();
+        holds(&root, &exact);
+
+        // an even power of an interval around zero is smallest at zero
+        let around_zero = Interval { lower: Float::with_val(PRECISION, -2), upper: Float::with_val(PRECISION, 3) };
+        let square = around_zero.pow(&point(2), &ctx).unwrap().unwrap_single();
+        assert_eq!(square, Interval { lower: Float::new(PRECISION), upper: Float::with_val(PRECISION, 9) });
+    }
+}
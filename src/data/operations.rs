@@ -3,8 +3,9 @@ use std::fmt::Debug;
 use std::thread::JoinHandle;
 use tree_sitter_beanie::data::expression::expression_type::ExpressionType;
 use crate::data::context::BeanieRuntimeContext;
+use crate::data::error::{BeanieError, BeanieResult};
 use crate::data::expression::BeanieExpression;
-use crate::utilities::logger;
+use crate::data::span::Span;
 
 pub mod print_operation;
 pub mod graph_operation;
@@ -12,27 +13,72 @@ pub mod use_operation;
 pub mod in_operation;
 pub mod out_operation;
 
+/// Work an operation hands off to another thread, such as rendering a graph, joined before the run
+/// ends.
+pub type BackgroundTask = JoinHandle<BeanieResult<()>>;
+
 pub trait Operation: Debug {
-    fn execute(&self, context: &mut BeanieRuntimeContext, parameters: &Vec<String>, threads_to_wait_for: &mut Vec<JoinHandle<()>>);
-    fn add_argument(&mut self, name: String, expression: BeanieExpression);
+    fn execute(&self, context: &mut BeanieRuntimeContext, inputs: &HashMap<String, String>, threads_to_wait_for: &mut Vec<BackgroundTask>) -> BeanieResult<()>;
+    fn add_argument(&mut self, name: String, expression: BeanieExpression) -> BeanieResult<()>;
+
+    /// Validates the operation without executing it, adding every problem found to `problems`.
+    /// Operations are checked in order, so this may record what later operations rely on in
+    /// `context` the way executing would.
+    fn check(&self, context: &mut BeanieRuntimeContext, problems: &mut Vec<BeanieError>);
+
+    /// Where the instruction was written, if it came from a file.
+    fn span(&self) -> Option<&Span>;
+
+    /// Whether the operation sets up what the output is evaluated from, as `in` and `use` do,
+    /// rather than printing, graphing or writing results.
+    fn prepares_output(&self) -> bool {
+        false
+    }
 }
 
-pub fn no_argument(instruction_name: &str) {
-    logger::log_error(format!("There are no valid arguments to the instruction {}", instruction_name).as_str());
+/// Executes `operation`, pointing errors that do not know where they come from at the instruction.
+pub fn execute(operation: &dyn Operation, context: &mut BeanieRuntimeContext, inputs: &HashMap<String, String>, threads_to_wait_for: &mut Vec<BackgroundTask>) -> BeanieResult<()> {
+    operation.execute(context, inputs, threads_to_wait_for).map_err(|err| err.at(operation.span()))
 }
 
-pub fn verify_argument(instruction_name: &str, name: &String, expression: &BeanieExpression, valid_arguments: &HashMap<String, ExpressionType>, arguments: &mut HashMap<String, BeanieExpression>) {
+pub fn no_argument(instruction_name: &str) -> BeanieResult<()> {
+    Err(BeanieError::Resolution(format!("There are no valid arguments to the instruction {}", instruction_name)))
+}
+
+pub fn verify_argument(instruction_name: &str, name: &String, expression: &BeanieExpression, valid_arguments: &HashMap<String, ExpressionType>, arguments: &mut HashMap<String, BeanieExpression>) -> BeanieResult<()> {
     if valid_arguments.contains_key(name) {
         let expected_type = &valid_arguments[name];
         let got_type = &expression.get_type();
         
         if expected_type == got_type {
             arguments.insert(name.clone(), expression.clone());
-            return;
+            return Ok(());
         } else {
-            logger::log_error(format!("Argument {} for instruction {} has the wrong type. Expected {}, got {}", name, instruction_name, expected_type, got_type).as_str())
+            return Err(BeanieError::Type(format!("Argument {} for instruction {} has the wrong type. Expected {}, got {}", name, instruction_name, expected_type, got_type)));
         }
     }
     
-    logger::log_error(format!("Argument {} is not valid for instruction {}", name, instruction_name).as_str());
-}
\ No newline at end of file
+    Err(BeanieError::Resolution(format!("Argument {} is not valid for instruction {}", name, instruction_name)))
+}
+
+pub fn check_arguments(context: &BeanieRuntimeContext, arguments: &HashMap<String, BeanieExpression>, problems: &mut Vec<BeanieError>) {
+    for expression in arguments.values() {
+        expression.check(context, problems);
+    }
+}
+
+/// Waits for every background task, returning the first error any of them ran into.
+pub fn join_all(threads_to_wait_for: Vec<BackgroundTask>) -> BeanieResult<()> {
+    let mut result = Ok(());
+
+    for thread in threads_to_wait_for {
+        let thread_result = thread.join()
+            .unwrap_or_else(|_| Err(BeanieError::Io(String::from("A background task stopped unexpectedly"))));
+
+        if result.is_ok() {
+            result = thread_result;
+        }
+    }
+
+    result
+}
@@ -1,27 +1,35 @@
 use std::collections::HashMap;
+use std::sync::atomic::Ordering;
 use std::thread;
-use std::thread::JoinHandle;
 use pgfplots::axis::{Axis, AxisKey};
 use pgfplots::axis::plot::Plot2D;
 use pgfplots::{Engine, Picture};
 use tree_sitter_beanie::data::instructions::graph_operation_args::{GRAPH_ARGUMENTS, MIN_X, MIN_Y, MAX_X, MAX_Y, STEP_X, STEP_Y, TITLE, LABEL_X, LABEL_Y};
 use crate::data::context::BeanieRuntimeContext;
 use crate::data::expression::BeanieExpression;
-use crate::data::operations::Operation;
-use crate::utilities::logger;
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::number::NumberType;
+use crate::data::operations::{BackgroundTask, Operation};
+use crate::data::span::Span;
 use crate::data::operations;
+use crate::NO_GRAPH;
+
+/// A legend entry, if any, and the points of one line on a graph.
+pub type Series = (Option<String>, Vec<(f64, f64)>);
 
 #[derive(Debug, Clone)]
 pub struct GraphOperation {
     function_name: String,
-    arguments: HashMap<String, BeanieExpression>
+    arguments: HashMap<String, BeanieExpression>,
+    span: Option<Span>,
 }
 
 impl GraphOperation {
-    pub fn new(function_name: String) -> GraphOperation {
+    pub fn new(function_name: String, span: Option<Span>) -> GraphOperation {
         
         GraphOperation {
             function_name,
+            span,
             arguments: hashmap! {
                 String::from(MIN_X) => BeanieExpression::SimpleF64(-10f64),
                 String::from(MIN_Y) => BeanieExpression::SimpleF64(-10f64),
@@ -34,42 +42,44 @@ impl GraphOperation {
     }
 }
 
-impl Operation for GraphOperation {
-    fn execute(&self, context: &mut BeanieRuntimeContext, _: &Vec<String>, threads_to_wait_for: &mut Vec<JoinHandle<()>>) {
-        if !context.has_function(&self.function_name) {
-            logger::log_error(format!("Can not graph function {} because it does not exist", self.function_name).as_str());
-            unreachable!()
-        }
+impl GraphOperation {
+    /// Draws every series, an optional legend entry with its points, onto one axis configured by
+    /// the graph arguments.
+    pub fn plot(&self, series: Vec<Series>, context: &BeanieRuntimeContext, threads_to_wait_for: &mut Vec<BackgroundTask>) -> BeanieResult<()> {
+        let min_x = self.arguments[MIN_X].evaluate_single::<f64>(context)?.round() as i32;
+        let min_y = self.arguments[MIN_Y].evaluate_single::<f64>(context)?.round() as i32;
+        let max_x = self.arguments[MAX_X].evaluate_single::<f64>(context)?.round() as i32;
+        let max_y = self.arguments[MAX_Y].evaluate_single::<f64>(context)?.round() as i32;
+        let step_x = self.arguments[STEP_X].evaluate_single::<f64>(context)?;
+        let step_y = self.arguments[STEP_Y].evaluate_single::<f64>(context)?;
 
-        let function = context.get_function(&self.function_name).unwrap();
-        
-        let min_x = self.arguments[MIN_X].evaluate::<f64>(context).unwrap_single().round() as i32;
-        let min_y = self.arguments[MIN_Y].evaluate::<f64>(context).unwrap_single().round() as i32;
-        let max_x = self.arguments[MAX_Y].evaluate::<f64>(context).unwrap_single().round() as i32;
-        let max_y = self.arguments[MAX_Y].evaluate::<f64>(context).unwrap_single().round() as i32;
-        let step_x = self.arguments[STEP_X].evaluate::<f64>(context).unwrap_single();
-        let step_y = self.arguments[STEP_Y].evaluate::<f64>(context).unwrap_single();
-
-        let mut plot = Plot2D::new();
-        plot.coordinates = (min_x..max_x)
-            .map(|i| (f64::from(i), f64::from(function.evaluate(vec![f64::from(i)], context))).into())
-            .collect();
-        
         let mut axis = Axis::new();
         
         if self.arguments.contains_key(TITLE) {
-            axis.set_title(self.arguments[TITLE].evaluate_to_string(context));
+            axis.set_title(self.arguments[TITLE].evaluate_to_string(context)?);
         } 
             
         if self.arguments.contains_key(LABEL_X) { 
-            axis.set_x_label(self.arguments[LABEL_X].evaluate_to_string(context));
+            axis.set_x_label(self.arguments[LABEL_X].evaluate_to_string(context)?);
         }
 
         if self.arguments.contains_key(LABEL_Y) {
-            axis.set_y_label(self.arguments[LABEL_Y].evaluate_to_string(context));
+            axis.set_y_label(self.arguments[LABEL_Y].evaluate_to_string(context)?);
+        }
+
+        let legend_entries: Vec<String> = series.iter()
+            .filter_map(|(legend_entry, _)| legend_entry.as_ref().map(|entry| format!("{{{}}}", entry)))
+            .collect();
+
+        for (_, points) in series {
+            let mut plot = Plot2D::new();
+            plot.coordinates = points.into_iter().map(|point| point.into()).collect();
+            axis.plots.push(plot);
         }
 
-        axis.plots.push(plot);
+        if !legend_entries.is_empty() {
+            axis.add_key(AxisKey::Custom(format!("legend entries={{{}}}", legend_entries.join(","))));
+        }
 
         axis.add_key(AxisKey::Custom(format!("ymin={}, ymax={}", min_y, max_y)));
         axis.add_key(AxisKey::Custom(format!("xmin={}, xmax={}", min_x, max_x)));
@@ -81,13 +91,80 @@ impl Operation for GraphOperation {
         axis.add_key(AxisKey::Custom(String::from("ylabel near ticks")));
         axis.add_key(AxisKey::Custom(String::from("scale only axis")));
 
+        if NO_GRAPH.load(Ordering::Relaxed) { return Ok(()); }
+
         // we wanna spawn a separate thread to do this so it doesn't block the main thread
         threads_to_wait_for.push(thread::spawn(|| {
-            Picture::from(axis).show_pdf(Engine::Tectonic).unwrap();
+            Picture::from(axis).show_pdf(Engine::Tectonic)
+                .map_err(|err| BeanieError::Io(format!("Failed to render graph. Error: {:?}", err)))
         }));
+
+        Ok(())
+    }
+}
+
+impl Operation for GraphOperation {
+    fn execute(&self, context: &mut BeanieRuntimeContext, _: &HashMap<String, String>, threads_to_wait_for: &mut Vec<BackgroundTask>) -> BeanieResult<()> {
+        let function = match context.get_function(&self.function_name) {
+            Some(function) => function,
+            None => return Err(BeanieError::Resolution(format!("Can not graph function {} because it does not exist", self.function_name))),
+        };
+        
+        let min_x = self.arguments[MIN_X].evaluate_single::<f64>(context)?.round() as i32;
+        let max_x = self.arguments[MAX_X].evaluate_single::<f64>(context)?.round() as i32;
+
+        // each value ± gives the function is drawn as a line of its own, which it must therefore
+        // give everywhere, and so is each bound of an interval function
+        let mut branches: Vec<Vec<(f64, f64, f64)>> = Vec::new();
+        for i in min_x..max_x {
+            let x = f64::from(i);
+            let values = function.evaluate(vec![x], context)?;
+
+            if branches.is_empty() {
+                branches = vec![Vec::new(); values.len()];
+            } else if values.len() != branches.len() {
+                return Err(BeanieError::Type(format!("Can not graph function {} because it gives {} values at {} but {} at {}", self.function_name, branches.len(), min_x, values.len(), x)));
+            }
+
+            for (points, (lower, upper)) in branches.iter_mut().zip(values) {
+                points.push((x, lower, upper));
+            }
+        }
+
+        let bounded = matches!(context.function_type(&self.function_name, &mut Vec::new()), Some(NumberType::Interval(_)));
+        let count = branches.len();
+        let name = |index: usize| match count {
+            1 => self.function_name.clone(),
+            _ => format!("{} ({})", self.function_name, index + 1),
+        };
+
+        let series = match (count, bounded) {
+            (1, false) => branches.into_iter().map(|points| (None, points.into_iter().map(|(x, y, _)| (x, y)).collect())).collect(),
+            (_, false) => branches.into_iter().enumerate().map(|(index, points)| (Some(name(index)), points.into_iter().map(|(x, y, _)| (x, y)).collect())).collect(),
+            (_, true) => branches.into_iter().enumerate().flat_map(|(index, points)| [
+                (Some(format!("lower bound of {}", name(index))), points.iter().map(|(x, lower, _)| (*x, *lower)).collect()),
+                (Some(format!("upper bound of {}", name(index))), points.iter().map(|(x, _, upper)| (*x, *upper)).collect()),
+            ]).collect(),
+        };
+
+        self.plot(series, context, threads_to_wait_for)
+    }
+
+    fn add_argument(&mut self, name: String, expression: BeanieExpression) -> BeanieResult<()> {
+        operations::verify_argument("Graph", &name, &expression, &GRAPH_ARGUMENTS, &mut self.arguments)
+    }
+
+    fn check(&self, context: &mut BeanieRuntimeContext, problems: &mut Vec<BeanieError>) {
+        match context.get_function(&self.function_name) {
+            Some(function) if function.parameters.len() != 1 => problems.push(BeanieError::Type(format!("Can not graph function {} because it takes {} parameters instead of 1", self.function_name, function.parameters.len()))),
+            Some(_) => {}
+            None => problems.push(BeanieError::Resolution(format!("Can not graph function {} because it does not exist", self.function_name))),
+        }
+
+        operations::check_arguments(context, &self.arguments, problems);
     }
 
-    fn add_argument(&mut self, name: String, expression: BeanieExpression) {
-        operations::verify_argument("Graph", &name, &expression, &GRAPH_ARGUMENTS, &mut self.arguments);
+    fn span(&self) -> Option<&Span> {
+        self.span.as_ref()
     }
 }
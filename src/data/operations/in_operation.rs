@@ -1,45 +1,78 @@
-use std::thread::JoinHandle;
-use crate::DEFAULT_DATA_TYPE;
+use std::collections::HashMap;
 use crate::data::context::BeanieRuntimeContext;
+use crate::data::error::{BeanieError, BeanieResult};
 use crate::data::expression::BeanieExpression;
-use crate::data::operations::Operation;
+use crate::data::number::NumberType;
+use crate::data::operations::{BackgroundTask, Operation};
+use crate::data::span::Span;
 use crate::data::operations;
-use crate::utilities::logger;
 
 #[derive(Debug, Clone)]
 pub struct InOperation {
     input_name: String,
+    /// The number type of the math of the file, which the value given is of.
+    number_type: Option<NumberType>,
+    span: Option<Span>,
 }
 
 impl InOperation {
-    pub fn new(input_name: String) -> InOperation {
+    pub fn new(input_name: String, number_type: Option<NumberType>, span: Option<Span>) -> InOperation {
         InOperation {
-            input_name
+            input_name,
+            number_type,
+            span,
         }
     }
 }
 
 impl Operation for InOperation {
-    fn execute(&self, context: &mut BeanieRuntimeContext, parameters: &Vec<String>, _: &mut Vec<JoinHandle<()>>) {
-        if let Some(index) = context.inputs.iter().position(|val| val == &self.input_name) {
-            if index >= parameters.len() {
-                logger::log_error("There are more required inputs than the number of inputs given");
-                unreachable!()
-            }
-            
-            unsafe {
-                context.constants.insert(
-                    vec![self.input_name.clone()],
-                    BeanieExpression::Math(parameters[index].to_string(), DEFAULT_DATA_TYPE.clone()),
-                );
+    fn execute(&self, context: &mut BeanieRuntimeContext, inputs: &HashMap<String, String>, _: &mut Vec<BackgroundTask>) -> BeanieResult<()> {
+        if !context.inputs.contains(&self.input_name) {
+            return Err(BeanieError::Resolution(format!("Failed to find input {} within the file context", &self.input_name)));
+        }
+
+        match inputs.get(&self.input_name) {
+            Some(value) => {
+                let mut expression = BeanieExpression::math(value.to_string(), self.number_type, &[]);
+
+                // a list given on the command line as 1,2,3
+                if expression.syntax_error().is_some() && value.contains(',') {
+                    let list = BeanieExpression::math(format!("[{}]", value), self.number_type, &[]);
+                    if list.syntax_error().is_none() {
+                        expression = list;
+                    }
+                }
+
+                // an interval given on the command line as [1.9,2.1], rather than a list of two
+                if let Some(bounds) = interval_bounds(value, self.number_type) {
+                    expression = BeanieExpression::math(format!("interval({})", bounds), self.number_type, &[]);
+                }
+
+                context.insert_constant(vec![self.input_name.clone()], expression.located(self.span.clone()));
+                Ok(())
             }
-        } else { 
-            logger::log_error(format!("Failed to find input {} within the file context", &self.input_name).as_str());
-            unreachable!()
+            None => Err(BeanieError::Input(format!("No value given for input {}", &self.input_name))),
         }
     }
 
-    fn add_argument(&mut self, _: String, _: BeanieExpression) {
-        operations::no_argument("In");
+    fn add_argument(&mut self, _: String, _: BeanieExpression) -> BeanieResult<()> {
+        operations::no_argument("In")
     }
+
+    fn check(&self, _: &mut BeanieRuntimeContext, _: &mut Vec<BeanieError>) {}
+
+    fn span(&self) -> Option<&Span> {
+        self.span.as_ref()
+    }
+
+    fn prepares_output(&self) -> bool {
+        true
+    }
+}
+
+/// The bounds of `value` when it is two numbers in brackets and `number_type` is intervals.
+fn interval_bounds(value: &str, number_type: Option<NumberType>) -> Option<&str> {
+    let bounds = value.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let is_interval = matches!(number_type, Some(NumberType::Interval(_)));
+    (is_interval && bounds.split(',').count() == 2 && !bounds.contains('[')).then_some(bounds)
 }
@@ -3,42 +3,44 @@ use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::Ordering;
-use std::thread::JoinHandle;
-use colored::Colorize;
 use tree_sitter_beanie::data::instructions::out_operation_args::{OUT_TO_FILE, OUT_FILE, OUT_ARGUMENTS};
 use crate::data::expression::BeanieExpression;
 use crate::data::operations;
-use crate::data::operations::Operation;
+use crate::data::operations::{BackgroundTask, Operation};
+use crate::data::span::Span;
+use crate::data::error::{BeanieError, BeanieResult};
 use crate::CLEANED_OUTPUT;
 use crate::data::context::BeanieRuntimeContext;
 
 #[derive(Debug, Clone)]
 pub struct OutOperation {
     expression: BeanieExpression,
-    arguments: HashMap<String, BeanieExpression>
+    arguments: HashMap<String, BeanieExpression>,
+    span: Option<Span>,
 }
 
 impl OutOperation {
-    pub fn new(expression: BeanieExpression) -> OutOperation {
+    pub fn new(expression: BeanieExpression, span: Option<Span>) -> OutOperation {
         OutOperation {
             expression,
             arguments: HashMap::new(),
+            span,
         }
     }
 }
 
 impl Operation for OutOperation {
-    fn execute(&self, context: &mut BeanieRuntimeContext, _: &Vec<String>, _: &mut Vec<JoinHandle<()>>) {
-        if self.arguments.contains_key(OUT_TO_FILE) && self.arguments[OUT_TO_FILE].evaluate::<f64>(context).unwrap_single().round() as i32 == true as i32 {
+    fn execute(&self, context: &mut BeanieRuntimeContext, _: &HashMap<String, String>, _: &mut Vec<BackgroundTask>) -> BeanieResult<()> {
+        if self.arguments.contains_key(OUT_TO_FILE) && self.arguments[OUT_TO_FILE].evaluate_single::<f64>(context)?.round() as i32 == true as i32 {
             let file_path = match self.arguments.contains_key(OUT_FILE) {
-                true => self.arguments[OUT_FILE].evaluate_to_string(context),
+                true => self.arguments[OUT_FILE].evaluate_to_string(context)?,
                 false => {
                     let path_buf = PathBuf::from(&context.beanie_file_path);
-                    let output_file_name = path_buf.file_stem().unwrap().to_string_lossy().to_string() + "_out.txt";
+                    let output_file_name = path_buf.file_stem().unwrap_or_default().to_string_lossy().to_string() + "_out.txt";
                     
-                    match path_buf.parent()
+                    match path_buf.parent().and_then(|path| path.to_str()).filter(|path| !path.is_empty())
                     {
-                        Some(path) => path.to_str().unwrap().to_string() + "/" + output_file_name.as_str(),
+                        Some(path) => path.to_string() + "/" + output_file_name.as_str(),
                         None => output_file_name,
                     }
                 }
@@ -58,15 +60,26 @@ impl Operation for OutOperation {
             } 
             
             let mut file = open_option
-                .open(file_path)
-                .unwrap_or_else(|err| panic!("{}. Error: {}", "Failed to open file".red(), err));
+                .open(&file_path)
+                .map_err(|err| BeanieError::Io(format!("Failed to open file {}. Error: {}", file_path, err)))?;
             
-            file.write_all((self.expression.evaluate_to_string(context) + "\n").as_bytes())
-                .unwrap_or_else(|err| panic!("{}. Error: {}", "Failed to write to output file".red(), err));
+            file.write_all((self.expression.evaluate_to_string(context)? + "\n").as_bytes())
+                .map_err(|err| BeanieError::Io(format!("Failed to write to output file {}. Error: {}", file_path, err)))?;
         }
+
+        Ok(())
+    }
+
+    fn add_argument(&mut self, name: String, expression: BeanieExpression) -> BeanieResult<()> {
+        operations::verify_argument("Out", &name, &expression, &OUT_ARGUMENTS, &mut self.arguments)
+    }
+
+    fn check(&self, context: &mut BeanieRuntimeContext, problems: &mut Vec<BeanieError>) {
+        self.expression.check(context, problems);
+        operations::check_arguments(context, &self.arguments, problems);
     }
 
-    fn add_argument(&mut self, name: String, expression: BeanieExpression) {
-        operations::verify_argument("Out", &name, &expression, &OUT_ARGUMENTS, &mut self.arguments);
+    fn span(&self) -> Option<&Span> {
+        self.span.as_ref()
     }
 }
@@ -1,30 +1,48 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
-use std::thread::JoinHandle;
+use std::sync::atomic::Ordering;
 use crate::data::context::BeanieRuntimeContext;
+use crate::data::error::{BeanieError, BeanieResult};
 use crate::data::expression::BeanieExpression;
-use crate::data::operations::Operation;
+use crate::data::operations::{BackgroundTask, Operation};
+use crate::data::span::Span;
 use crate::data::operations;
 use crate::utilities::logger;
+use crate::QUIET;
 
 #[derive(Debug, Clone)]
 pub struct PrintOperation {
-    expression: BeanieExpression
+    expression: BeanieExpression,
+    span: Option<Span>,
 }
 
 impl PrintOperation {
-    pub fn new(expression: BeanieExpression) -> PrintOperation {
+    pub fn new(expression: BeanieExpression, span: Option<Span>) -> PrintOperation {
         PrintOperation {
-            expression
+            expression,
+            span,
         }
     }
 }
 
 impl Operation for PrintOperation {
-    fn execute(&self, context: &mut BeanieRuntimeContext, _: &Vec<String>, _: &mut Vec<JoinHandle<()>>) {
-        logger::log_info(self.expression.evaluate_to_string(context).as_str());
+    fn execute(&self, context: &mut BeanieRuntimeContext, _: &HashMap<String, String>, _: &mut Vec<BackgroundTask>) -> BeanieResult<()> {
+        let result = self.expression.evaluate_to_pretty_string(context)?;
+        if !QUIET.load(Ordering::Relaxed) {
+            logger::log_info(result.as_str());
+        }
+        Ok(())
+    }
+
+    fn add_argument(&mut self, _: String, _: BeanieExpression) -> BeanieResult<()> {
+        operations::no_argument("Print")
+    }
+
+    fn check(&self, context: &mut BeanieRuntimeContext, problems: &mut Vec<BeanieError>) {
+        self.expression.check(context, problems);
     }
 
-    fn add_argument(&mut self, _: String, _: BeanieExpression) {
-        operations::no_argument("In");
+    fn span(&self) -> Option<&Span> {
+        self.span.as_ref()
     }
 }
@@ -1,46 +1,88 @@
+use std::collections::HashMap;
+use std::fs;
 use std::path::PathBuf;
-use std::thread::JoinHandle;
-use crate::{DEFAULT_DATA_TYPE, beanie_interpreter};
+use crate::beanie_interpreter;
 use crate::data::context::BeanieRuntimeContext;
 use crate::data::expression::BeanieExpression;
 use crate::data::function::Function;
-use crate::data::operations::Operation;
-use crate::utilities::{file_utils, logger};
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::operations::{BackgroundTask, Operation};
+use crate::data::span::Span;
+use crate::data::operations;
+use crate::utilities::file_utils;
 
 #[derive(Debug, Clone)]
 pub struct UseOperation {
     file_path: String,
+    span: Option<Span>,
 }
 
 impl UseOperation {
-    pub fn new(file_path: String) -> UseOperation {
+    pub fn new(file_path: String, span: Option<Span>) -> UseOperation {
         UseOperation {
-            file_path
+            file_path,
+            span,
         }
     }
+
+    /// Makes the output of `external_file` callable as a function named after the file. An output
+    /// ± gives several values makes a function giving all of them, which names assigned together
+    /// can take one each.
+    fn import(&self, mut external_file: BeanieRuntimeContext, context: &mut BeanieRuntimeContext) {
+        external_file.declare_inputs();
+        let name = PathBuf::from(&self.file_path).file_stem().unwrap().to_string_lossy().to_string();
+        
+        if context.has_function(&name) { return; }
+        context.insert_function(name.clone(), Function {
+            name,
+            parameters: external_file.inputs.clone(),
+            expression: external_file.output.clone().unwrap(),
+            external_context: Some(external_file),
+            span: self.span.clone(),
+        });
+    }
 }
 
 impl Operation for UseOperation {
-    fn execute(&self, context: &mut BeanieRuntimeContext, _: &Vec<String>, _: &mut Vec<JoinHandle<()>>) {
-        unsafe {
-            let external_file = beanie_interpreter::parse(self.file_path.clone(), file_utils::read_file(&self.file_path), DEFAULT_DATA_TYPE.clone()).0; 
-            if external_file.output.is_none() {
-                logger::log_error(format!("Using {} but it has no output", self.file_path).as_str());
-                unreachable!()
+    fn execute(&self, context: &mut BeanieRuntimeContext, _: &HashMap<String, String>, _: &mut Vec<BackgroundTask>) -> BeanieResult<()> {
+        // the used file is of the type of the file using it, unless it has a #type pragma of its own
+        let external_file = beanie_interpreter::parse(self.file_path.clone(), file_utils::read_file(&self.file_path)?, context.number_type)?.0;
+
+        if external_file.output.is_none() {
+            return Err(BeanieError::Resolution(format!("Using {} but it has no output", self.file_path)));
+        }
+
+        self.import(external_file, context);
+        Ok(())
+    }
+
+    fn add_argument(&mut self, _: String, _: BeanieExpression) -> BeanieResult<()> {
+        operations::no_argument("Use")
+    }
+
+    fn check(&self, context: &mut BeanieRuntimeContext, problems: &mut Vec<BeanieError>) {
+        let external_file = match fs::read_to_string(&self.file_path) {
+            Ok(external_file) => external_file,
+            Err(err) => {
+                problems.push(BeanieError::Io(format!("Can not use {} because it can not be read. Error: {}", self.file_path, err)));
+                return;
             }
+        };
 
-            let name = PathBuf::from(&self.file_path).file_stem().unwrap().to_string_lossy().to_string();
-            
-            if context.has_function(&name) { return; }
-            context.functions.insert(name, Function {
-                parameters: external_file.inputs.clone(),
-                expression: external_file.output.clone().unwrap(),
-                external_context: Some(external_file),
-            });
+        let external_file = beanie_interpreter::parse(self.file_path.clone(), external_file, context.number_type);
+
+        match external_file {
+            Ok((external_file, _)) if external_file.output.is_none() => problems.push(BeanieError::Resolution(format!("Using {} but it has no output", self.file_path))),
+            Ok((external_file, _)) => self.import(external_file, context),
+            Err(err) => problems.push(err),
         }
     }
 
-    fn add_argument(&mut self, _: String, _: BeanieExpression) {
-        logger::log_error("User instruction does not have any arguments")
+    fn span(&self) -> Option<&Span> {
+        self.span.as_ref()
+    }
+
+    fn prepares_output(&self) -> bool {
+        true
     }
 }
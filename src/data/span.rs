@@ -0,0 +1,327 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+/// A piece of a beanie file, such as an expression or an instruction, used to point errors at the
+/// source they came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub file_path: String,
+    /// Starts at 1.
+    pub line: usize,
+    /// Starts at 1, counted in characters.
+    pub column: usize,
+    /// Number of characters underlined, at least 1. A span going over several lines is underlined
+    /// up to the end of its first one.
+    pub length: usize,
+    pub source_line: String,
+    /// The source the span covers, over all of its lines.
+    text: String,
+}
+
+impl Span {
+    /// The span of the bytes `range` of `source`, the whole of the file at `file_path`.
+    pub fn new(file_path: &str, source: &str, range: Range<usize>) -> Span {
+        let line_start = source[..range.start].rfind('\n').map_or(0, |newline| newline + 1);
+        let line_end = source[range.start..].find('\n').map_or(source.len(), |newline| range.start + newline);
+        let text = &source[range.clone()];
+
+        Span {
+            file_path: file_path.to_string(),
+            line: source[..range.start].matches('\n').count() + 1,
+            column: source[line_start..range.start].chars().count() + 1,
+            length: text.split('\n').next().unwrap_or_default().trim_end().chars().count().max(1),
+            source_line: source[line_start..line_end].trim_end_matches('\r').to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    /// The span of the bytes `range` of `parsed`, the text this span covers as it was parsed. The
+    /// whole span when the text was reformatted before parsing, as its bytes are then not those
+    /// of the source.
+    pub fn within(&self, parsed: &str, range: &Range<usize>) -> Span {
+        if self.text != parsed || parsed.get(range.clone()).is_none() {
+            return self.clone();
+        }
+
+        let before = &self.text[..range.start];
+        let text = &self.text[range.clone()];
+        let length = text.split('\n').next().unwrap_or_default().trim_end().chars().count().max(1);
+
+        match before.rfind('\n') {
+            None => Span {
+                column: self.column + before.chars().count(),
+                length,
+                text: text.to_string(),
+                ..self.clone()
+            },
+            Some(newline) => Span {
+                line: self.line + before.matches('\n').count(),
+                column: before[newline + 1..].chars().count() + 1,
+                length,
+                source_line: self.text[newline + 1..].split('\n').next().unwrap_or_default().trim_end_matches('\r').to_string(),
+                text: text.to_string(),
+                ..self.clone()
+            },
+        }
+    }
+
+    /// The blank going from the start of the line to the span, keeping the tabs of the line so the
+    /// underline is below the span however wide a terminal shows them.
+    fn indent(&self) -> String {
+        self.source_line.chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect()
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let gutter = " ".repeat(self.line.to_string().len());
+
+        writeln!(f, "{} --> {}:{}:{}", gutter, self.file_path, self.line, self.column)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", self.line, self.source_line)?;
+        write!(f, "{} | {}{}", gutter, self.indent(), "^".repeat(self.length))
+    }
+}
+
+/// The words instructions start with.
+const INSTRUCTIONS: [&str; 5] = ["use", "in", "out", "graph", "print"];
+
+/// An instruction or definition of a beanie file, by the bytes of the source its parts were read
+/// from. A statement goes on over the next lines while it has brackets left open.
+#[derive(Debug)]
+struct Statement {
+    /// The word an instruction starts with, `None` for a definition.
+    keyword: Option<&'static str>,
+    /// The names a definition assigns, each with where it is written.
+    names: Vec<(String, Range<usize>)>,
+    /// What follows the keyword or the `=`, without the blanks around it.
+    body: Range<usize>,
+}
+
+impl Statement {
+    /// Reads the statement written in the bytes `range` of `source`, `None` when it is blank or
+    /// neither an instruction nor a definition, as the arguments written below an instruction.
+    fn read(source: &str, range: Range<usize>) -> Option<Statement> {
+        let text = &source[range.clone()];
+        let start = range.start + (text.len() - text.trim_start().len());
+        let text = text.trim();
+
+        let word = &text[..text.find(|c: char| !is_name_character(c)).unwrap_or(text.len())];
+        if let Some(keyword) = INSTRUCTIONS.iter().find(|keyword| **keyword == word) {
+            if text.len() == word.len() || text[word.len()..].starts_with(char::is_whitespace) {
+                return Some(Statement {
+                    keyword: Some(keyword),
+                    names: Vec::new(),
+                    body: trimmed(source, start + word.len()..start + text.len()),
+                });
+            }
+        }
+
+        let equals = definition_equals(text)?;
+        Some(Statement {
+            keyword: None,
+            names: names(&text[..equals]).into_iter()
+                .map(|(name, name_range)| (name, start + name_range.start..start + name_range.end))
+                .collect(),
+            body: trimmed(source, start + equals + 1..start + text.len()),
+        })
+    }
+}
+
+fn is_name_character(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// The bytes `range` of `source` without the blanks around them.
+fn trimmed(source: &str, range: Range<usize>) -> Range<usize> {
+    let text = &source[range.clone()];
+    let start = range.start + (text.len() - text.trim_start().len());
+    start..start + text.trim().len()
+}
+
+/// Where the `=` of a definition is in `text`, outside of brackets and not part of a comparison
+/// such as `==` or `<=`.
+fn definition_equals(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+
+    for (index, byte) in bytes.iter().enumerate() {
+        match byte {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b'=' if depth == 0 => {
+                let after_comparison = index > 0 && matches!(bytes[index - 1], b'=' | b'<' | b'>' | b'!');
+                let before_equals = bytes.get(index + 1) == Some(&b'=');
+                if !after_comparison && !before_equals {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The names assigned by the head of a definition, as in `x`, `a, b`, `(a, b)` or `f(x, y)`, the
+/// last assigning only the function.
+fn names(head: &str) -> Vec<(String, Range<usize>)> {
+    let mut names = Vec::new();
+    let mut characters = head.char_indices().peekable();
+
+    while let Some((start, c)) = characters.next() {
+        if !c.is_ascii_alphabetic() && c != '_' { continue; }
+
+        let mut end = start + c.len_utf8();
+        while let Some((index, c)) = characters.next_if(|(_, c)| is_name_character(*c)) {
+            end = index + c.len_utf8();
+        }
+
+        names.push((head[start..end].to_string(), start..end));
+        if head[end..].trim_start().starts_with('(') {
+            return names.split_off(names.len() - 1);
+        }
+    }
+    names
+}
+
+/// Finds where the instructions and definitions of a parsed file are in its source.
+/// tree-sitter-beanie only hands back the text of each of them, so the source is read once into
+/// statements, keeping the bytes each part was read from.
+pub struct SourceLocator<'a> {
+    file_path: String,
+    source: &'a str,
+    statements: Vec<Statement>,
+    next_instruction: usize,
+}
+
+impl<'a> SourceLocator<'a> {
+    pub fn new(file_path: &str, source: &'a str) -> SourceLocator<'a> {
+        let mut statements = Vec::new();
+        let mut start = 0;
+        let mut depth = 0;
+
+        for (index, c) in source.char_indices() {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                '\n' if depth <= 0 => {
+                    statements.extend(Statement::read(source, start..index));
+                    start = index + 1;
+                    depth = 0;
+                }
+                _ => {}
+            }
+        }
+        statements.extend(Statement::read(source, start..source.len()));
+
+        SourceLocator {
+            file_path: file_path.to_string(),
+            source,
+            statements,
+            next_instruction: 0,
+        }
+    }
+
+    fn span(&self, range: Range<usize>) -> Span {
+        Span::new(&self.file_path, self.source, range)
+    }
+
+    /// Locates what follows `keyword` in the next instruction starting with it, or the keyword
+    /// when nothing does. Instructions must be located in the order they appear in the file.
+    pub fn instruction(&mut self, keyword: &str) -> Option<Span> {
+        let index = (self.next_instruction..self.statements.len())
+            .find(|index| self.statements[*index].keyword == Some(keyword))?;
+        self.next_instruction = index + 1;
+
+        let body = self.statements[index].body.clone();
+        Some(match body.is_empty() {
+            true => self.span(body.start - keyword.len()..body.start),
+            false => self.span(body),
+        })
+    }
+
+    /// Locates the name of the last definition of the constant or function `name`.
+    pub fn definition(&self, name: &str) -> Option<Span> {
+        self.last_definition(name)
+            .and_then(|statement| statement.names.iter().find(|(assigned, _)| assigned == name))
+            .map(|(_, range)| self.span(range.clone()))
+    }
+
+    /// Locates the body of the last definition of the constant or function `name`.
+    pub fn definition_body(&self, name: &str) -> Option<Span> {
+        self.last_definition(name).map(|statement| self.span(statement.body.clone()))
+    }
+
+    /// Later definitions replace earlier ones.
+    fn last_definition(&self, name: &str) -> Option<&Statement> {
+        self.statements.iter().rev().find(|statement| statement.names.iter().any(|(assigned, _)| assigned == name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn definitions_assigned_together_are_found() {
+        let source = "r = 2\n(r1, r) = ±r\nx, y = 1, 2\n";
+        let locator = SourceLocator::new("roots.bn", source);
+
+        let r = locator.definition("r").unwrap();
+        assert_eq!((r.line, r.column, r.length), (2, 6, 1));
+
+        let y = locator.definition("y").unwrap();
+        assert_eq!((y.line, y.column), (3, 4));
+
+        let body = locator.definition_body("r1").unwrap();
+        assert_eq!((body.line, body.column, body.length), (2, 11, 2));
+    }
+
+    #[test]
+    fn underline_keeps_the_tabs_of_the_line() {
+        let source = "\tf(x) =\t1 / x\n";
+        let locator = SourceLocator::new("tabs.bn", source);
+
+        let span = locator.definition_body("f").unwrap();
+        let underline = span.to_string().lines().last().unwrap().to_string();
+        assert_eq!(underline, "  | \t      \t^^^^^");
+    }
+
+    #[test]
+    fn parts_of_a_body_are_found_by_where_they_were_parsed() {
+        let source = "x = 1\ny = 1 / x + 1 / x\n";
+        let locator = SourceLocator::new("twice.bn", source);
+
+        let body = locator.definition_body("y").unwrap();
+        let second = body.within("1 / x + 1 / x", &(8..13));
+        assert_eq!((second.line, second.column, second.length), (2, 13, 5));
+
+        // reformatted text is not that of the source, so the whole body is kept
+        assert_eq!(body.within("1/x + 1/x", &(6..9)), body);
+    }
+
+    #[test]
+    fn statements_go_on_while_brackets_are_open() {
+        let source = "in a\nf(x) = {\n    1 if x > 0;\n    1 / a otherwise\n}\nout f(a)\n";
+        let mut locator = SourceLocator::new("cases.bn", source);
+
+        let input = locator.instruction("in").unwrap();
+        assert_eq!((input.line, input.column, input.length), (1, 4, 1));
+        let output = locator.instruction("out").unwrap();
+        assert_eq!((output.line, output.column), (6, 5));
+
+        let name = locator.definition("f").unwrap();
+        assert_eq!((name.line, name.column, name.length), (2, 1, 1));
+
+        let body = locator.definition_body("f").unwrap();
+        assert_eq!((body.line, body.column, body.length), (2, 8, 1));
+
+        let parsed = &source[source.find('{').unwrap()..source.find("\nout").unwrap()];
+        let division = body.within(parsed, &(parsed.find("1 / a").unwrap()..parsed.find(" otherwise").unwrap()));
+        assert_eq!((division.line, division.column, division.length), (4, 5, 5));
+        assert_eq!(division.source_line, "    1 / a otherwise");
+    }
+}
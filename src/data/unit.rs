@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// Exponents of the SI base units metre, kilogram, second, ampere, kelvin, mole and candela.
+pub type Dimension = [i32; 7];
+
+const BASE_SYMBOLS: [&str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0];
+const CURRENT: Dimension = [0, 0, 0, 1, 0, 0, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: Dimension = [0, 0, 0, 0, 0, 1, 0];
+const LUMINOSITY: Dimension = [0, 0, 0, 0, 0, 0, 1];
+const AREA: Dimension = [2, 0, 0, 0, 0, 0, 0];
+const VOLUME: Dimension = [3, 0, 0, 0, 0, 0, 0];
+const FREQUENCY: Dimension = [0, 0, -1, 0, 0, 0, 0];
+const FORCE: Dimension = [1, 1, -2, 0, 0, 0, 0];
+const ENERGY: Dimension = [2, 1, -2, 0, 0, 0, 0];
+const POWER: Dimension = [2, 1, -3, 0, 0, 0, 0];
+const PRESSURE: Dimension = [-1, 1, -2, 0, 0, 0, 0];
+const CHARGE: Dimension = [0, 0, 1, 1, 0, 0, 0];
+const VOLTAGE: Dimension = [2, 1, -3, -1, 0, 0, 0];
+const RESISTANCE: Dimension = [2, 1, -3, -2, 0, 0, 0];
+
+lazy_static! {
+    /// Every unit that can be written in an expression, by symbol.
+    static ref UNITS: HashMap<&'static str, (Dimension, f64)> = hashmap! {
+        "m" => (LENGTH, 1.0),
+        "mm" => (LENGTH, 1e-3),
+        "cm" => (LENGTH, 1e-2),
+        "km" => (LENGTH, 1e3),
+        "um" => (LENGTH, 1e-6),
+        "inch" => (LENGTH, 0.0254),
+        "ft" => (LENGTH, 0.3048),
+        "yd" => (LENGTH, 0.9144),
+        "mi" => (LENGTH, 1609.344),
+        "kg" => (MASS, 1.0),
+        "g" => (MASS, 1e-3),
+        "mg" => (MASS, 1e-6),
+        "t" => (MASS, 1e3),
+        "lb" => (MASS, 0.45359237),
+        "s" => (TIME, 1.0),
+        "ms" => (TIME, 1e-3),
+        "min" => (TIME, 60.0),
+        "h" => (TIME, 3600.0),
+        "day" => (TIME, 86400.0),
+        "A" => (CURRENT, 1.0),
+        "mA" => (CURRENT, 1e-3),
+        "K" => (TEMPERATURE, 1.0),
+        "mol" => (AMOUNT, 1.0),
+        "cd" => (LUMINOSITY, 1.0),
+        "L" => (VOLUME, 1e-3),
+        "ha" => (AREA, 1e4),
+        "Hz" => (FREQUENCY, 1.0),
+        "N" => (FORCE, 1.0),
+        "kN" => (FORCE, 1e3),
+        "MN" => (FORCE, 1e6),
+        "lbf" => (FORCE, 4.4482216152605),
+        "J" => (ENERGY, 1.0),
+        "kJ" => (ENERGY, 1e3),
+        "MJ" => (ENERGY, 1e6),
+        "Wh" => (ENERGY, 3600.0),
+        "kWh" => (ENERGY, 3.6e6),
+        "W" => (POWER, 1.0),
+        "kW" => (POWER, 1e3),
+        "MW" => (POWER, 1e6),
+        "Pa" => (PRESSURE, 1.0),
+        "kPa" => (PRESSURE, 1e3),
+        "MPa" => (PRESSURE, 1e6),
+        "GPa" => (PRESSURE, 1e9),
+        "bar" => (PRESSURE, 1e5),
+        "psi" => (PRESSURE, 6894.757293168),
+        "C" => (CHARGE, 1.0),
+        "V" => (VOLTAGE, 1.0),
+        "ohm" => (RESISTANCE, 1.0),
+    };
+}
+
+/// Named SI units a result is shown in when it has their dimension, rather than in base units.
+const DERIVED: [(&str, Dimension); 7] = [
+    ("N", FORCE),
+    ("J", ENERGY),
+    ("W", POWER),
+    ("Pa", PRESSURE),
+    ("C", CHARGE),
+    ("V", VOLTAGE),
+    ("ohm", RESISTANCE),
+];
+
+/// The unit of a quantity, such as mm or m/s^2.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    pub dimension: Dimension,
+    /// One of the unit in SI base units, such as 0.001 for mm.
+    pub scale: f64,
+    pub symbol: String,
+}
+
+impl Unit {
+    /// The unit written as `symbol` in an expression, if there is one.
+    pub fn named(symbol: &str) -> Option<Unit> {
+        UNITS.get(symbol).map(|(dimension, scale)| Unit {
+            dimension: *dimension,
+            scale: *scale,
+            symbol: symbol.to_string(),
+        })
+    }
+
+    pub fn is_named(symbol: &str) -> bool {
+        UNITS.contains_key(symbol)
+    }
+
+    /// The SI unit of `dimension`, named if it has a name and made of base units otherwise, as in
+    /// kg·m^2/s.
+    pub fn si(dimension: Dimension) -> Unit {
+        if let Some((symbol, _)) = DERIVED.iter().find(|(_, derived)| *derived == dimension) {
+            return Unit { dimension, scale: 1.0, symbol: symbol.to_string() };
+        }
+
+        let part = |symbol: &str, exponent: i32| match exponent {
+            1 => symbol.to_string(),
+            exponent => format!("{}^{}", symbol, exponent),
+        };
+        let numerator: Vec<String> = BASE_SYMBOLS.iter().zip(dimension)
+            .filter(|(_, exponent)| *exponent > 0)
+            .map(|(symbol, exponent)| part(symbol, exponent))
+            .collect();
+        let denominator: Vec<String> = BASE_SYMBOLS.iter().zip(dimension)
+            .filter(|(_, exponent)| *exponent < 0)
+            .map(|(symbol, exponent)| part(symbol, -exponent))
+            .collect();
+
+        let numerator = if numerator.is_empty() { String::from("1") } else { numerator.join("·") };
+        let symbol = match denominator.len() {
+            0 => numerator,
+            1 => format!("{}/{}", numerator, denominator[0]),
+            _ => format!("{}/({})", numerator, denominator.join("·")),
+        };
+
+        Unit { dimension, scale: 1.0, symbol }
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.dimension.iter().all(|exponent| *exponent == 0)
+    }
+
+    /// The dimension of the product of two quantities in these units, or of their quotient when
+    /// `sign` is -1.
+    pub fn combined_dimension(&self, other: &Unit, sign: i32) -> Dimension {
+        let mut dimension = self.dimension;
+        for (exponent, other) in dimension.iter_mut().zip(other.dimension) {
+            *exponent += sign * other;
+        }
+        dimension
+    }
+
+    /// The unit of the product of two quantities in these units, or of their quotient when `sign`
+    /// is -1, written with both symbols as in kN·m.
+    pub fn combine(&self, other: &Unit, sign: i32) -> Unit {
+        let other_symbol = match other.symbol.contains(['·', '/']) {
+            true => format!("({})", other.symbol),
+            false => other.symbol.clone(),
+        };
+
+        Unit {
+            dimension: self.combined_dimension(other, sign),
+            scale: self.scale * other.scale.powi(sign),
+            symbol: format!("{}{}{}", self.symbol, if sign > 0 { "·" } else { "/" }, other_symbol),
+        }
+    }
+
+    /// The unit raised to `exponent`, as in mm^2 or (m/s)^2.
+    pub fn powi(&self, exponent: i32) -> Unit {
+        if exponent == 1 {
+            return self.clone();
+        }
+
+        let symbol = match self.symbol.chars().all(char::is_alphabetic) {
+            true => format!("{}^{}", self.symbol, exponent),
+            false => format!("({})^{}", self.symbol, exponent),
+        };
+        Unit { dimension: self.dimension.map(|own| own * exponent), scale: self.scale.powi(exponent), symbol }
+    }
+
+    /// The square root of the unit, if every exponent is even.
+    pub fn sqrt(&self) -> Option<Unit> {
+        if self.dimension.iter().any(|exponent| exponent % 2 != 0) {
+            return None;
+        }
+
+        let dimension = self.dimension.map(|exponent| exponent / 2);
+        match self.symbol.strip_suffix("^2").filter(|base| base.chars().all(char::is_alphabetic)) {
+            Some(base) => Some(Unit { dimension, scale: self.scale.sqrt(), symbol: base.to_string() }),
+            None => Some(Unit::si(dimension)),
+        }
+    }
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.symbol)
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt::{Display, Formatter};
+use mexprp::{Answer, Context};
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::matrix::Matrix;
+use crate::data::measured::Measured;
+use crate::data::number::{self, BeanieNum};
+use crate::data::unit::Unit;
+
+/// What an expression evaluates to: a number, which ± may have given several values, a quantity,
+/// which is such a number in a unit, a measured number with its uncertainty, a list or a matrix.
+#[derive(Debug, Clone)]
+pub enum Value<N: BeanieNum> {
+    Number(Answer<N>),
+    /// A number of the unit, as in 5 mm, never in a unit without dimension.
+    Quantity(Answer<N>, Unit),
+    /// A single number with a standard uncertainty, as in 9.81 +- 0.02.
+    Measured(Measured<N>),
+    List(Vec<Value<N>>),
+    Matrix(Matrix<N>),
+}
+
+impl<N: BeanieNum> Value<N> {
+    pub fn single(value: N) -> Value<N> {
+        Value::Number(Answer::Single(value))
+    }
+
+    /// The number the value is, failing for quantities, measured numbers, lists and matrices.
+    pub fn number(self) -> BeanieResult<Answer<N>> {
+        match self {
+            Value::Number(number) => Ok(number),
+            Value::Quantity(number, unit) => Err(BeanieError::Type(format!("Expected a number, got the quantity {} {}", number, unit))),
+            Value::Measured(measured) => Err(BeanieError::Type(format!("Expected a number, got the measured value {}", measured))),
+            Value::List(list) => Err(BeanieError::Type(format!("Expected a number, got the list {}", Value::List(list)))),
+            Value::Matrix(matrix) => Err(BeanieError::Type(format!("Expected a number, got the matrix {}", matrix))),
+        }
+    }
+
+    /// Applies `operation` to every number of the value. Measured numbers are left to the evaluator,
+    /// which knows how the operation changes their uncertainty.
+    pub fn map<F: Fn(&Answer<N>) -> BeanieResult<Answer<N>>>(&self, operation: &F) -> BeanieResult<Value<N>> {
+        match self {
+            Value::Number(number) => Ok(Value::Number(operation(number)?)),
+            Value::Quantity(number, unit) => Ok(Value::Quantity(operation(number)?, unit.clone())),
+            Value::Measured(measured) => Err(BeanieError::Type(format!("Expected a number, got the measured value {}", measured))),
+            Value::List(list) => Ok(Value::List(list.iter().map(|element| element.map(operation)).collect::<BeanieResult<_>>()?)),
+            Value::Matrix(matrix) => Ok(Value::Matrix(matrix.map(|element| matrix_element(operation(&Answer::Single(element.clone()))?))?)),
+        }
+    }
+
+    /// Applies `operation` to the numbers of `self` and `other`, element by element when they are
+    /// lists of the same length or matrices of the same shape, and with the number for every
+    /// element when only one of them is a number. Quantities and measured numbers are left to the
+    /// evaluator, which knows what the operation does to their units and uncertainties.
+    pub fn zip_with<F: Fn(&Answer<N>, &Answer<N>) -> BeanieResult<Answer<N>>>(&self, other: &Value<N>, operation: &F) -> BeanieResult<Value<N>> {
+        let on_elements = |a: &N, b: &N| matrix_element(operation(&Answer::Single(a.clone()), &Answer::Single(b.clone()))?);
+
+        match (self, other) {
+            (Value::Number(left), Value::Number(right)) => Ok(Value::Number(operation(left, right)?)),
+            (Value::Matrix(left), Value::Matrix(right)) => Ok(Value::Matrix(left.zip_with(right, on_elements)?)),
+            (Value::Matrix(left), Value::Number(right)) => Ok(Value::Matrix(left.map(|a| matrix_element(operation(&Answer::Single(a.clone()), right)?))?)),
+            (Value::Number(left), Value::Matrix(right)) => Ok(Value::Matrix(right.map(|b| matrix_element(operation(left, &Answer::Single(b.clone()))?))?)),
+            (Value::List(_), _) | (_, Value::List(_)) => Value::broadcast(&[self.clone(), other.clone()], &|values| values[0].zip_with(&values[1], operation)),
+            _ => Err(BeanieError::Type(format!("{} and {} can not be combined", self, other))),
+        }
+    }
+
+    /// The value with its numbers converted to `M`, as when a constant is used by an expression of
+    /// a type holding its own, or by a conversion such as `as_rational(x)`. `builtins` are those of
+    /// the expression converted to, whose precision rug numbers take.
+    pub fn convert<M: BeanieNum>(&self, builtins: &Context<M>) -> BeanieResult<Value<M>> {
+        let convert = |number: &N| number::convert(number, builtins)
+            .ok_or_else(|| BeanieError::Type(format!("{} is not a value of type {}", number, M::number_type(builtins))));
+        let convert_answer = |answer: &Answer<N>| match answer {
+            Answer::Single(number) => convert(number).map(Answer::Single),
+            Answer::Multiple(numbers) => numbers.iter().map(convert).collect::<BeanieResult<_>>().map(Answer::Multiple),
+        };
+
+        match self {
+            Value::Number(number) => Ok(Value::Number(convert_answer(number)?)),
+            Value::Quantity(number, unit) => Ok(Value::Quantity(convert_answer(number)?, unit.clone())),
+            Value::Measured(measured) => Ok(Value::Measured(Measured {
+                value: convert(&measured.value)?,
+                parts: measured.parts.iter().map(|(measurement, part)| Ok((*measurement, convert(part)?))).collect::<BeanieResult<_>>()?,
+            })),
+            Value::List(list) => Ok(Value::List(list.iter().map(|element| element.convert(builtins)).collect::<BeanieResult<_>>()?)),
+            Value::Matrix(matrix) => Ok(Value::Matrix(matrix.map(convert)?)),
+        }
+    }
+
+    /// The value as printed, matrices being laid out over several lines, and the values ± gave a
+    /// number one per line.
+    pub fn to_pretty_string(&self) -> String {
+        match self {
+            Value::Matrix(matrix) => matrix.pretty(),
+            Value::Number(Answer::Multiple(_)) | Value::Quantity(Answer::Multiple(_), _) => self.labelled_branches("\n"),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Every value ± gave the value, in the order names assigned together take them, as in
+    /// r1, r2 = ±c. Any other value is its only branch.
+    pub fn branches(&self) -> Vec<Value<N>> {
+        match self {
+            Value::Number(Answer::Multiple(values)) => values.iter().map(|value| Value::single(value.clone())).collect(),
+            Value::Quantity(Answer::Multiple(values), unit) => values.iter().map(|value| Value::Quantity(Answer::Single(value.clone()), unit.clone())).collect(),
+            Value::List(list) => list.clone(),
+            _ => vec![self.clone()],
+        }
+    }
+
+    /// The branches numbered from 1, as in #1: 2, #2: -2, with `separator` between them.
+    fn labelled_branches(&self, separator: &str) -> String {
+        let branches: Vec<String> = self.branches().iter().enumerate()
+            .map(|(index, branch)| format!("#{}: {}", index + 1, branch))
+            .collect();
+        branches.join(separator)
+    }
+
+    /// Calls `operation` with `arguments`, or once for each element when some of them are lists,
+    /// the other arguments being repeated for every element. The lists must be of the same length.
+    pub fn broadcast<F: Fn(&[Value<N>]) -> BeanieResult<Value<N>>>(arguments: &[Value<N>], operation: &F) -> BeanieResult<Value<N>> {
+        let lengths: Vec<usize> = arguments.iter()
+            .filter_map(|argument| match argument {
+                Value::List(list) => Some(list.len()),
+                Value::Number(_) | Value::Quantity(..) | Value::Measured(_) | Value::Matrix(_) => None,
+            })
+            .collect();
+
+        let length = match lengths.first() {
+            Some(length) => *length,
+            None => return operation(arguments),
+        };
+        if lengths.iter().any(|other| *other != length) {
+            let lengths: Vec<String> = lengths.iter().map(|length| length.to_string()).collect();
+            return Err(BeanieError::Type(format!("Lists of different lengths ({}) can not be combined element by element", lengths.join(", "))));
+        }
+
+        let elements = (0..length)
+            .map(|index| {
+                let element_arguments: Vec<Value<N>> = arguments.iter()
+                    .map(|argument| match argument {
+                        Value::List(list) => list[index].clone(),
+                        number => number.clone(),
+                    })
+                    .collect();
+                Value::broadcast(&element_arguments, operation)
+            })
+            .collect::<BeanieResult<_>>()?;
+
+        Ok(Value::List(elements))
+    }
+}
+
+impl<N: BeanieNum> Display for Value<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(Answer::Multiple(_)) | Value::Quantity(Answer::Multiple(_), _) => write!(f, "{}", self.labelled_branches(", ")),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Quantity(number, unit) => write!(f, "{} {}", number, unit),
+            Value::Measured(measured) => write!(f, "{}", measured),
+            Value::List(list) => {
+                // an element with several values is bracketed, so its values stay apart from the
+                // other elements
+                let elements: Vec<String> = list.iter()
+                    .map(|element| match element {
+                        Value::Number(Answer::Multiple(_)) | Value::Quantity(Answer::Multiple(_), _) => format!("({})", element),
+                        _ => element.to_string(),
+                    })
+                    .collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Value::Matrix(matrix) => write!(f, "{}", matrix),
+        }
+    }
+}
+
+/// The single value an element of a matrix must have.
+pub fn matrix_element<N: BeanieNum>(number: Answer<N>) -> BeanieResult<N> {
+    match number {
+        Answer::Single(element) => Ok(element),
+        Answer::Multiple(values) => Err(BeanieError::Type(format!("An element of a matrix must have a single value, got {} values", values.len()))),
+    }
+}
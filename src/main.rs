@@ -4,65 +4,215 @@
 mod data;
 mod beanie_interpreter;
 mod utilities;
+mod runners;
 
-use std::env;
-use std::sync::atomic::AtomicBool;
-use tree_sitter_beanie::data::expression::data_type::DataType;
+use std::fs;
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use clap::{Parser, Subcommand};
+use tree_sitter_beanie::data::instructions::graph_operation_args::{MIN_X, MIN_Y, MAX_X, MAX_Y, STEP_X, STEP_Y, TITLE, LABEL_X, LABEL_Y};
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::expression::BeanieExpression;
+use crate::data::number::NumberType;
 use crate::utilities::{file_utils, logger};
 
 lazy_static! {
     pub static ref CLEANED_OUTPUT: AtomicBool = AtomicBool::new(false);
+    pub static ref QUIET: AtomicBool = AtomicBool::new(false);
+    pub static ref NO_GRAPH: AtomicBool = AtomicBool::new(false);
+    pub static ref MAX_RECURSION_DEPTH: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_RECURSION_DEPTH);
+    pub static ref MEMOIZE: AtomicBool = AtomicBool::new(false);
 }
 
-static mut DEFAULT_DATA_TYPE: DataType = DataType::Decimal;
+const DEFAULT_MAX_RECURSION_DEPTH: usize = 1000;
+
+/// Stack reserved for each level of function calls on top of the base, so that going past the
+/// maximum recursion depth ends with an error instead of overflowing the stack.
+const BASE_STACK_SIZE: usize = 8 * 1024 * 1024;
+const STACK_SIZE_PER_CALL: usize = 64 * 1024;
+
+#[derive(Parser)]
+#[command(name = "beanie", version, about = "Runtime for the beanie math language")]
+struct Cli {
+    /// Data type used for expressions that do not declare one (e.g. decimal, complex, rational), float:<bits> or float:<digits>d for decimals of that precision, integer for exact integers, or interval (also interval:<bits>) for bounds rounded outward. A file opening with a line such as #type rational or #type float:256 uses that type instead, and the same line before a definition gives the type of that definition
+    #[arg(long = "default_data_type", global = true, value_parser = NumberType::from_str)]
+    default_data_type: Option<NumberType>,
+
+    /// Do not print the results of print instructions
+    #[arg(long, global = true)]
+    quiet: bool,
+
+    /// Do not render graph instructions
+    #[arg(long = "no_graph", global = true)]
+    no_graph: bool,
+
+    /// How deeply function calls may be nested, e.g. by a recursive function
+    #[arg(long = "max_recursion_depth", global = true, default_value_t = DEFAULT_MAX_RECURSION_DEPTH)]
+    max_recursion_depth: usize,
+
+    /// Remember the values of function calls with integer arguments, speeding up recursions such as fib(n - 1) + fib(n - 2)
+    #[arg(long, global = true)]
+    memoize: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a beanie file
+    Run {
+        /// Path to the beanie file
+        file: String,
+        /// Values for the file's inputs, as name=value or in declaration order
+        #[arg(allow_negative_numbers = true)]
+        parameters: Vec<String>,
+        /// Run the file once per row of this CSV or JSONL file, columns naming the inputs
+        #[arg(long)]
+        batch: Option<String>,
+        /// Run the file for every combination of input ranges, given as name=start..end[:step]
+        #[arg(long, conflicts_with = "batch", allow_negative_numbers = true)]
+        sweep: Vec<String>,
+        /// Graph the sweep output against the first swept input, one line per combination of the others
+        #[arg(long = "sweep_graph", requires = "sweep")]
+        sweep_graph: bool,
+        /// Where to write batch or sweep results, as CSV or JSONL depending on the extension (stdout if omitted)
+        #[arg(long = "table_output", alias = "batch_output")]
+        table_output: Option<String>,
+        /// Rerun whenever the file or any file it uses changes
+        #[arg(long, conflicts_with_all = ["batch", "sweep"])]
+        watch: bool,
+    },
+    /// Validate beanie files without running them, reporting every problem found and exiting with
+    /// the code of the first one
+    Check {
+        /// Paths to the beanie files
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Evaluate a single expression
+    Eval {
+        /// Expression to evaluate
+        expression: String,
+        /// Beanie files to use before evaluating
+        #[arg(long = "use")]
+        uses: Vec<String>,
+    },
+    /// Start an interactive session that keeps its definitions between lines
+    Repl,
+    /// Graph a function defined in a beanie file
+    Graph {
+        /// Path to the beanie file
+        file: String,
+        /// Name of the function to graph
+        function: String,
+        /// Values for the file's inputs, as name=value or in declaration order
+        #[arg(allow_negative_numbers = true)]
+        parameters: Vec<String>,
+        #[arg(long = "min_x", allow_negative_numbers = true)]
+        min_x: Option<f64>,
+        #[arg(long = "max_x", allow_negative_numbers = true)]
+        max_x: Option<f64>,
+        #[arg(long = "min_y", allow_negative_numbers = true)]
+        min_y: Option<f64>,
+        #[arg(long = "max_y", allow_negative_numbers = true)]
+        max_y: Option<f64>,
+        #[arg(long = "step_x")]
+        step_x: Option<f64>,
+        #[arg(long = "step_y")]
+        step_y: Option<f64>,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long = "label_x")]
+        label_x: Option<String>,
+        #[arg(long = "label_y")]
+        label_y: Option<String>,
+    },
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let cli = Cli::parse();
+
+    QUIET.store(cli.quiet, Ordering::Relaxed);
+    NO_GRAPH.store(cli.no_graph, Ordering::Relaxed);
+    MAX_RECURSION_DEPTH.store(cli.max_recursion_depth, Ordering::Relaxed);
+    MEMOIZE.store(cli.memoize, Ordering::Relaxed);
+
+    let default_data_type = cli.default_data_type.unwrap_or(NumberType::Decimal);
+    let command = cli.command;
+    let stack_size = BASE_STACK_SIZE.saturating_add(cli.max_recursion_depth.saturating_mul(STACK_SIZE_PER_CALL));
+
+    let result = thread::Builder::new()
+        .stack_size(stack_size)
+        .spawn(move || execute(command, default_data_type))
+        .map_err(|err| BeanieError::Recursion(format!("Failed to reserve the stack for a maximum recursion depth of {}: {}", cli.max_recursion_depth, err)))
+        .and_then(|evaluation| evaluation.join().unwrap_or_else(|_| process::exit(101)));
 
-    // no file to run
-    if args.len() <= 1 {
-        logger::log_error("No file provided");
+    if let Err(err) = result {
+        logger::log_error(err.to_string().as_str());
+        process::exit(err.exit_code());
     }
-    
-    let file_path = &args[1];
-    let parameters = match args.iter().position(|s| s.starts_with("--")) {
-        Some(index) => { 
-            let options = args[index..].to_vec();
-
-            for option in options {
-                let option = option[2..].to_string();
-
-                match option.chars().into_iter().position(|c| c == '=') {
-                    Some(index) => {
-                        let option_data = option[index+1..].to_string();
-                        let option = option[..index].to_string();
-                        
-                        match option.as_str() {
-                            "default_data_type" => unsafe {
-                                DEFAULT_DATA_TYPE = option_data.parse::<DataType>().unwrap();
-                            },
-                            _ => {
-                                logger::log_info(format!("Unknown option {}", option).as_str())
-                            }
-                        }
-                    }
-                    None => {
-                        todo!()
+}
+
+fn execute(command: Command, default_data_type: NumberType) -> BeanieResult<()> {
+    match command {
+        Command::Run { file, parameters, batch, sweep, sweep_graph, table_output, watch } => {
+            if watch {
+                runners::watch_runner::run(file, parameters, default_data_type)
+            } else if let Some(batch) = batch {
+                runners::batch_runner::run(file.clone(), file_utils::read_file(&file)?, parameters, default_data_type, &batch, table_output.as_deref())
+            } else if !sweep.is_empty() {
+                let sweeps = sweep.iter().map(|sweep| runners::sweep_runner::Sweep::parse(sweep)).collect::<BeanieResult<_>>()?;
+                runners::sweep_runner::run(file.clone(), file_utils::read_file(&file)?, parameters, default_data_type, sweeps, table_output.as_deref(), sweep_graph)
+            } else {
+                beanie_interpreter::run(file.clone(), file_utils::read_file(&file)?, parameters, default_data_type)
+            }
+        }
+        Command::Check { files } => {
+            let mut problem_count = 0;
+            let mut exit_code = None;
+
+            for file in files {
+                let problems = match fs::read_to_string(&file) {
+                    Ok(bn_file) => beanie_interpreter::check(file.clone(), bn_file, default_data_type),
+                    Err(err) => vec![BeanieError::Io(format!("Failed to read file. Error: {}", err))],
+                };
+
+                for problem in &problems {
+                    match problem.span() {
+                        Some(_) => logger::log_error(problem.to_string().as_str()),
+                        None => logger::log_error(format!("{}: {}", file, problem).as_str()),
                     }
                 }
+                problem_count += problems.len();
+                exit_code = exit_code.or_else(|| problems.first().map(BeanieError::exit_code));
             }
-            
-            args[2..index].to_vec()
-        },
-        None => args[2..].to_vec(),
-    };
-
-    unsafe {
-        beanie_interpreter::run(
-            file_path.clone(),
-            file_utils::read_file(file_path),
-            parameters,
-            DEFAULT_DATA_TYPE.clone(),
-        );
+
+            // exits as a run failing with the first problem found would
+            if let Some(exit_code) = exit_code {
+                logger::log_error(format!("Found {} problem(s)", problem_count).as_str());
+                process::exit(exit_code);
+            }
+            Ok(())
+        }
+        Command::Eval { expression, uses } => {
+            logger::log_info(beanie_interpreter::eval(expression, uses, default_data_type)?.as_str());
+            Ok(())
+        }
+        Command::Repl => runners::repl_runner::run(default_data_type),
+        Command::Graph { file, function, parameters, min_x, max_x, min_y, max_y, step_x, step_y, title, label_x, label_y } => {
+            let mut arguments = Vec::new();
+
+            for (name, value) in [(MIN_X, min_x), (MAX_X, max_x), (MIN_Y, min_y), (MAX_Y, max_y), (STEP_X, step_x), (STEP_Y, step_y)] {
+                if let Some(value) = value { arguments.push((name.to_string(), BeanieExpression::SimpleF64(value))); }
+            }
+            for (name, value) in [(TITLE, title), (LABEL_X, label_x), (LABEL_Y, label_y)] {
+                if let Some(value) = value { arguments.push((name.to_string(), BeanieExpression::String(value))); }
+            }
+
+            beanie_interpreter::graph(file.clone(), file_utils::read_file(&file)?, parameters, default_data_type, function, arguments)
+        }
     }
 }
@@ -0,0 +1,4 @@
+pub mod repl_runner;
+pub mod batch_runner;
+pub mod sweep_runner;
+pub mod watch_runner;
@@ -0,0 +1,294 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use serde_json::{Map, Value};
+use crate::beanie_interpreter;
+use crate::data::context::BeanieRuntimeContext;
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::number::NumberType;
+use crate::data::operations::Operation;
+use crate::utilities::file_utils;
+
+pub const OUT_COLUMN: &str = "out";
+pub const ERROR_COLUMN: &str = "error";
+
+/// The input values of each row read from a batch file by column name, or why the row is malformed.
+type BatchRows = Vec<Result<Vec<(String, String)>, String>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Csv,
+    Jsonl,
+}
+
+impl TableFormat {
+    pub fn from_path(path: &str) -> TableFormat {
+        match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("jsonl") | Some("ndjson") => TableFormat::Jsonl,
+            _ => TableFormat::Csv,
+        }
+    }
+}
+
+/// The inputs a file was run with and what came out, one row per run.
+#[derive(Debug, Clone)]
+pub struct ResultTable {
+    pub input_columns: Vec<String>,
+    pub rows: Vec<ResultRow>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResultRow {
+    pub inputs: Vec<String>,
+    pub output: Result<String, String>,
+}
+
+/// Runs `bn_file` once for every row of the CSV or JSONL file at `batch_path`, matching columns to
+/// the file's `in` declarations. `parameters` are bound on every row alongside the row's values.
+/// Only the output is evaluated for each row, so graph, print and out instructions of the file are
+/// left out rather than run once per row and mixed into the table.
+pub fn run(bn_file_path: String, bn_file: String, parameters: Vec<String>, default_data_type: NumberType, batch_path: &str, output_path: Option<&str>) -> BeanieResult<()> {
+    let parsed = beanie_interpreter::parse(bn_file_path.clone(), bn_file, default_data_type)?;
+
+    if parsed.0.output.is_none() {
+        return Err(BeanieError::Resolution(format!("Can not run {} in batch mode because it has no output", bn_file_path)));
+    }
+
+    let rows = match TableFormat::from_path(batch_path) {
+        TableFormat::Csv => read_csv(batch_path, &parsed.0.inputs)?,
+        TableFormat::Jsonl => read_jsonl(batch_path, &parsed.0.inputs)?,
+    };
+
+    let mut table = ResultTable {
+        input_columns: parsed.0.inputs.clone(),
+        rows: Vec::new(),
+    };
+
+    for row in rows {
+        let mut row_parameters = parameters.clone();
+        let result = row.and_then(|values| {
+            row_parameters.extend(values.iter().map(|(name, value)| format!("{}={}", name, value)));
+            evaluate(&parsed, &row_parameters).map_err(|err| err.to_string())
+        });
+
+        table.rows.push(ResultRow {
+            inputs: input_values(&parsed.0.inputs, &row_parameters, &result),
+            output: result.map(|(_, output)| output),
+        });
+    }
+
+    write_table(&table, output_path)
+}
+
+/// Binds the parameters and uses of the parsed file and evaluates its output, without running its
+/// other instructions.
+pub fn evaluate(parsed: &(BeanieRuntimeContext, Vec<Box<dyn Operation>>), parameters: &[String]) -> BeanieResult<(BeanieRuntimeContext, String)> {
+    let context = beanie_interpreter::prepare_parsed(parsed, parameters)?;
+    let output = match &context.output {
+        Some(output) => output.evaluate_to_string(&context)?,
+        None => return Err(BeanieError::Resolution(format!("{} has no output", context.beanie_file_path))),
+    };
+
+    Ok((context, output))
+}
+
+fn input_values(inputs: &[String], parameters: &[String], result: &Result<(BeanieRuntimeContext, String), String>) -> Vec<String> {
+    match result {
+        Ok((context, _)) => inputs.iter()
+            .map(|input| context.get_constant(input).and_then(|constant| constant.0.get_math()).unwrap_or_default())
+            .collect(),
+        // the inputs could not be bound, so fall back to whatever was given by name, the row's own
+        // values coming after the command line ones
+        Err(_) => inputs.iter()
+            .map(|input| parameters.iter()
+                .rev()
+                .find_map(|parameter| parameter.strip_prefix(format!("{}=", input).as_str()))
+                .unwrap_or_default()
+                .to_string())
+            .collect(),
+    }
+}
+
+fn read_csv(path: &str, inputs: &[String]) -> BeanieResult<BatchRows> {
+    let mut reader = csv::Reader::from_path(path)
+        .map_err(|err| BeanieError::Io(format!("Failed to read batch file {}. Error: {}", path, err)))?;
+
+    let headers: Vec<String> = reader.headers()
+        .map_err(|err| BeanieError::Io(format!("Failed to read the header of batch file {}. Error: {}", path, err)))?
+        .iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+
+    for header in &headers {
+        if !inputs.contains(header) {
+            return Err(BeanieError::Input(format!("Column {} of {} does not match any input. Expected inputs: {}", header, path, inputs.join(", "))));
+        }
+    }
+
+    Ok(reader.records()
+        .map(|record| match record {
+            Ok(record) => Ok(headers.iter().cloned().zip(record.iter().map(|value| value.trim().to_string())).collect()),
+            Err(err) => Err(format!("Malformed row. Error: {}", err)),
+        })
+        .collect())
+}
+
+fn read_jsonl(path: &str, inputs: &[String]) -> BeanieResult<BatchRows> {
+    let mut rows = Vec::new();
+
+    for (index, line) in file_utils::read_file(path)?.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let object = match serde_json::from_str::<Map<String, Value>>(line) {
+            Ok(object) => object,
+            Err(err) => {
+                rows.push(Err(format!("Malformed row. Error: {}", err)));
+                continue;
+            }
+        };
+
+        if let Some(key) = object.keys().find(|key| !inputs.contains(&key.trim().to_string())) {
+            return Err(BeanieError::Input(format!("Key {} on line {} of {} does not match any input. Expected inputs: {}", key, index + 1, path, inputs.join(", "))));
+        }
+
+        rows.push(Ok(object.into_iter()
+            .map(|(name, value)| match value {
+                Value::String(value) => (name.trim().to_string(), value.trim().to_string()),
+                value => (name.trim().to_string(), value.to_string()),
+            })
+            .collect()));
+    }
+
+    Ok(rows)
+}
+
+/// Writes `table` to `output_path`, picking CSV or JSONL from its extension, or as CSV to stdout
+/// when no path is given.
+pub fn write_table(table: &ResultTable, output_path: Option<&str>) -> BeanieResult<()> {
+    let (writer, format): (Box<dyn Write>, TableFormat) = match output_path {
+        Some(path) => {
+            if let Some(parent) = Path::new(path).parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                fs::create_dir_all(parent).map_err(|err| BeanieError::Io(format!("Failed to create directory {}. Error: {}", parent.display(), err)))?;
+            }
+            let file = File::create(path).map_err(|err| BeanieError::Io(format!("Failed to open file {}. Error: {}", path, err)))?;
+            (Box::new(file), TableFormat::from_path(path))
+        }
+        None => (Box::new(io::stdout()), TableFormat::Csv),
+    };
+
+    let result = match format {
+        TableFormat::Csv => write_csv(table, writer),
+        TableFormat::Jsonl => write_jsonl(table, writer),
+    };
+
+    result.map_err(|err| BeanieError::Io(format!("Failed to write results. Error: {}", err)))
+}
+
+fn write_csv(table: &ResultTable, writer: Box<dyn Write>) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+
+    let mut header = table.input_columns.clone();
+    header.push(OUT_COLUMN.to_string());
+    header.push(ERROR_COLUMN.to_string());
+    writer.write_record(&header)?;
+
+    for row in &table.rows {
+        let mut record = row.inputs.clone();
+        match &row.output {
+            Ok(output) => record.extend([output.clone(), String::new()]),
+            Err(error) => record.extend([String::new(), error.clone()]),
+        }
+        writer.write_record(&record)?;
+    }
+
+    writer.flush()
+}
+
+fn write_jsonl(table: &ResultTable, mut writer: Box<dyn Write>) -> io::Result<()> {
+    for row in &table.rows {
+        let mut object = Map::new();
+
+        for (name, value) in table.input_columns.iter().zip(row.inputs.iter()) {
+            object.insert(name.clone(), to_json_value(value));
+        }
+        match &row.output {
+            Ok(output) => {
+                object.insert(OUT_COLUMN.to_string(), to_json_value(output));
+                object.insert(ERROR_COLUMN.to_string(), Value::Null);
+            }
+            Err(error) => {
+                object.insert(OUT_COLUMN.to_string(), Value::Null);
+                object.insert(ERROR_COLUMN.to_string(), Value::String(error.clone()));
+            }
+        }
+
+        writeln!(writer, "{}", Value::Object(object))?;
+    }
+
+    writer.flush()
+}
+
+fn to_json_value(value: &str) -> Value {
+    match value.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+        Some(number) => Value::Number(number),
+        None => Value::String(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs() -> Vec<String> {
+        vec![String::from("a"), String::from("b")]
+    }
+
+    /// Writes `contents` to a file of the temporary directory named `name`, returning its path.
+    fn batch_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("beanie_{}_{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn row(values: &[(&str, &str)]) -> Result<Vec<(String, String)>, String> {
+        Ok(values.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect())
+    }
+
+    #[test]
+    fn csv_rows_are_read_by_column_and_trimmed() {
+        let path = batch_file("rows.csv", "a, b\n1, 2\n 3 ,4\n5\n");
+        let rows = read_csv(&path, &inputs()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(rows[..2], [row(&[("a", "1"), ("b", "2")]), row(&[("a", "3"), ("b", "4")])]);
+        // a row with fewer values than there are columns is reported on its own
+        assert!(rows[2].is_err());
+    }
+
+    #[test]
+    fn jsonl_rows_are_read_by_key_whatever_the_type_of_the_value() {
+        let path = batch_file("rows.jsonl", "{\"a\": 1, \"b\": \"2/3\"}\n\n{\"a\": 1.5}\nnot json\n");
+        let rows = read_jsonl(&path, &inputs()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[..2], [row(&[("a", "1"), ("b", "2/3")]), row(&[("a", "1.5")])]);
+        assert!(rows[2].is_err());
+    }
+
+    #[test]
+    fn columns_that_are_not_inputs_are_rejected() {
+        let csv = batch_file("unknown.csv", "a,c\n1,2\n");
+        let jsonl = batch_file("unknown.jsonl", "{\"a\": 1, \"c\": 2}\n");
+        let (csv_rows, jsonl_rows) = (read_csv(&csv, &inputs()), read_jsonl(&jsonl, &inputs()));
+        fs::remove_file(&csv).unwrap();
+        fs::remove_file(&jsonl).unwrap();
+
+        assert!(matches!(csv_rows, Err(BeanieError::Input(_))));
+        assert!(matches!(jsonl_rows, Err(BeanieError::Input(_))));
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+use crate::beanie_interpreter::{self, TypePragmas};
+use crate::data::context::BeanieRuntimeContext;
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::expression::BeanieExpression;
+use crate::data::number::NumberType;
+use crate::data::operations;
+use crate::data::operations::Operation;
+use crate::utilities::{file_utils, logger};
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+const REPL_FILE_PATH: &str = "<repl>";
+const HISTORY_FILE_NAME: &str = ".beanie_history";
+
+const HELP: &str = "\
+Enter constants (x = 2), functions (f(x) = x^2), instructions (use file.bn, print x) or bare expressions.
+End a line with \\ or leave a bracket open to continue on the next line.
+
+:type <name or expression>  show the data type of a constant, function or expression
+:vars                       list the constants and functions currently defined
+:load <file.bn>             load the constants, functions and instructions of a file
+:reset                      forget everything defined so far
+:help                       show this message
+:quit                       leave the repl";
+
+pub fn run(default_data_type: NumberType) -> BeanieResult<()> {
+    let mut editor = DefaultEditor::new()
+        .map_err(|err| BeanieError::Io(format!("Failed to start the repl. Error: {}", err)))?;
+
+    let history_path = history_path();
+    if let Some(path) = &history_path {
+        // there is no history yet on the first run
+        let _ = editor.load_history(path);
+    }
+
+    let mut context = BeanieRuntimeContext::new(String::from(REPL_FILE_PATH));
+    context.number_type = default_data_type;
+    logger::log_info("Type :help for a list of commands");
+
+    while let Some(input) = read_input(&mut editor) {
+        let input = input.trim();
+        if input.is_empty() { continue; }
+
+        let _ = editor.add_history_entry(input);
+        if input == ":quit" || input == ":q" { break; }
+
+        let result = match input.strip_prefix(':') {
+            Some(command) => execute_command(command, &mut context, &default_data_type),
+            None => execute_line(input, &mut context, &default_data_type),
+        };
+
+        if let Err(err) = result {
+            logger::log_error(err.to_string().as_str());
+        }
+    }
+
+    if let Some(path) = &history_path {
+        if let Err(err) = editor.save_history(path) {
+            logger::log_error(format!("Failed to save repl history. Error: {}", err).as_str());
+        }
+    }
+
+    Ok(())
+}
+
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE_NAME))
+}
+
+/// Reads one complete input, which spans several lines when a line ends with `\` or leaves a
+/// bracket open. Returns `None` once the input stream ends.
+fn read_input(editor: &mut DefaultEditor) -> Option<String> {
+    let mut input = String::new();
+    let mut prompt = PROMPT;
+
+    loop {
+        match editor.readline(prompt) {
+            Ok(line) => {
+                let continued = line.ends_with('\\');
+                input += line.trim_end_matches('\\');
+
+                if !continued && is_balanced(&input) {
+                    return Some(input);
+                }
+
+                input.push(' ');
+                prompt = CONTINUATION_PROMPT;
+            }
+            // ctrl-c throws away what has been typed so far
+            Err(ReadlineError::Interrupted) => return Some(String::new()),
+            Err(ReadlineError::Eof) => return None,
+            Err(err) => {
+                logger::log_error(format!("Failed to read input. Error: {}", err).as_str());
+                return None;
+            }
+        }
+    }
+}
+
+fn is_balanced(input: &str) -> bool {
+    let mut depth = 0;
+
+    for c in input.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth <= 0
+}
+
+fn execute_command(command: &str, context: &mut BeanieRuntimeContext, default_data_type: &NumberType) -> BeanieResult<()> {
+    let (name, argument) = match command.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (command, ""),
+    };
+
+    match name {
+        "help" | "h" => logger::log_info(HELP),
+        "vars" | "v" => list_definitions(context)?,
+        "type" | "t" => show_type(argument, context, default_data_type)?,
+        "load" | "l" => load(argument, context, default_data_type)?,
+        "reset" => {
+            *context = BeanieRuntimeContext::new(String::from(REPL_FILE_PATH));
+            context.number_type = *default_data_type;
+            logger::log_info("Cleared all constants and functions");
+        }
+        _ => return Err(BeanieError::Input(format!("Unknown command :{}. Type :help for a list of commands", name))),
+    }
+
+    Ok(())
+}
+
+fn execute_line(input: &str, context: &mut BeanieRuntimeContext, default_data_type: &NumberType) -> BeanieResult<()> {
+    // anything the grammar does not turn into a definition or instruction is a bare expression
+    let parsed = tree_sitter_beanie::parse(input, default_data_type.data_type(), None)
+        .ok()
+        .filter(|parsed| !parsed.instructions.is_empty() || !parsed.constants.is_empty() || !parsed.functions.is_empty());
+
+    match parsed {
+        Some(parsed) => {
+            let (line_context, instructions) = beanie_interpreter::contextualize(String::from(REPL_FILE_PATH), input, parsed, &TypePragmas::new(*default_data_type))?;
+
+            if !line_context.inputs.is_empty() {
+                return Err(BeanieError::Input(format!("Inputs can not be declared in the repl, define {} as a constant instead", line_context.inputs.join(", "))));
+            }
+
+            let output = line_context.output.clone();
+            merge(context, line_context);
+            execute(instructions, context)?;
+
+            if let Some(output) = output {
+                logger::log_info(output.evaluate_to_pretty_string(context)?.as_str());
+            }
+        }
+        None => {
+            let expression = BeanieExpression::math(input.to_string(), Some(*default_data_type), &[]);
+            logger::log_info(expression.evaluate_to_pretty_string(context)?.as_str());
+        }
+    }
+
+    Ok(())
+}
+
+fn load(file_path: &str, context: &mut BeanieRuntimeContext, default_data_type: &NumberType) -> BeanieResult<()> {
+    if file_path.is_empty() {
+        return Err(BeanieError::Input(String::from("Usage: :load <file.bn>")));
+    }
+
+    let (loaded_context, instructions) = beanie_interpreter::parse(file_path.to_string(), file_utils::read_file(file_path)?, *default_data_type)?;
+    let inputs = loaded_context.inputs.clone();
+    merge(context, loaded_context);
+
+    if inputs.is_empty() {
+        execute(instructions, context)?;
+    } else {
+        logger::log_info(format!("Loaded the definitions of {} without running its instructions, it needs the inputs {}", file_path, inputs.join(", ")).as_str());
+    }
+
+    Ok(())
+}
+
+fn show_type(argument: &str, context: &BeanieRuntimeContext, default_data_type: &NumberType) -> BeanieResult<()> {
+    if argument.is_empty() {
+        return Err(BeanieError::Input(String::from("Usage: :type <name or expression>")));
+    }
+
+    if let Some(function) = context.get_function(argument) {
+        match &function.external_context {
+            Some(external_context) => logger::log_info(format!("{}({}): function from {}", argument, function.parameters.join(", "), external_context.beanie_file_path).as_str()),
+            None => logger::log_info(format!("{}({}): function", argument, function.parameters.join(", ")).as_str()),
+        }
+    } else if let Some((expression, _)) = context.get_constant(argument) {
+        match expression.number_type(context) {
+            Some(number_type) => logger::log_info(format!("{}: {} constant", argument, number_type).as_str()),
+            None => logger::log_info(format!("{}: {} constant", argument, expression.get_type()).as_str()),
+        }
+    } else {
+        // evaluating makes sure the expression is actually valid before reporting a type for it
+        let expression = BeanieExpression::math(argument.to_string(), Some(*default_data_type), &[]);
+        expression.evaluate_to_string(context)?;
+        let number_type = expression.number_type(context).unwrap_or(*default_data_type);
+        logger::log_info(number_type.to_string().as_str());
+    }
+
+    Ok(())
+}
+
+fn list_definitions(context: &BeanieRuntimeContext) -> BeanieResult<()> {
+    let mut constants: Vec<String> = context.constants.iter()
+        .map(|(names, expression)| Ok(format!("{} = {}", names.join(", "), match expression.get_math() {
+            Some(math) => math,
+            None => expression.evaluate_to_string(context)?,
+        })))
+        .collect::<BeanieResult<_>>()?;
+    constants.sort();
+
+    let mut functions: Vec<String> = context.functions.iter()
+        .map(|(name, function)| match &function.external_context {
+            Some(external_context) => format!("{}({}) from {}", name, function.parameters.join(", "), external_context.beanie_file_path),
+            None => format!("{}({}) = {}", name, function.parameters.join(", "), function.expression.get_math().unwrap_or_default()),
+        })
+        .collect();
+    functions.sort();
+
+    if constants.is_empty() && functions.is_empty() {
+        logger::log_info("Nothing is defined yet");
+        return Ok(());
+    }
+
+    for line in constants.iter().chain(functions.iter()) {
+        logger::log_info(line.as_str());
+    }
+
+    Ok(())
+}
+
+/// Folds the definitions of `other` into `context`, later definitions replacing earlier ones.
+fn merge(context: &mut BeanieRuntimeContext, other: BeanieRuntimeContext) {
+    for (names, expression) in other.constants {
+        for name in &names {
+            context.remove_constant(name);
+        }
+        context.insert_constant(names, expression);
+    }
+
+    for (name, function) in other.functions {
+        context.insert_function(name, function);
+    }
+
+    if other.output.is_some() {
+        context.output = other.output;
+    }
+}
+
+fn execute(instructions: Vec<Box<dyn Operation>>, context: &mut BeanieRuntimeContext) -> BeanieResult<()> {
+    let mut threads_to_wait = Vec::new();
+
+    for instruction in instructions {
+        if let Err(err) = operations::execute(instruction.as_ref(), context, &HashMap::new(), &mut threads_to_wait) {
+            let _ = operations::join_all(threads_to_wait);
+            return Err(err);
+        }
+    }
+
+    operations::join_all(threads_to_wait)
+}
@@ -0,0 +1,228 @@
+use regex::Regex;
+use tree_sitter_beanie::data::instructions::graph_operation_args::{MIN_X, MIN_Y, MAX_X, MAX_Y, STEP_X, STEP_Y, LABEL_X, LABEL_Y};
+use crate::beanie_interpreter;
+use crate::data::context::BeanieRuntimeContext;
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::expression::BeanieExpression;
+use crate::data::number::NumberType;
+use crate::data::operations::graph_operation::{GraphOperation, Series};
+use crate::data::operations;
+use crate::data::operations::Operation;
+use crate::runners::batch_runner;
+use crate::runners::batch_runner::{ResultRow, ResultTable, OUT_COLUMN};
+
+/// One input swept over `start..=end` in increments of `step`.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub input_name: String,
+    pub values: Vec<f64>,
+}
+
+impl Sweep {
+    /// Parses `name=start..end` or `name=start..end:step`, the step defaulting to 1.
+    pub fn parse(sweep: &str) -> BeanieResult<Sweep> {
+        let pattern = Regex::new(r"^([a-zA-Z][a-zA-Z0-9_]*)=(.+?)\.\.([^:]+)(?::(.+))?$").unwrap();
+
+        let captures = pattern.captures(sweep)
+            .ok_or_else(|| BeanieError::Input(format!("Invalid sweep {}. Expected name=start..end or name=start..end:step", sweep)))?;
+
+        let number = |text: &str| text.trim().parse::<f64>()
+            .map_err(|_| BeanieError::Input(format!("Invalid number {} in sweep {}", text, sweep)));
+
+        let start = number(&captures[2])?;
+        let end = number(&captures[3])?;
+        let step = match captures.get(4) {
+            Some(step) => number(step.as_str())?,
+            None => 1f64,
+        };
+
+        // values are rounded to the decimals the sweep was written with, so 0..1:0.1 gives 0.3 and
+        // not 0.30000000000000004
+        let decimals = [Some(&captures[2]), captures.get(4).map(|step| step.as_str())].iter()
+            .flatten()
+            .map(|text| decimals(text))
+            .max()
+            .unwrap_or(0);
+        let scale = 10f64.powi(decimals as i32);
+
+        if step <= 0f64 || end < start {
+            return Err(BeanieError::Input(format!("Sweep {} never reaches its end, the step must be positive and the start no larger than the end", sweep)));
+        }
+
+        // counting steps instead of accumulating them keeps the error from adding up
+        let count = ((end - start) / step + 1e-9).floor() as usize + 1;
+
+        Ok(Sweep {
+            input_name: captures[1].to_string(),
+            values: (0..count).map(|index| ((start + index as f64 * step) * scale).round() / scale).collect(),
+        })
+    }
+}
+
+/// The number of decimals of the number written as `text`, counting those an exponent adds, so
+/// 1e-3 has 3 and 2.5e1 none.
+fn decimals(text: &str) -> u32 {
+    let text = text.trim();
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().unwrap_or(0)),
+        None => (text, 0),
+    };
+    let fraction = mantissa.split_once('.').map(|(_, fraction)| fraction.len() as i32).unwrap_or(0);
+
+    (fraction - exponent).max(0) as u32
+}
+
+/// Runs `bn_file` for every combination of the swept inputs, `parameters` supplying the remaining
+/// inputs, and writes the results as one table. With `graph` set, the output is also plotted against
+/// the first swept input, one series per value of the second.
+pub fn run(bn_file_path: String, bn_file: String, parameters: Vec<String>, default_data_type: NumberType, sweeps: Vec<Sweep>, output_path: Option<&str>, graph: bool) -> BeanieResult<()> {
+    let parsed = beanie_interpreter::parse(bn_file_path.clone(), bn_file, default_data_type)?;
+
+    if parsed.0.output.is_none() {
+        return Err(BeanieError::Resolution(format!("Can not sweep {} because it has no output", bn_file_path)));
+    }
+
+    for (index, sweep) in sweeps.iter().enumerate() {
+        if !parsed.0.inputs.contains(&sweep.input_name) {
+            return Err(BeanieError::Input(format!("Can not sweep {} because it is not an input. Expected inputs: {}", sweep.input_name, parsed.0.inputs.join(", "))));
+        }
+        if sweeps[..index].iter().any(|other| other.input_name == sweep.input_name) {
+            return Err(BeanieError::Input(format!("Input {} is swept more than once", sweep.input_name)));
+        }
+    }
+
+    let mut table = ResultTable {
+        input_columns: sweeps.iter().map(|sweep| sweep.input_name.clone()).collect(),
+        rows: Vec::new(),
+    };
+
+    for combination in cartesian_product(&sweeps) {
+        let mut row_parameters = parameters.clone();
+        row_parameters.extend(sweeps.iter().zip(combination.iter()).map(|(sweep, value)| format!("{}={}", sweep.input_name, value)));
+
+        table.rows.push(ResultRow {
+            inputs: combination.iter().map(|value| value.to_string()).collect(),
+            output: batch_runner::evaluate(&parsed, &row_parameters).map(|(_, output)| output).map_err(|err| err.to_string()),
+        });
+    }
+
+    batch_runner::write_table(&table, output_path)?;
+
+    if graph {
+        plot(&table, &sweeps, &parsed.0)?;
+    }
+
+    Ok(())
+}
+
+/// Every combination of the swept values, the first sweep varying slowest.
+fn cartesian_product(sweeps: &[Sweep]) -> Vec<Vec<f64>> {
+    let mut combinations = vec![Vec::new()];
+
+    for sweep in sweeps {
+        combinations = combinations.into_iter()
+            .flat_map(|combination| sweep.values.iter().map(move |value| {
+                let mut combination = combination.clone();
+                combination.push(*value);
+                combination
+            }))
+            .collect();
+    }
+
+    combinations
+}
+
+fn plot(table: &ResultTable, sweeps: &[Sweep], context: &BeanieRuntimeContext) -> BeanieResult<()> {
+    // rows whose output failed or is not a single real number have nothing to draw
+    let points: Vec<(Vec<f64>, f64)> = table.rows.iter()
+        .filter_map(|row| {
+            let output = row.output.as_ref().ok()?.parse::<f64>().ok()?;
+            let inputs = row.inputs.iter().map(|input| input.parse::<f64>().unwrap()).collect();
+            Some((inputs, output))
+        })
+        .collect();
+
+    if points.is_empty() {
+        return Err(BeanieError::Type(String::from("Can not graph the sweep because none of its outputs is a real number")));
+    }
+
+    // one line per combination of the values of every input after the first, in sweep order
+    let mut series: Vec<(String, Vec<(f64, f64)>)> = Vec::new();
+    for (inputs, output) in &points {
+        let label = sweeps.iter().zip(inputs.iter()).skip(1)
+            .map(|(sweep, value)| format!("{}={}", sweep.input_name, value))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        match series.iter_mut().find(|(existing, _)| *existing == label) {
+            Some((_, line)) => line.push((inputs[0], *output)),
+            None => series.push((label, vec![(inputs[0], *output)])),
+        }
+    }
+
+    let min_x = sweeps[0].values[0];
+    let max_x = *sweeps[0].values.last().unwrap();
+    let min_y = points.iter().map(|(_, output)| *output).fold(f64::INFINITY, f64::min);
+    let max_y = points.iter().map(|(_, output)| *output).fold(f64::NEG_INFINITY, f64::max);
+
+    let mut graph_operation = GraphOperation::new(OUT_COLUMN.to_string(), None);
+    graph_operation.add_argument(MIN_X.to_string(), BeanieExpression::SimpleF64(min_x.floor()))?;
+    graph_operation.add_argument(MAX_X.to_string(), BeanieExpression::SimpleF64(max_x.ceil()))?;
+    graph_operation.add_argument(MIN_Y.to_string(), BeanieExpression::SimpleF64(min_y.floor()))?;
+    graph_operation.add_argument(MAX_Y.to_string(), BeanieExpression::SimpleF64(max_y.ceil()))?;
+    graph_operation.add_argument(STEP_X.to_string(), BeanieExpression::SimpleF64(tick_distance(min_x, max_x)))?;
+    graph_operation.add_argument(STEP_Y.to_string(), BeanieExpression::SimpleF64(tick_distance(min_y, max_y)))?;
+    graph_operation.add_argument(LABEL_X.to_string(), BeanieExpression::String(sweeps[0].input_name.clone()))?;
+    graph_operation.add_argument(LABEL_Y.to_string(), BeanieExpression::String(OUT_COLUMN.to_string()))?;
+
+    let series: Vec<Series> = match sweeps.len() {
+        1 => series.into_iter().map(|(_, points)| (None, points)).collect(),
+        _ => series.into_iter().map(|(label, points)| (Some(label), points)).collect(),
+    };
+
+    let mut threads_to_wait = Vec::new();
+    graph_operation.plot(series, context, &mut threads_to_wait)?;
+
+    operations::join_all(threads_to_wait)
+}
+
+fn tick_distance(min: f64, max: f64) -> f64 {
+    if max > min { (max - min) / 10f64 } else { 1f64 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_keep_the_decimals_of_the_sweep() {
+        let sweep = Sweep::parse("a=0..1:0.1").unwrap();
+        assert_eq!(sweep.input_name, "a");
+        assert_eq!(sweep.values.len(), 11);
+        assert_eq!(sweep.values[3], 0.3);
+
+        let sweep = Sweep::parse("b=-2..2").unwrap();
+        assert_eq!(sweep.values, vec![-2.0, -1.0, 0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn steps_in_exponent_notation_are_not_rounded_away() {
+        let sweep = Sweep::parse("a=0..0.01:1e-3").unwrap();
+        assert_eq!(sweep.values.len(), 11);
+        assert_eq!(sweep.values[3], 0.003);
+
+        let sweep = Sweep::parse("a=0..1:5e-1").unwrap();
+        assert_eq!(sweep.values, vec![0.0, 0.5, 1.0]);
+
+        let sweep = Sweep::parse("a=0..500:2.5e2").unwrap();
+        assert_eq!(sweep.values, vec![0.0, 250.0, 500.0]);
+    }
+
+    #[test]
+    fn sweeps_that_never_end_or_are_malformed_are_rejected() {
+        assert!(matches!(Sweep::parse("a=0..1:0"), Err(BeanieError::Input(_))));
+        assert!(matches!(Sweep::parse("a=1..0"), Err(BeanieError::Input(_))));
+        assert!(matches!(Sweep::parse("a=0..x"), Err(BeanieError::Input(_))));
+        assert!(matches!(Sweep::parse("0..1"), Err(BeanieError::Input(_))));
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use inotify::{Inotify, WatchMask};
+use crate::beanie_interpreter;
+use crate::data::error::{BeanieError, BeanieResult};
+use crate::data::number::NumberType;
+use crate::utilities::{file_utils, logger};
+use crate::CLEANED_OUTPUT;
+
+/// How long to wait after a change before rerunning, editors often write a file in several steps.
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Runs `bn_file_path` and reruns it whenever it or any file it read, such as `use`d files,
+/// changes. A failing run is reported and the watch carries on.
+pub fn run(bn_file_path: String, parameters: Vec<String>, default_data_type: NumberType) -> BeanieResult<()> {
+    loop {
+        clear_terminal();
+        CLEANED_OUTPUT.store(false, Ordering::Relaxed);
+
+        // forget anything read before this run
+        file_utils::take_read_files();
+
+        let result = file_utils::read_file(&bn_file_path)
+            .and_then(|bn_file| beanie_interpreter::run(bn_file_path.clone(), bn_file, parameters.clone(), default_data_type));
+
+        if let Err(err) = result {
+            logger::log_error(err.to_string().as_str());
+        }
+
+        let mut files = file_utils::take_read_files();
+        if !files.contains(&bn_file_path) {
+            files.push(bn_file_path.clone());
+        }
+
+        logger::log_info(format!("\nWatching {} for changes", files.join(", ")).as_str());
+        wait_for_change(&files)?;
+    }
+}
+
+fn clear_terminal() {
+    print!("\x1B[2J\x1B[1;1H");
+}
+
+/// Blocks until one of `files` is written, created or replaced.
+fn wait_for_change(files: &[String]) -> BeanieResult<()> {
+    let mut inotify = Inotify::init()
+        .map_err(|err| BeanieError::Io(format!("Failed to start watching files. Error: {}", err)))?;
+
+    // watching the directories rather than the files themselves also catches editors that save by
+    // replacing the file, and files that do not exist yet
+    let mut directories = HashSet::new();
+    let mut file_names = HashSet::new();
+
+    for file in files {
+        let path = Path::new(file);
+        let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+
+        if let Some(file_name) = path.file_name() {
+            file_names.insert(file_name.to_os_string());
+        }
+
+        if directories.insert(directory.to_path_buf()) {
+            inotify.add_watch(directory, WatchMask::MODIFY | WatchMask::CLOSE_WRITE | WatchMask::CREATE | WatchMask::MOVED_TO)
+                .map_err(|err| BeanieError::Io(format!("Failed to watch {}. Error: {}", directory.display(), err)))?;
+        }
+    }
+
+    let mut buffer = [0u8; 4096];
+    loop {
+        let events = inotify.read_events_blocking(&mut buffer)
+            .map_err(|err| BeanieError::Io(format!("Failed to read file changes. Error: {}", err)))?;
+
+        let changed = events
+            .filter_map(|event| event.name.map(OsString::from))
+            .any(|name| file_names.contains(&name));
+
+        if changed {
+            thread::sleep(SETTLE_TIME);
+            return Ok(());
+        }
+    }
+}
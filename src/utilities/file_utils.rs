@@ -1,12 +1,21 @@
+use std::collections::BTreeSet;
 use std::fs;
-use crate::utilities::logger;
-
-pub fn read_file(file_path: &str) -> String {
-    match fs::read_to_string(file_path) {
-        Ok(content) => content,
-        Err(_) => {
-            logger::log_error(format!("Failed to read file {}", file_path).as_str());
-            unreachable!()
-        }
-    }
+use std::sync::Mutex;
+use crate::data::error::{BeanieError, BeanieResult};
+
+lazy_static! {
+    static ref READ_FILES: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+}
+
+pub fn read_file(file_path: &str) -> BeanieResult<String> {
+    READ_FILES.lock().unwrap().insert(file_path.to_string());
+
+    fs::read_to_string(file_path)
+        .map_err(|err| BeanieError::Io(format!("Failed to read file {}. Error: {}", file_path, err)))
+}
+
+/// Every file passed to [`read_file`] since the last call, so callers can tell which files a run
+/// depended on.
+pub fn take_read_files() -> Vec<String> {
+    std::mem::take(&mut *READ_FILES.lock().unwrap()).into_iter().collect()
 }
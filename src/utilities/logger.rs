@@ -1,9 +1,9 @@
 use colored::Colorize;
 
 pub fn log_error(text: &str) {
-    panic!("{}", text.red())
+    eprintln!("{}", text.red())
 }
 
 pub fn log_info(text: &str) {
     println!("{}", text)
-}
\ No newline at end of file
+}